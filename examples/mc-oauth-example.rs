@@ -17,7 +17,7 @@ fn main() -> Result<()> {
     // Initialize logger
     env_logger::init();
 
-    let authenticator = MinecraftAuthenticator::from_compile_env();
+    let authenticator = MinecraftAuthenticator::from_compile_env()?;
 
     // Step 1-2: Start device flow and wait for user authorization
     let device_flow_state = authenticator.start_device_flow()?;
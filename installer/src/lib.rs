@@ -9,7 +9,9 @@
 //! - **Concurrent Downloads**: Download multiple files simultaneously with configurable concurrency
 //! - **Progress Tracking**: Visual progress bars showing download status and progress
 //! - **Integrity Verification**: SHA1 hash verification to ensure file integrity
-//! - **Retry Logic**: Automatic retries for failed downloads (up to 5 attempts with 3 second delay)
+//! - **Retry Logic**: Automatic retries for network errors (up to 5 attempts with 10 second delay)
+//! - **Mirror Failover**: Fallback URLs are tried in order when a download fails or has a wrong
+//!   hash, and hosts that keep failing are skipped for the rest of the run
 //! - **Incremental Updates**: Skip downloading files that already exist with matching hashes
 //! - **Error Handling**: Comprehensive error handling for network and filesystem operations
 //!
@@ -35,18 +37,20 @@
 //!             sha1: Some("abc123...".to_string()),
 //!             save_file: PathBuf::from("./mods/mod1.jar"),
 //!             message: "Downloading mod1.jar".to_string(),
+//!             fallback_urls: Vec::new(),
 //!         },
 //!         InstallTask {
 //!             url: "https://example.com/mod2.jar".to_string(),
 //!             sha1: Some("def456...".to_string()),
 //!             save_file: PathBuf::from("./mods/mod2.jar"),
 //!             message: "Downloading mod2.jar".to_string(),
+//!             fallback_urls: Vec::new(),
 //!         },
 //!     ]);
 //!
 //!     // Create task pool and install all files
 //!     let pool = TaskPool::from(tasks);
-//!     pool.install();
+//!     pool.install()?;
 //!
 //!     Ok(())
 //! }
@@ -66,13 +70,21 @@
 //! When `sha1` is provided:
 //! - Downloaded files are verified against the expected hash
 //! - Existing files are verified before being skipped
-//! - Downloads are retried if hash verification fails
+//! - A download with a wrong hash is not retried, the next mirror is tried instead
+//!
+//! ## Mirror Failover
+//!
+//! Each task has a primary `url` and optional `fallback_urls`, usually the same
+//! file on other mirrors. A URL is given up after its retries are exhausted, or
+//! at once if it serves data with a wrong hash, and the next one is tried. Every host that fails [`MAX_HOST_FAILURES`] downloads
+//! in a row is skipped by all later downloads of the process, so a dead mirror
+//! only costs time once.
 //!
 //! ## Concurrent Execution
 //!
 //! - Files are downloaded concurrently (64 parallel downloads by default)
 //! - Progress is updated in real-time for each task
-//! - Errors in individual tasks will cause the entire installation to panic
+//! - A task failing on all of its URLs stops the installation with an error
 //!
 //! # Error Handling
 //!
@@ -95,15 +107,17 @@
 //! [00:01:23] ##############------------------------ 3/10 Downloading mod3.jar
 //! ```
 
-use futures::stream::{self, StreamExt};
+use futures::stream::{self, StreamExt, TryStreamExt};
 use indicatif::{ProgressBar, ProgressStyle};
 use log::warn;
 use reqwest::header;
 use sha1::{Digest, Sha1};
 use std::cmp::Ordering;
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
 use std::fs;
 use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard, PoisonError};
 use std::time::Duration;
 
 /// Defines interface for file installation operations with async execution and progress tracking.
@@ -136,6 +150,7 @@ pub trait FileInstall {
     ///     sha1: None,
     ///     save_file: PathBuf::from("./file.jar"),
     ///     message: "Installing file.jar".to_string(),
+    ///     fallback_urls: Vec::new(),
     /// };
     /// let bar = ProgressBar::new(1);
     /// task.bar_update(&bar);
@@ -204,7 +219,7 @@ where
 /// Represents a file download and installation task.
 ///
 /// If `sha1` is provided, verifies integrity and skips download if file exists
-/// with matching hash.
+/// with matching hash. `fallback_urls` are tried in order when `url` fails.
 ///
 /// # Example
 /// ```no_run
@@ -216,6 +231,7 @@ where
 ///     sha1: Some("abc123...".to_string()),
 ///     save_file: PathBuf::from("./mods/mod.jar"),
 ///     message: "Downloading mod.jar".to_string(),
+///     fallback_urls: vec!["https://mirror.example.com/mod.jar".to_string()],
 /// };
 /// ```
 #[derive(Debug, Default, Clone, PartialEq)]
@@ -228,13 +244,120 @@ pub struct InstallTask {
     pub save_file: PathBuf,
     /// Message to display in the progress bar during installation.
     pub message: String,
+    /// URLs of the same file on other mirrors, tried in order when `url` fails.
+    pub fallback_urls: Vec<String>,
+}
+
+/// Number of downloads in a row a host may fail before it is skipped.
+pub const MAX_HOST_FAILURES: u32 = 3;
+
+/// Failed downloads in a row keyed by host, shared by all tasks of the process.
+static HOST_FAILURES: Mutex<BTreeMap<String, u32>> = Mutex::new(BTreeMap::new());
+
+/// Returns the host of a URL, or the URL itself if it has none.
+fn host_of(url: &str) -> String {
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_owned))
+        .unwrap_or_else(|| url.to_owned())
+}
+
+/// Returns the failure counters, even if another task panicked while holding them.
+fn host_failures() -> MutexGuard<'static, BTreeMap<String, u32>> {
+    HOST_FAILURES.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Returns whether the host of a URL failed too often to be tried again.
+///
+/// # Example
+/// ```
+/// use installer::{host_is_dead, record_host_failure, MAX_HOST_FAILURES};
+///
+/// for _ in 0..MAX_HOST_FAILURES {
+///     record_host_failure("https://down.example.com/a.jar");
+/// }
+/// assert!(host_is_dead("https://down.example.com/b.jar"));
+/// assert!(!host_is_dead("https://up.example.com/a.jar"));
+/// ```
+#[must_use]
+pub fn host_is_dead(url: &str) -> bool {
+    host_failures()
+        .get(&host_of(url))
+        .is_some_and(|failures| *failures >= MAX_HOST_FAILURES)
+}
+
+/// Records a failed download from the host of a URL.
+pub fn record_host_failure(url: &str) {
+    let host = host_of(url);
+    let mut failures = host_failures();
+    let count = failures.entry(host.clone()).or_default();
+    *count += 1;
+    if *count == MAX_HOST_FAILURES {
+        warn!("{host} failed {count} downloads in a row, skipping it for the rest of this run");
+    }
+}
+
+/// Records a successful download, resetting the failures of the host of a URL.
+pub fn record_host_success(url: &str) {
+    host_failures().remove(&host_of(url));
+}
+
+/// Error of a download whose data does not match the expected SHA1.
+///
+/// Retrying the same URL would return the same data, so downloads failing
+/// with this error move on to the next mirror at once.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sha1Mismatch {
+    /// The URL the data was downloaded from.
+    pub url: String,
+    /// The expected SHA1 hash.
+    pub sha1: String,
+}
+
+impl fmt::Display for Sha1Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "sha1 of {} does not match {}", self.url, self.sha1)
+    }
+}
+
+impl std::error::Error for Sha1Mismatch {}
+
+/// Downloads a URL once, verifying the data against `sha1` if provided.
+///
+/// # Errors
+/// Returns a [`Sha1Mismatch`] error if the data does not match `sha1`.
+async fn fetch_once(
+    client: &reqwest::Client,
+    url: &str,
+    sha1: Option<&String>,
+) -> anyhow::Result<bytes::Bytes> {
+    let data = client
+        .get(url)
+        .header(header::USER_AGENT, "github.com/funny233-github/MCLauncher")
+        .timeout(Duration::from_secs(1000))
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+    if let Some(sha1) = sha1 {
+        if !data.sha1_cmp(sha1).is_eq() {
+            return Err(Sha1Mismatch {
+                url: url.to_owned(),
+                sha1: sha1.clone(),
+            }
+            .into());
+        }
+    }
+    Ok(data)
 }
 
 /// Downloads bytes from a URL with retry logic and optional SHA1 verification.
 ///
 /// Makes up to `retry_num` attempts to download the file, waiting `sleep_time`
 /// between retries. Times out after 128 seconds per attempt. If `sha1` is provided,
-/// verifies the downloaded data against the hash.
+/// verifies the downloaded data against the hash; a mismatch is returned as a
+/// [`Sha1Mismatch`] error without retrying.
 ///
 /// # Example
 /// ```no_run
@@ -266,30 +389,86 @@ pub async fn fetch_bytes(
     retry_num: u32,
 ) -> anyhow::Result<bytes::Bytes> {
     let client = reqwest::Client::new();
-    for _ in 0..retry_num {
-        let send = client
-            .get(url)
-            .header(header::USER_AGENT, "github.com/funny233-github/MCLauncher")
-            .timeout(Duration::from_secs(1000))
-            .send()
-            .await;
+    fetch_with_retry(&client, url, sha1, sleep_time, retry_num).await
+}
 
-        // There must handle Result for 'for loop'
-        let data = if let Ok(send) = send {
-            send.bytes().await
-        } else {
-            continue;
-        };
+/// Downloads a URL, retrying network errors up to `retry_num` times.
+async fn fetch_with_retry(
+    client: &reqwest::Client,
+    url: &str,
+    sha1: Option<&String>,
+    sleep_time: Duration,
+    retry_num: u32,
+) -> anyhow::Result<bytes::Bytes> {
+    let mut error = anyhow::anyhow!("download {url} fail");
+    for attempt in 1..=retry_num {
+        match fetch_once(client, url, sha1).await {
+            Ok(data) => return Ok(data),
+            Err(e) if e.is::<Sha1Mismatch>() => return Err(e),
+            Err(e) => error = e,
+        }
+        if attempt < retry_num {
+            warn!("install {url} fail, then retry");
+            tokio::time::sleep(sleep_time).await;
+        }
+    }
+    Err(error.context(format!("download {url} fail")))
+}
 
-        if let Ok(data) = data {
-            if sha1.is_none() || data.sha1_cmp(sha1.unwrap()).is_eq() {
+/// Downloads bytes from the first working URL of a list of mirrors.
+///
+/// Each URL is tried like [`fetch_bytes`]; when its attempts are exhausted, or
+/// right away when the data does not match `sha1`, the next URL is tried. URLs whose host
+/// failed [`MAX_HOST_FAILURES`] downloads in a row are skipped, except the
+/// last URL, which is always tried so a flaky network cannot rule out every
+/// mirror.
+///
+/// # Example
+/// ```no_run
+/// use installer::fetch_bytes_from;
+/// use std::time::Duration;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let data = fetch_bytes_from(
+///         &["https://bmclapi2.bangbang93.com/maven/a.jar", "https://libraries.minecraft.net/a.jar"],
+///         None,
+///         Duration::from_secs(10),
+///         5,
+///     ).await?;
+///     println!("Downloaded {} bytes", data.len());
+///     Ok(())
+/// }
+/// ```
+///
+/// # Errors
+/// Returns an error if every URL fails or is skipped, with the last failure as its cause.
+pub async fn fetch_bytes_from(
+    urls: &[&str],
+    sha1: Option<&String>,
+    sleep_time: Duration,
+    retry_num: u32,
+) -> anyhow::Result<bytes::Bytes> {
+    let client = reqwest::Client::new();
+    let mut error = None;
+    for (index, url) in urls.iter().enumerate() {
+        if host_is_dead(url) && index + 1 < urls.len() {
+            continue;
+        }
+        match fetch_with_retry(&client, url, sha1, sleep_time, retry_num).await {
+            Ok(data) => {
+                record_host_success(url);
                 return Ok(data);
             }
+            Err(e) => {
+                record_host_failure(url);
+                warn!("{e:#}, trying the next mirror");
+                error = Some(e);
+            }
         }
-        warn!("install {url} fail, then retry");
-        tokio::time::sleep(sleep_time).await;
     }
-    Err(anyhow::anyhow!("download {url} fail"))
+    let error = error.unwrap_or_else(|| anyhow::anyhow!("no URL to download from"));
+    Err(error.context(format!("download {} fail on all mirrors", urls.join(", "))))
 }
 
 impl FileInstall for InstallTask {
@@ -297,10 +476,12 @@ impl FileInstall for InstallTask {
     ///
     /// If `sha1` is `None`, always downloads the file. If `sha1` is `Some`,
     /// skips download if file exists with matching hash. Downloads with retry logic
-    /// (5 attempts, 10 second delay) and creates parent directories if needed.
+    /// (5 attempts, 10 second delay) from `url`, then from each of `fallback_urls`,
+    /// moving on without retrying when a URL serves data with a wrong hash, and
+    /// creates parent directories if needed.
     ///
     /// # Errors
-    /// Returns an error if download fails after all retry attempts, filesystem permissions
+    /// Returns an error if download fails on every URL, filesystem permissions
     /// prevent directory creation or file writing, or SHA1 verification fails.
     ///
     /// # Panics
@@ -312,8 +493,12 @@ impl FileInstall for InstallTask {
                     .sha1_cmp(self.sha1.as_ref().unwrap())
                     .is_eq())
         {
+            let urls: Vec<&str> = std::iter::once(&self.url)
+                .chain(&self.fallback_urls)
+                .map(String::as_str)
+                .collect();
             let data =
-                fetch_bytes(&self.url, self.sha1.as_ref(), Duration::from_secs(10), 5).await?;
+                fetch_bytes_from(&urls, self.sha1.as_ref(), Duration::from_secs(10), 5).await?;
             fs::create_dir_all(self.save_file.parent().unwrap())?;
             fs::write(&self.save_file, data)?;
         }
@@ -331,8 +516,8 @@ impl FileInstall for InstallTask {
 
 /// A pool of installation tasks that executes concurrently with progress tracking.
 ///
-/// Tasks are executed with configurable concurrency (64 by default). Errors in
-/// individual tasks cause the entire installation to panic.
+/// Tasks are executed with configurable concurrency (64 by default). A task
+/// failing on all of its URLs stops the installation with an error.
 ///
 /// # Example
 /// ```no_run
@@ -340,18 +525,20 @@ impl FileInstall for InstallTask {
 /// use std::collections::VecDeque;
 /// use std::path::PathBuf;
 ///
-/// fn main() {
+/// fn main() -> anyhow::Result<()> {
 ///     let tasks = VecDeque::from(vec![
 ///         InstallTask {
 ///             url: "https://example.com/mod1.jar".to_string(),
 ///             sha1: Some("abc123...".to_string()),
 ///             save_file: PathBuf::from("./mods/mod1.jar"),
 ///             message: "Downloading mod1.jar".to_string(),
+///             fallback_urls: Vec::new(),
 ///         },
 ///     ]);
 ///
 ///     let pool = TaskPool::from(tasks);
-///     pool.install(); // Blocks until all tasks complete
+///     pool.install()?; // Blocks until all tasks complete
+///     Ok(())
 /// }
 /// ```
 #[derive(Debug, Clone)]
@@ -408,33 +595,42 @@ where
     ///         sha1: Some("abc123...".to_string()),
     ///         save_file: PathBuf::from("./mods/mod1.jar"),
     ///         message: "Downloading mod1.jar".to_string(),
+    ///         fallback_urls: Vec::new(),
     ///     },
     ///     InstallTask {
     ///         url: "https://example.com/mod2.jar".to_string(),
     ///         sha1: Some("def456...".to_string()),
     ///         save_file: PathBuf::from("./mods/mod2.jar"),
     ///         message: "Downloading mod2.jar".to_string(),
+    ///         fallback_urls: Vec::new(),
     ///     },
     /// ]);
     ///
     /// let pool = TaskPool::from(tasks);
-    /// pool.install(); // Blocks until all tasks complete
+    /// pool.install()?; // Blocks until all tasks complete
+    /// # Ok::<(), anyhow::Error>(())
     /// ```
     ///
+    /// # Errors
+    /// Returns the error of the first task whose `install` method fails; the
+    /// remaining tasks are cancelled.
+    ///
     /// # Panics
-    /// Panics if any task's `install` method returns an error or progress bar update fails.
+    /// Panics if the tokio runtime cannot be created.
     #[tokio::main(flavor = "current_thread")]
-    pub async fn install(self) {
+    pub async fn install(self) -> anyhow::Result<()> {
         let tasks = self.pool.into_iter().map(|x| {
             let share = self.bar.clone();
             async move {
-                x.install().await.unwrap();
+                x.install().await?;
                 x.bar_update(&share);
+                anyhow::Ok(())
             }
         });
         stream::iter(tasks)
             .buffer_unordered(64)
-            .collect::<VecDeque<_>>()
-            .await;
+            .try_collect::<VecDeque<_>>()
+            .await?;
+        Ok(())
     }
 }
@@ -42,8 +42,16 @@
 //!
 //! # Environment Variables
 //!
-//! For production builds, you can use `MinecraftAuthenticator::from_compile_env()` which
-//! requires the `AZURE_CLIENT_ID` environment variable to be set at compile time.
+//! For production builds, you can use `MinecraftAuthenticator::from_compile_env()`, which
+//! uses the `AZURE_CLIENT_ID` environment variable set at compile time, if any.
+//! `MinecraftAuthenticator::from_env()` lets `AZURE_CLIENT_ID` override it at runtime.
+//! Both return an error when no client ID is available, so the crate builds without it.
+//!
+//! # Endpoints
+//!
+//! The service URLs are taken from `AuthEndpoints`, which defaults to the official
+//! Microsoft, Xbox Live and Minecraft services. `MinecraftAuthenticator::with_endpoints`
+//! points the whole chain at other URLs, for example a local mock server in tests.
 //!
 //! # Features
 //!
@@ -60,6 +68,75 @@ use serde_json::json;
 use std::thread;
 use std::time::Duration;
 
+/// Environment variable overriding the Azure client ID at runtime, see
+/// `MinecraftAuthenticator::from_env`.
+pub const ENV_CLIENT_ID: &str = "AZURE_CLIENT_ID";
+
+/// Azure client ID embedded from `AZURE_CLIENT_ID` at compile time, if it was set.
+pub const BUILT_IN_CLIENT_ID: Option<&str> = option_env!("AZURE_CLIENT_ID");
+
+/// URLs of the services taking part in the authentication.
+///
+/// `Default` returns the official Microsoft, Xbox Live and Minecraft services.
+/// Other URLs point the authenticator at a proxy or a mock server.
+///
+/// # Example
+/// ```
+/// use mc_oauth::AuthEndpoints;
+///
+/// let endpoints = AuthEndpoints::with_base("http://127.0.0.1:8080/");
+/// assert_eq!(endpoints.xsts, "http://127.0.0.1:8080/xsts/authorize");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct AuthEndpoints {
+    /// Microsoft OAuth device code endpoint.
+    pub device_code: String,
+    /// Microsoft OAuth token endpoint, used for polling and refreshing.
+    pub token: String,
+    /// Xbox Live user authentication endpoint.
+    pub xbox_live: String,
+    /// XSTS authorization endpoint.
+    pub xsts: String,
+    /// Minecraft services login endpoint.
+    pub minecraft_login: String,
+    /// Minecraft profile endpoint.
+    pub minecraft_profile: String,
+}
+
+impl Default for AuthEndpoints {
+    /// Returns the official service URLs.
+    fn default() -> Self {
+        Self {
+            device_code: "https://login.microsoftonline.com/consumers/oauth2/v2.0/devicecode"
+                .into(),
+            token: "https://login.microsoftonline.com/consumers/oauth2/v2.0/token".into(),
+            xbox_live: "https://user.auth.xboxlive.com/user/authenticate".into(),
+            xsts: "https://xsts.auth.xboxlive.com/xsts/authorize".into(),
+            minecraft_login: "https://api.minecraftservices.com/authentication/login_with_xbox"
+                .into(),
+            minecraft_profile: "https://api.minecraftservices.com/minecraft/profile".into(),
+        }
+    }
+}
+
+impl AuthEndpoints {
+    /// Returns endpoints with the paths of the official services under one base URL.
+    ///
+    /// Useful for a mock server or a reverse proxy serving every service.
+    #[must_use]
+    pub fn with_base(base: &str) -> Self {
+        let base = base.trim_end_matches('/');
+        Self {
+            device_code: format!("{base}/consumers/oauth2/v2.0/devicecode"),
+            token: format!("{base}/consumers/oauth2/v2.0/token"),
+            xbox_live: format!("{base}/user/authenticate"),
+            xsts: format!("{base}/xsts/authorize"),
+            minecraft_login: format!("{base}/authentication/login_with_xbox"),
+            minecraft_profile: format!("{base}/minecraft/profile"),
+        }
+    }
+}
+
 /// Minecraft OAuth authenticator for handling Microsoft device code flow.
 ///
 /// Manages the complete authentication process for Minecraft using Microsoft's OAuth 2.0
@@ -67,6 +144,8 @@ use std::time::Duration;
 pub struct MinecraftAuthenticator {
     /// Azure application client ID for OAuth authentication.
     client_id: String,
+    /// URLs of the services used by the authentication chain.
+    endpoints: AuthEndpoints,
 }
 
 impl MinecraftAuthenticator {
@@ -86,9 +165,40 @@ impl MinecraftAuthenticator {
     pub fn new(client_id: &str) -> Self {
         Self {
             client_id: client_id.into(),
+            endpoints: AuthEndpoints::default(),
         }
     }
 
+    /// Uses other service URLs, e.g. a local mock server.
+    ///
+    /// The endpoints are passed on to every state of the authentication chain.
+    ///
+    /// # Example
+    /// ```
+    /// use mc_oauth::{AuthEndpoints, MinecraftAuthenticator};
+    ///
+    /// let authenticator = MinecraftAuthenticator::new("your_client_id")
+    ///     .with_endpoints(AuthEndpoints::with_base("http://127.0.0.1:8080"));
+    /// assert_eq!(authenticator.endpoints().token, "http://127.0.0.1:8080/consumers/oauth2/v2.0/token");
+    /// ```
+    #[must_use]
+    pub fn with_endpoints(mut self, endpoints: AuthEndpoints) -> Self {
+        self.endpoints = endpoints;
+        self
+    }
+
+    /// Returns the Azure client ID.
+    #[must_use]
+    pub fn client_id(&self) -> &str {
+        &self.client_id
+    }
+
+    /// Returns the service URLs.
+    #[must_use]
+    pub fn endpoints(&self) -> &AuthEndpoints {
+        &self.endpoints
+    }
+
     /// Initiates the OAuth device code flow by requesting a device code from Microsoft.
     ///
     /// Requests a device code and user verification instructions from Microsoft's OAuth endpoint.
@@ -120,7 +230,7 @@ impl MinecraftAuthenticator {
         });
         let client = reqwest::blocking::Client::new();
         let res = client
-            .post("https://login.microsoftonline.com/consumers/oauth2/v2.0/devicecode")
+            .post(&self.endpoints.device_code)
             .form(&param)
             .send()?;
         trace!("device code response: {res:#?}");
@@ -128,6 +238,7 @@ impl MinecraftAuthenticator {
         Ok(DeviceFlowState {
             initial_response,
             client_id: self.client_id.clone(),
+            endpoints: self.endpoints.clone(),
         })
     }
 
@@ -224,7 +335,7 @@ impl MinecraftAuthenticator {
         });
         let client = reqwest::blocking::Client::new();
         let res = client
-            .post("https://login.microsoftonline.com/consumers/oauth2/v2.0/token")
+            .post(&self.endpoints.token)
             .form(&param)
             .timeout(Duration::from_secs(10))
             .send()?;
@@ -245,35 +356,96 @@ impl MinecraftAuthenticator {
 
         Ok(TokenState {
             token_data: res.json::<TokenResponse>()?,
+            endpoints: self.endpoints.clone(),
         })
     }
 }
 
 impl MinecraftAuthenticator {
-    /// Creates a Minecraft OAuth authenticator using the `AZURE_CLIENT_ID` environment variable.
+    /// Creates a Minecraft OAuth authenticator using the client ID embedded at compile time.
     ///
-    /// Reads the `AZURE_CLIENT_ID` environment variable at **compile time** and embeds
-    /// it in the binary. This is useful for production builds where the client ID should
-    /// not be configurable at runtime.
+    /// The client ID is the `AZURE_CLIENT_ID` environment variable at **compile time**,
+    /// see `BUILT_IN_CLIENT_ID`. This is useful for production builds where the client
+    /// ID should not be configurable at runtime.
     ///
     /// # Example
     /// ```
     /// // Set AZURE_CLIENT_ID before compilation: export AZURE_CLIENT_ID="your_client_id"
-    /// use mc_oauth::MinecraftAuthenticator;
+    /// use mc_oauth::{MinecraftAuthenticator, BUILT_IN_CLIENT_ID};
     ///
     /// let authenticator = MinecraftAuthenticator::from_compile_env();
+    /// assert_eq!(authenticator.is_ok(), BUILT_IN_CLIENT_ID.is_some_and(|x| !x.is_empty()));
     /// ```
     ///
-    /// # Panics
-    /// Panics if the `AZURE_CLIENT_ID` environment variable is not set at compile time.
-    #[must_use]
-    pub fn from_compile_env() -> Self {
-        Self {
-            client_id: env!("AZURE_CLIENT_ID").to_string(),
-        }
+    /// # Errors
+    /// Returns an error if `AZURE_CLIENT_ID` was not set at compile time.
+    pub fn from_compile_env() -> Result<Self> {
+        BUILT_IN_CLIENT_ID
+            .filter(|client_id| !client_id.is_empty())
+            .map(Self::new)
+            .ok_or_else(no_client_id)
+    }
+
+    /// Creates a Minecraft OAuth authenticator using the `AZURE_CLIENT_ID` set at runtime.
+    ///
+    /// Falls back to the client ID embedded at compile time, see `from_compile_env`,
+    /// when the variable is unset or empty. This lets users of a prebuilt binary log in
+    /// with their own Azure application.
+    ///
+    /// # Example
+    /// ```
+    /// use mc_oauth::MinecraftAuthenticator;
+    ///
+    /// if let Ok(authenticator) = MinecraftAuthenticator::from_env() {
+    ///     assert!(!authenticator.client_id().is_empty());
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if `AZURE_CLIENT_ID` is set neither at runtime nor at compile time.
+    pub fn from_env() -> Result<Self> {
+        Self::from_env_or(None)
+    }
+
+    /// Creates a Minecraft OAuth authenticator, taking the client ID from the first
+    /// source that has one.
+    ///
+    /// The sources are the `AZURE_CLIENT_ID` environment variable at runtime, then
+    /// `fallback`, e.g. a client ID from a configuration file, then the client ID
+    /// embedded at compile time. Empty client IDs are skipped.
+    ///
+    /// # Example
+    /// ```
+    /// use mc_oauth::MinecraftAuthenticator;
+    ///
+    /// let authenticator = MinecraftAuthenticator::from_env_or(Some("my-client-id"))?;
+    /// # if std::env::var("AZURE_CLIENT_ID").is_err() {
+    /// assert_eq!(authenticator.client_id(), "my-client-id");
+    /// # }
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if none of the sources has a client ID.
+    pub fn from_env_or(fallback: Option<&str>) -> Result<Self> {
+        let runtime = std::env::var(ENV_CLIENT_ID).ok();
+        [runtime.as_deref(), fallback, BUILT_IN_CLIENT_ID]
+            .into_iter()
+            .flatten()
+            .find(|client_id| !client_id.is_empty())
+            .map(Self::new)
+            .ok_or_else(no_client_id)
     }
 }
 
+/// Returns the error for a missing Azure client ID.
+fn no_client_id() -> anyhow::Error {
+    anyhow!(
+        "no Azure client ID is configured, set the {ENV_CLIENT_ID} environment variable \
+         or build with {ENV_CLIENT_ID} set"
+    )
+}
+
 /// Response from Microsoft's device code endpoint.
 ///
 /// Contains verification information for OAuth device code flow.
@@ -302,6 +474,8 @@ pub struct DeviceFlowState {
     pub initial_response: DeviceCodeResponse,
     /// Azure client ID for OAuth authentication.
     pub client_id: String,
+    /// URLs of the services used by the authentication chain.
+    pub endpoints: AuthEndpoints,
 }
 
 impl DeviceFlowState {
@@ -351,15 +525,13 @@ impl DeviceFlowState {
                 "device_code": self.initial_response.device_code,
             });
 
-            let res = client
-                .post("https://login.microsoftonline.com/consumers/oauth2/v2.0/token")
-                .form(&param)
-                .send()?;
+            let res = client.post(&self.endpoints.token).form(&param).send()?;
 
             if res.status().is_success() {
                 trace!("token response: {res:#?}");
                 return Ok(TokenState {
                     token_data: res.json::<TokenResponse>()?,
+                    endpoints: self.endpoints.clone(),
                 });
             }
             let error_response: TokenErrorResponse = res.json()?;
@@ -422,6 +594,8 @@ struct TokenErrorResponse {
 pub struct TokenState {
     /// OAuth token response with access and refresh tokens.
     pub token_data: TokenResponse,
+    /// URLs of the services used by the authentication chain.
+    pub endpoints: AuthEndpoints,
 }
 
 impl TokenState {
@@ -463,7 +637,7 @@ impl TokenState {
 
         let client = reqwest::blocking::Client::new();
         let res = client
-            .post("https://user.auth.xboxlive.com/user/authenticate")
+            .post(&self.endpoints.xbox_live)
             .json(&auth_request)
             .header("x-xbl-contract-version", "1")
             .send()?;
@@ -471,7 +645,10 @@ impl TokenState {
         trace!("Xbox Live auth response: {res:#?}");
         let xbox_auth_data = res.json::<XboxLiveAuthResponse>()?;
 
-        Ok(XboxLiveAuthState { xbox_auth_data })
+        Ok(XboxLiveAuthState {
+            xbox_auth_data,
+            endpoints: self.endpoints.clone(),
+        })
     }
 }
 
@@ -516,6 +693,8 @@ pub struct XuiClaim {
 pub struct XboxLiveAuthState {
     /// Xbox Live authentication response.
     pub xbox_auth_data: XboxLiveAuthResponse,
+    /// URLs of the services used by the authentication chain.
+    pub endpoints: AuthEndpoints,
 }
 
 impl XboxLiveAuthState {
@@ -562,7 +741,7 @@ impl XboxLiveAuthState {
 
         let client = reqwest::blocking::Client::new();
         let res = client
-            .post("https://xsts.auth.xboxlive.com/xsts/authorize")
+            .post(&self.endpoints.xsts)
             .json(&auth_request)
             .header("x-xbl-contract-version", "1")
             .send()?;
@@ -587,6 +766,7 @@ impl XboxLiveAuthState {
 
         Ok(XSTSAuthState {
             xsts_token_data: res.json::<XSTSAuthResponse>()?,
+            endpoints: self.endpoints.clone(),
         })
     }
 
@@ -626,6 +806,8 @@ pub struct XSTSAuthResponse {
 pub struct XSTSAuthState {
     /// XSTS authentication response.
     pub xsts_token_data: XSTSAuthResponse,
+    /// URLs of the services used by the authentication chain.
+    pub endpoints: AuthEndpoints,
 }
 
 impl XSTSAuthState {
@@ -662,7 +844,7 @@ impl XSTSAuthState {
 
         let client = reqwest::blocking::Client::new();
         let res = client
-            .post("https://api.minecraftservices.com/authentication/login_with_xbox")
+            .post(&self.endpoints.minecraft_login)
             .json(&auth_request)
             .send()?;
 
@@ -673,6 +855,7 @@ impl XSTSAuthState {
 
         Ok(MinecraftAuthState {
             minecraft_token_data: res.json::<MinecraftAuthResponse>()?,
+            endpoints: self.endpoints.clone(),
         })
     }
 }
@@ -701,6 +884,8 @@ pub struct MinecraftAuthResponse {
 pub struct MinecraftAuthState {
     /// Minecraft authentication response.
     pub minecraft_token_data: MinecraftAuthResponse,
+    /// URLs of the services used by the authentication chain.
+    pub endpoints: AuthEndpoints,
 }
 
 impl MinecraftAuthState {
@@ -732,7 +917,7 @@ impl MinecraftAuthState {
     pub fn fetch_minecraft_profile(&self) -> Result<MinecraftProfile> {
         let client = reqwest::blocking::Client::new();
         let res = client
-            .get("https://api.minecraftservices.com/minecraft/profile")
+            .get(&self.endpoints.minecraft_profile)
             .header(
                 "Authorization",
                 format!("Bearer {}", self.minecraft_token_data.access_token),
@@ -812,3 +997,190 @@ pub struct MinecraftAuth {
     /// Player's Minecraft profile with UUID, username, and skin data.
     pub profile: MinecraftProfile,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+
+    /// Serves scripted responses on a local port, one connection per response.
+    ///
+    /// Returns the base URL and a handle yielding the path and body of every request.
+    fn mock_server(
+        responses: Vec<(u16, serde_json::Value)>,
+    ) -> (String, thread::JoinHandle<Vec<(String, String)>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            let mut requests = Vec::new();
+            for (status, body) in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let path = line.split(' ').nth(1).unwrap().to_owned();
+                let mut length = 0;
+                loop {
+                    line.clear();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':')
+                        && name.eq_ignore_ascii_case("content-length")
+                    {
+                        length = value.trim().parse().unwrap();
+                    }
+                }
+                let mut request = vec![0; length];
+                reader.read_exact(&mut request).unwrap();
+                requests.push((path, String::from_utf8(request).unwrap()));
+
+                let body = body.to_string();
+                write!(
+                    stream,
+                    "HTTP/1.1 {status} Mock\r\nContent-Type: application/json\r\n\
+                     Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                )
+                .unwrap();
+            }
+            requests
+        });
+        (base, handle)
+    }
+
+    /// Returns an Xbox Live or XSTS response carrying `token`.
+    fn xbox_response(token: &str) -> serde_json::Value {
+        json!({
+            "IssueInstant": "2026-01-01T00:00:00Z",
+            "NotAfter": "2026-01-02T00:00:00Z",
+            "Token": token,
+            "DisplayClaims": { "xui": [{ "uhs": "userhash" }] },
+        })
+    }
+
+    /// Returns a Microsoft token response with the given refresh token.
+    fn token_response(refresh_token: &str) -> serde_json::Value {
+        json!({
+            "token_type": "Bearer",
+            "scope": "XboxLive.signin offline_access",
+            "expires_in": 3600,
+            "access_token": "microsoft-token",
+            "refresh_token": refresh_token,
+        })
+    }
+
+    /// Verifies the order of the client ID sources and the error when none is set.
+    #[test]
+    fn test_client_id_sources() {
+        if std::env::var(ENV_CLIENT_ID).is_ok() {
+            return;
+        }
+        let authenticator = MinecraftAuthenticator::from_env_or(Some("config-id")).unwrap();
+        assert_eq!(authenticator.client_id(), "config-id");
+        let built_in = BUILT_IN_CLIENT_ID.filter(|client_id| !client_id.is_empty());
+        match MinecraftAuthenticator::from_env_or(Some("")) {
+            Ok(authenticator) => assert_eq!(Some(authenticator.client_id()), built_in),
+            Err(e) => {
+                assert!(built_in.is_none());
+                assert!(e.to_string().contains(ENV_CLIENT_ID));
+            }
+        }
+    }
+
+    /// Runs the whole device code chain against a mock server.
+    #[test]
+    fn test_authenticate_with_mock_endpoints() {
+        let (base, server) = mock_server(vec![
+            (
+                200,
+                json!({
+                    "device_code": "device",
+                    "user_code": "ABCD-EFGH",
+                    "verification_uri": "https://example.com/link",
+                    "expires_in": 10,
+                    "interval": 1,
+                    "message": "Enter ABCD-EFGH",
+                }),
+            ),
+            (400, json!({ "error": "authorization_pending" })),
+            (200, token_response("refresh-1")),
+            (200, xbox_response("xbox-token")),
+            (200, xbox_response("xsts-token")),
+            (
+                200,
+                json!({
+                    "username": "00000000-0000-0000-0000-000000000000",
+                    "roles": [],
+                    "access_token": "minecraft-token",
+                    "token_type": "Bearer",
+                    "expires_in": 86400,
+                }),
+            ),
+            (
+                200,
+                json!({ "id": "069a79f444e94726a5befca90e38aaf5", "name": "Notch", "skins": [] }),
+            ),
+        ]);
+        let auth = MinecraftAuthenticator::new("client")
+            .with_endpoints(AuthEndpoints::with_base(&base))
+            .authenticate()
+            .unwrap();
+        assert_eq!(auth.access_token, "minecraft-token");
+        assert_eq!(auth.profile.name, "Notch");
+
+        let requests = server.join().unwrap();
+        let paths: Vec<_> = requests.iter().map(|(path, _)| path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "/consumers/oauth2/v2.0/devicecode",
+                "/consumers/oauth2/v2.0/token",
+                "/consumers/oauth2/v2.0/token",
+                "/user/authenticate",
+                "/xsts/authorize",
+                "/authentication/login_with_xbox",
+                "/minecraft/profile",
+            ]
+        );
+        assert!(requests[0].1.contains("client_id=client"));
+        assert!(requests[3].1.contains("d=microsoft-token"));
+        assert!(requests[4].1.contains("xbox-token"));
+        assert!(requests[5].1.contains("XBL3.0 x=userhash;xsts-token"));
+    }
+
+    /// Verifies refresh token rotation and the error messages of rejected requests.
+    #[test]
+    fn test_refresh_and_errors_with_mock_endpoints() {
+        let (base, server) = mock_server(vec![
+            (200, token_response("refresh-2")),
+            (
+                400,
+                json!({ "error": "invalid_grant", "error_description": "expired" }),
+            ),
+            (401, json!({ "XErr": 2_148_916_233_u64 })),
+        ]);
+        let authenticator =
+            MinecraftAuthenticator::new("client").with_endpoints(AuthEndpoints::with_base(&base));
+        let token_state = authenticator.refresh("refresh-1").unwrap();
+        assert_eq!(token_state.token_data.refresh_token, "refresh-2");
+        let error = authenticator.refresh("refresh-1").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Token refresh failed: invalid_grant - expired"
+        );
+
+        let xbox_state = XboxLiveAuthState {
+            xbox_auth_data: serde_json::from_value(xbox_response("xbox-token")).unwrap(),
+            endpoints: token_state.endpoints,
+        };
+        let error = xbox_state.request_xsts_token().unwrap_err();
+        assert!(error.to_string().contains("doesn't have an Xbox account"));
+
+        let requests = server.join().unwrap();
+        assert!(requests[0].1.contains("refresh_token=refresh-1"));
+        assert_eq!(requests[2].0, "/xsts/authorize");
+    }
+}
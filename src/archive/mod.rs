@@ -0,0 +1,294 @@
+//! Portable instance archives.
+//!
+//! An instance is exported as a zip file holding `config.toml` and
+//! `config.lock` at its root, next to the game directory files that cannot be
+//! downloaded again: the `config/` directory, local mod files and any extra
+//! paths selected by the user. `account.toml` and `accounts.toml` are never
+//! exported.
+//!
+//! Inside the archive, `game_dir` is always the archive root and `game_root`
+//! and `account` are removed, so an imported instance is self-contained
+//! wherever it is unpacked and uses the active account. Mods from Modrinth
+//! and the game itself are not part of the archive; they are installed again
+//! from `config.lock` after an import.
+
+use crate::config::{ConfigHandler, ConfigPaths, LOCK_FILE};
+use anyhow::{Context, Result};
+use std::fs::{self, File};
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use walkdir::WalkDir;
+use zip::write::SimpleFileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+/// File names that are never read from or written to an archive.
+const SECRET_FILES: [&str; 2] = ["account.toml", "accounts.toml"];
+
+/// Instance files stored by the archive itself, skipped in the game directory.
+const INSTANCE_FILES: [&str; 3] = ["config.toml", "config.lock", LOCK_FILE];
+
+/// Exports an instance to a zip archive.
+///
+/// The archive holds `config.toml`, `config.lock`, the `config/` directory of
+/// the game directory, the files of local mods and the `include` paths.
+/// Included paths are relative to the game directory and may be files or
+/// directories. Returns the number of game directory files written.
+///
+/// # Example
+/// ```no_run
+/// use gluon::archive::export;
+/// use gluon::config::ConfigHandler;
+/// use std::path::{Path, PathBuf};
+///
+/// let config = ConfigHandler::read()?;
+/// export(&config, Path::new("survival.zip"), &[PathBuf::from("options.txt")])?;
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+///
+/// # Errors
+/// - `anyhow::Error` if an included path is absolute, leaves the game directory,
+///   holds accounts or does not exist
+/// - `anyhow::Error` if the file of a local mod cannot be found
+/// - `anyhow::Error` if `config.toml` or `config.lock` cannot be read
+/// - `anyhow::Error` if the archive cannot be written
+pub fn export(handle: &ConfigHandler, file: &Path, include: &[PathBuf]) -> Result<usize> {
+    let game_dir = PathBuf::from(handle.get_absolute_game_dir()?);
+    let mut sources = vec![PathBuf::from("config")];
+    sources.extend(local_mod_files(handle, &game_dir)?);
+    for path in include {
+        check_relative(path)?;
+        if is_secret(path) {
+            return Err(anyhow::anyhow!(
+                "'{}' holds account credentials and cannot be exported",
+                path.display()
+            ));
+        }
+        if !game_dir.join(path).exists() {
+            return Err(anyhow::anyhow!(
+                "'{}' not found in the game directory",
+                path.display()
+            ));
+        }
+        sources.push(path.clone());
+    }
+
+    let mut zip = ZipWriter::new(
+        File::create(file).with_context(|| format!("failed to create '{}'", file.display()))?,
+    );
+    let output = fs::canonicalize(file)?;
+    let options = SimpleFileOptions::default();
+
+    zip.start_file("config.toml", options)?;
+    io::Write::write_all(&mut zip, portable_config(handle.config_path())?.as_bytes())?;
+    let lock_path = handle.config_path().with_file_name("config.lock");
+    zip.start_file("config.lock", options)?;
+    io::Write::write_all(&mut zip, &fs::read(&lock_path)?)?;
+
+    let mut count = 0;
+    for source in sources {
+        for entry in WalkDir::new(game_dir.join(&source)).sort_by_file_name() {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) if e.io_error().map(io::Error::kind) == Some(io::ErrorKind::NotFound) => {
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+            let relative = entry.path().strip_prefix(&game_dir)?;
+            if !entry.file_type().is_file()
+                || is_secret(relative)
+                || INSTANCE_FILES
+                    .iter()
+                    .any(|name| relative == Path::new(name))
+                || fs::canonicalize(entry.path())? == output
+            {
+                continue;
+            }
+            zip.start_file(entry_name(relative)?, options)?;
+            io::copy(&mut File::open(entry.path())?, &mut zip)?;
+            count += 1;
+        }
+    }
+    zip.finish()?;
+    Ok(count)
+}
+
+/// Imports an instance from a zip archive created by `export`.
+///
+/// The archive is unpacked into `root`, which becomes the instance root, and
+/// the configuration of the new instance is returned. The game and its mods
+/// are not installed; use `install_locked` and `modmanage::install_for` with
+/// the returned handler.
+///
+/// # Example
+/// ```no_run
+/// use gluon::archive::import;
+/// use gluon::install::install_locked;
+/// use gluon::modmanage;
+/// use std::path::Path;
+///
+/// let mut config = import(Path::new("survival.zip"), Path::new("survival"))?;
+/// install_locked(&mut config)?;
+/// modmanage::install_for(&mut config)?;
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+///
+/// # Errors
+/// - `anyhow::Error` if `root` already contains `config.toml`
+/// - `anyhow::Error` if the archive cannot be read or has no `config.toml`
+/// - `anyhow::Error` if an entry would be unpacked outside of `root`
+/// - `anyhow::Error` if the files cannot be written
+/// - `anyhow::Error` if the unpacked configuration cannot be read
+pub fn import(file: &Path, root: &Path) -> Result<ConfigHandler> {
+    unpack(file, root)?;
+    ConfigHandler::read_from_paths(ConfigPaths::from_root(root))
+}
+
+/// Unpacks an instance archive into `root`, skipping secrets.
+fn unpack(file: &Path, root: &Path) -> Result<()> {
+    if root.join("config.toml").exists() {
+        return Err(anyhow::anyhow!(
+            "config.toml already exists in '{}'",
+            root.display()
+        ));
+    }
+    let mut zip = ZipArchive::new(
+        File::open(file).with_context(|| format!("failed to open '{}'", file.display()))?,
+    )?;
+    if zip.index_for_name("config.toml").is_none() {
+        return Err(anyhow::anyhow!(
+            "'{}' is not an instance archive, config.toml is missing",
+            file.display()
+        ));
+    }
+
+    fs::create_dir_all(root)?;
+    for i in 0..zip.len() {
+        let mut entry = zip.by_index(i)?;
+        let relative = entry
+            .enclosed_name()
+            .with_context(|| format!("unsafe path '{}' in archive", entry.name()))?;
+        if is_secret(&relative) {
+            continue;
+        }
+        let target = root.join(&relative);
+        if entry.is_dir() {
+            fs::create_dir_all(&target)?;
+            continue;
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        io::copy(&mut entry, &mut File::create(&target)?)?;
+    }
+    Ok(())
+}
+
+/// Returns the paths of local mod files, relative to the game directory.
+///
+/// Disabled mods are exported with their `.unuse` suffix.
+fn local_mod_files(handle: &ConfigHandler, game_dir: &Path) -> Result<Vec<PathBuf>> {
+    let Some(mods) = handle.config().mods.as_ref() else {
+        return Ok(Vec::new());
+    };
+    mods.iter()
+        .filter(|(_, conf)| conf.version.is_none())
+        .filter_map(|(_, conf)| conf.file_name.as_ref())
+        .map(|file_name| {
+            let enabled = Path::new("mods").join(file_name);
+            let disabled = Path::new("mods").join(format!("{file_name}.unuse"));
+            [enabled, disabled]
+                .into_iter()
+                .find(|path| game_dir.join(path).exists())
+                .ok_or_else(|| anyhow::anyhow!("local mod file '{file_name}' not found"))
+        })
+        .collect()
+}
+
+/// Reads `config.toml` with `game_dir` set to the archive root, without `game_root` and `account`.
+fn portable_config(path: &Path) -> Result<String> {
+    let data = fs::read_to_string(path)?;
+    let mut document: toml_edit::DocumentMut = data
+        .parse()
+        .with_context(|| format!("invalid TOML in '{}'", path.display()))?;
+    document["game_dir"] = toml_edit::value("./");
+    document.remove("game_root");
+    document.remove("account");
+    Ok(document.to_string())
+}
+
+/// Checks that a path stays inside the directory it is relative to.
+fn check_relative(path: &Path) -> Result<()> {
+    if path
+        .components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!(
+            "'{}' must be a path inside the game directory",
+            path.display()
+        ))
+    }
+}
+
+/// Tells whether a path names a file that must never leave the instance.
+fn is_secret(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| SECRET_FILES.iter().any(|secret| name == *secret))
+}
+
+/// Converts a relative path to a zip entry name, which always uses `/`.
+fn entry_name(path: &Path) -> Result<String> {
+    let parts = path
+        .components()
+        .map(|c| {
+            c.as_os_str()
+                .to_str()
+                .with_context(|| format!("Failed to convert path to string: {}", path.display()))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(parts.join("/"))
+}
+
+/// Verifies that an exported instance is imported with its files but without secrets.
+#[test]
+fn test_export_import() {
+    let dir = std::env::temp_dir().join("gluon-test-archive");
+    let _ = fs::remove_dir_all(&dir);
+    let paths = |root: &Path| {
+        ConfigPaths::from_root(root)
+            .with_account_store(&dir.join("accounts.toml"))
+            .with_user_config(&dir.join("user.toml"))
+    };
+    let source = dir.join("source");
+    fs::create_dir_all(source.join("config").join("sodium")).unwrap();
+    ConfigHandler::init_for_paths(paths(&source)).unwrap();
+    fs::write(source.join("config/sodium/options.json"), "{}").unwrap();
+    fs::write(source.join("config/account.toml"), "token").unwrap();
+    fs::write(source.join("options.txt"), "fov:90").unwrap();
+    fs::write(source.join("servers.dat"), "").unwrap();
+
+    let handle = ConfigHandler::read_from_paths(paths(&source)).unwrap();
+    let file = dir.join("source.zip");
+    for bad in ["account.toml", "../servers.dat", "missing.txt"] {
+        assert!(export(&handle, &file, &[PathBuf::from(bad)]).is_err());
+    }
+    let count = export(&handle, &file, &[PathBuf::from("options.txt")]).unwrap();
+    assert_eq!(count, 2);
+    drop(handle);
+
+    let target = dir.join("target");
+    unpack(&file, &target).unwrap();
+    let handle = ConfigHandler::read_from_paths(paths(&target)).unwrap();
+    assert_eq!(handle.config().game_dir, "./");
+    assert_eq!(
+        fs::read_to_string(target.join("config/sodium/options.json")).unwrap(),
+        "{}"
+    );
+    assert!(target.join("options.txt").exists());
+    assert!(!target.join("servers.dat").exists());
+    assert!(!target.join("config/account.toml").exists());
+    drop(handle);
+    assert!(unpack(&file, &target).is_err());
+}
@@ -1,43 +1,191 @@
 //! Account-related methods for `ConfigHandler`.
 
-use super::{ConfigHandler, UserAccount};
+use super::migrate::{self, SchemaKind};
+use super::{AccountStore, ConfigHandler, UserAccount};
+use crate::yggdrasil::YggdrasilClient;
 use anyhow::Result;
 use mc_oauth::MinecraftAuthenticator;
+use std::fs;
+use std::path::Path;
 use std::time::SystemTime;
 
 impl ConfigHandler {
     /// Adds an offline account with the given username.
     ///
+    /// The account is saved to the account store by the next `commit` and
+    /// the instance is set to use it.
+    ///
     /// # Example
     /// ```no_run
     /// use gluon::config::ConfigHandler;
     /// let mut config = ConfigHandler::read().unwrap();
     /// config.add_offline_account("Steve");
+    /// config.commit().unwrap();
     /// ```
     pub fn add_offline_account(&mut self, name: &str) {
-        *self.user_account_mut() = UserAccount::new_offline(name);
+        self.set_account(UserAccount::new_offline(name));
     }
 
     /// Adds a Microsoft account to the configuration.
     ///
     /// Initiates an interactive authentication process where the user
     /// must visit a URL and enter a code to authorize the application.
+    /// Like `add_offline_account`, the account is saved to the account store
+    /// and the instance is set to use it. The client ID is chosen by
+    /// `RuntimeConfig::microsoft_authenticator`.
     ///
     /// # Errors
+    /// - `anyhow::Error` if no client ID is configured
     /// - `anyhow::Error` if Microsoft device flow initialization fails
     /// - `anyhow::Error` if user authentication times out
     /// - `anyhow::Error` if Xbox Live authentication fails
     /// - `anyhow::Error` if Minecraft authentication fails
     pub fn add_microsoft_account(&mut self) -> anyhow::Result<()> {
-        *self.user_account_mut() = UserAccount::new_microsoft()?;
+        let authenticator = self.config().microsoft_authenticator()?;
+        self.set_account(UserAccount::new_microsoft_with(&authenticator)?);
+        Ok(())
+    }
+
+    /// Adds an account of a Yggdrasil server, e.g. one used with authlib-injector.
+    ///
+    /// Logs in with `username` and `password`, see `UserAccount::new_yggdrasil`.
+    /// Like `add_offline_account`, the account is saved to the account store
+    /// and the instance is set to use it.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use gluon::config::ConfigHandler;
+    /// let mut config = ConfigHandler::read()?;
+    /// config.add_yggdrasil_account("littleskin.cn", "steve@example.com", "secret", None)?;
+    /// config.commit()?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the server cannot be reached
+    /// - `anyhow::Error` if the credentials are rejected
+    /// - `anyhow::Error` if no game profile can be selected
+    pub fn add_yggdrasil_account(
+        &mut self,
+        server: &str,
+        username: &str,
+        password: &str,
+        profile: Option<&str>,
+    ) -> Result<()> {
+        self.set_account(UserAccount::new_yggdrasil(
+            server, username, password, profile,
+        )?);
         Ok(())
     }
 
+    /// Uses a stored account for this handler only, e.g. for one launch.
+    ///
+    /// `account` is a player name or UUID of the account store. The
+    /// instance's `account` setting is not changed.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use gluon::config::ConfigHandler;
+    ///
+    /// let mut config = ConfigHandler::read()?;
+    /// config.use_account("Alex")?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the account store cannot be read
+    /// - `anyhow::Error` if no account or several accounts match `account`
+    pub fn use_account(&mut self, account: &str) -> Result<()> {
+        let store = AccountStore::read_from(&self.paths.account_store()?)?;
+        self.user_account.set_unmarked(store.get(account)?.clone());
+        Ok(())
+    }
+
+    /// Decrypts the tokens of the account if they are sealed in a locked account store.
+    ///
+    /// The tokens stay decrypted in memory only; they are sealed again when
+    /// the account is written back to the store.
+    fn open_account(&mut self) -> Result<()> {
+        if self.user_account().sealed_tokens.is_none() {
+            return Ok(());
+        }
+        let store = AccountStore::read_from(&self.paths.account_store()?)?;
+        let mut account = self.user_account().clone();
+        store.open_account(&mut account)?;
+        self.user_account.set_unmarked(account);
+        Ok(())
+    }
+
+    /// Sets the account of this instance and pins the instance to it.
+    fn set_account(&mut self, account: UserAccount) {
+        self.config_mut().account = Some(account.user_uuid.clone());
+        *self.user_account_mut() = account;
+    }
+
+    /// Moves the legacy `account.toml` of the instance into the account store.
+    ///
+    /// If the store already holds the account, the copy whose token expires
+    /// later is kept. The instance is pinned to the moved account when the
+    /// store has another active account, so it keeps launching as before.
+    /// Returns the account store.
+    pub(super) fn migrate_legacy_account(&mut self) -> Result<AccountStore> {
+        let store_path = self.paths.account_store()?;
+        let legacy = Path::new(&self.paths.user_account);
+        if !fs::exists(legacy)? {
+            return AccountStore::read_from(&store_path);
+        }
+        let data = migrate::read_versioned(legacy, SchemaKind::Account)?;
+        let account: UserAccount = toml::Value::Table(data).try_into()?;
+        let uuid = account.user_uuid.clone();
+        let store = AccountStore::modify_at(&store_path, |store| {
+            if store
+                .accounts
+                .get(&uuid)
+                .is_none_or(|stored| stored.token_expires_at <= account.token_expires_at)
+            {
+                store.update(account);
+            }
+            Ok(store.clone())
+        })?;
+        fs::remove_file(legacy)?;
+        eprintln!(
+            "Moved '{}' to the account store '{}'",
+            legacy.display(),
+            store_path.display()
+        );
+        if self.config().account.is_none() && store.active.as_ref() != Some(&uuid) {
+            self.config_mut().account = Some(uuid);
+            self.commit()?;
+        }
+        Ok(store)
+    }
+
+    /// Selects the account of this instance from the account store.
+    ///
+    /// Uses the account named by the `account` setting, or the active account.
+    /// An unknown account only prints a warning, so the game can still be
+    /// launched with the active account.
+    pub(super) fn select_stored_account(&mut self, store: &AccountStore) {
+        let selected = match self.config().account.as_deref().map(|x| store.get(x)) {
+            Some(Ok(account)) => Some(account),
+            Some(Err(e)) => {
+                eprintln!("Warning: {e}, using the active account");
+                store.active_account()
+            }
+            None => store.active_account(),
+        };
+        if let Some(account) = selected {
+            self.user_account.set_unmarked(account.clone());
+        }
+    }
+
     /// Refreshes the Microsoft account tokens using the stored refresh token.
     ///
     /// Runs the full refresh pipeline: Microsoft refresh token -> Xbox Live
     /// -> XSTS -> Minecraft, then updates the stored access token, the rotated
     /// refresh token (the old one becomes invalid) and the new expiry time.
+    /// The tokens are refreshed with the client ID stored with the account.
+    /// Yggdrasil accounts exchange their access token for a new one instead.
     ///
     /// # Example
     /// ```no_run
@@ -45,20 +193,34 @@ impl ConfigHandler {
     ///
     /// let mut config = ConfigHandler::read().unwrap();
     /// config.refresh_account().unwrap();
+    /// config.commit().unwrap();
     /// ```
     ///
     /// # Errors
+    /// - `anyhow::Error` if the tokens are encrypted and the passphrase is wrong
+    ///   or cannot be read
     /// - `anyhow::Error` if no refresh token is stored (offline account or
     ///   old configuration)
+    /// - `anyhow::Error` if the account has no stored client ID and none is configured
     /// - `anyhow::Error` if any step of the refresh pipeline fails
     pub fn refresh_account(&mut self) -> Result<()> {
+        self.open_account()?;
+        if self.user_account().auth_server.is_some() {
+            return self.refresh_yggdrasil_account();
+        }
         let Some(refresh_token) = self.user_account().refresh_token.clone() else {
             return Err(anyhow::anyhow!(
                 "No refresh token stored, run 'gluon account microsoft' first"
             ));
         };
 
-        let authenticator = MinecraftAuthenticator::from_compile_env();
+        // Refresh tokens only work with the client ID they were issued to,
+        // whatever this instance configures. Accounts stored before the
+        // client ID was recorded fall back to the configured one.
+        let authenticator = match &self.user_account().microsoft_client_id {
+            Some(client_id) => MinecraftAuthenticator::new(client_id),
+            None => self.config().microsoft_authenticator()?,
+        };
         let token_state = authenticator.refresh(&refresh_token)?;
         let minecraft_state = token_state
             .request_xbox_token()?
@@ -70,6 +232,7 @@ impl ConfigHandler {
         // Token rotation: the refresh response contains a new refresh token,
         // the old one becomes invalid.
         user.refresh_token = Some(token_state.token_data.refresh_token.clone());
+        user.microsoft_client_id = Some(authenticator.client_id().to_owned());
         user.token_expires_at = Some(
             SystemTime::now()
                 .duration_since(SystemTime::UNIX_EPOCH)
@@ -84,7 +247,10 @@ impl ConfigHandler {
     ///
     /// Uses a pure local check (no network) when the token is still valid.
     /// When expired, attempts a full refresh; on failure prints a warning and
-    /// continues so the game can still be launched offline.
+    /// continues so the game can still be launched offline. Tokens of a locked
+    /// account store are decrypted first, asking for the passphrase.
+    /// Yggdrasil tokens have no known expiry and are validated against the
+    /// server instead.
     ///
     /// # Example
     /// ```no_run
@@ -92,21 +258,34 @@ impl ConfigHandler {
     ///
     /// let mut config = ConfigHandler::read().unwrap();
     /// config.ensure_valid_token().unwrap();
+    /// config.commit().unwrap();
     /// ```
     ///
     /// # Errors
     /// - `anyhow::Error` if the system clock cannot be read
+    /// - `anyhow::Error` if the tokens are encrypted and the passphrase is wrong
+    ///   or cannot be read
     pub fn ensure_valid_token(&mut self) -> Result<()> {
         let now = SystemTime::now()
             .duration_since(SystemTime::UNIX_EPOCH)
             .map_err(|e| anyhow::anyhow!("system time before unix epoch: {e}"))?
             .as_secs();
 
+        if self.user_account().auth_server.is_some() {
+            self.open_account()?;
+            if let Err(e) = self.validate_yggdrasil_token() {
+                eprintln!("Warning: token validation failed: {e}");
+                eprintln!("Run 'gluon account refresh' to retry, or log in again");
+            }
+            return Ok(());
+        }
+
         let Some(expires_at) = self.user_account().token_expires_at else {
             // Offline account, or old configuration without expiry info:
             // nothing to check or refresh.
             return Ok(());
         };
+        self.open_account()?;
 
         if now < expires_at {
             return Ok(());
@@ -120,4 +299,44 @@ impl ConfigHandler {
         }
         Ok(())
     }
+
+    /// Refreshes the access token if the Yggdrasil server no longer accepts it.
+    fn validate_yggdrasil_token(&mut self) -> Result<()> {
+        let account = self.user_account();
+        let (Some(server), Some(access_token), Some(client_token)) = (
+            &account.auth_server,
+            &account.access_token,
+            &account.client_token,
+        ) else {
+            return self.refresh_yggdrasil_account();
+        };
+        if YggdrasilClient::new(server).validate(access_token, client_token)? {
+            return Ok(());
+        }
+        self.refresh_yggdrasil_account()
+    }
+
+    /// Exchanges the Yggdrasil access token for a new one.
+    ///
+    /// The player name follows the profile, in case it was renamed on the server.
+    fn refresh_yggdrasil_account(&mut self) -> Result<()> {
+        let account = self.user_account();
+        let (Some(server), Some(access_token), Some(client_token)) = (
+            &account.auth_server,
+            &account.access_token,
+            &account.client_token,
+        ) else {
+            return Err(anyhow::anyhow!(
+                "No Yggdrasil token stored, run 'gluon account yggdrasil' first"
+            ));
+        };
+        let session = YggdrasilClient::new(server).refresh(access_token, client_token, None)?;
+        let user = self.user_account_mut();
+        user.access_token = Some(session.access_token);
+        user.client_token = Some(session.client_token);
+        if let Some(profile) = session.selected_profile {
+            user.user_name = profile.name;
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,161 @@
+//! Format-preserving edits of TOML documents.
+//!
+//! Instead of serializing a configuration from scratch, the document read from
+//! disk is edited in place: unchanged keys keep their comments, order and
+//! formatting, changed values keep their trailing comments, removed keys are
+//! dropped and new keys are appended to their table.
+
+use anyhow::{Context, Result};
+use std::path::Path;
+use toml::{Table, Value};
+use toml_edit::{Array, ArrayOfTables, DocumentMut, InlineTable, Item, TableLike};
+
+/// Parses a TOML document, keeping its formatting.
+///
+/// # Errors
+/// - `anyhow::Error` if `data` is not valid TOML
+pub(super) fn parse(data: &str, path: &Path) -> Result<DocumentMut> {
+    data.parse()
+        .with_context(|| format!("invalid TOML in '{}'", path.display()))
+}
+
+/// Edits `document` until it holds the values of `table`.
+///
+/// # Errors
+/// - `anyhow::Error` if the current document cannot be read back as a table
+pub(super) fn update(document: &mut DocumentMut, table: &Table) -> Result<()> {
+    let old: Table = toml::from_str(&document.to_string())?;
+    update_table(document.as_table_mut(), &old, table, false);
+    Ok(())
+}
+
+/// Edits `doc`, whose values are `old`, until it holds the values of `new`.
+///
+/// `inline` tells whether `doc` is an inline table, which can only hold values.
+fn update_table(doc: &mut dyn TableLike, old: &Table, new: &Table, inline: bool) {
+    for key in old.keys().filter(|key| !new.contains_key(*key)) {
+        doc.remove(key);
+    }
+    for (key, value) in new {
+        let old_value = old.get(key);
+        if old_value == Some(value) {
+            continue;
+        }
+        let Some(item) = doc.get_mut(key) else {
+            doc.insert(key, to_item(value, inline));
+            continue;
+        };
+        if let (Some(Value::Table(old)), Value::Table(new)) = (old_value, value) {
+            let inline = item.is_inline_table();
+            if let Some(table) = item.as_table_like_mut() {
+                update_table(table, old, new, inline);
+                continue;
+            }
+        }
+        // Replacing the item in place keeps the key and its leading comments.
+        let mut new_item = to_item(value, inline || item.is_value());
+        if let (Some(old), Some(new)) = (item.as_value(), new_item.as_value_mut()) {
+            *new.decor_mut() = old.decor().clone();
+        }
+        *item = new_item;
+    }
+}
+
+/// Converts a value to a document item, using standard tables unless `inline`.
+fn to_item(value: &Value, inline: bool) -> Item {
+    match value {
+        Value::Table(table) if !inline => Item::Table(to_table(table)),
+        Value::Array(array)
+            if !inline && !array.is_empty() && array.iter().all(Value::is_table) =>
+        {
+            Item::ArrayOfTables(
+                array
+                    .iter()
+                    .filter_map(Value::as_table)
+                    .map(to_table)
+                    .collect::<ArrayOfTables>(),
+            )
+        }
+        value => Item::Value(to_value(value)),
+    }
+}
+
+/// Converts a table to a standard document table.
+///
+/// Tables holding only tables get no header of their own.
+fn to_table(table: &Table) -> toml_edit::Table {
+    let mut doc = toml_edit::Table::new();
+    for (key, value) in table {
+        doc.insert(key, to_item(value, false));
+    }
+    doc.set_implicit(!table.is_empty() && table.values().all(Value::is_table));
+    doc
+}
+
+/// Converts a value to an inline document value.
+fn to_value(value: &Value) -> toml_edit::Value {
+    match value {
+        Value::String(s) => s.clone().into(),
+        Value::Integer(i) => (*i).into(),
+        Value::Float(f) => (*f).into(),
+        Value::Boolean(b) => (*b).into(),
+        Value::Datetime(d) => (*d).into(),
+        Value::Array(array) => array.iter().map(to_value).collect::<Array>().into(),
+        Value::Table(table) => table
+            .iter()
+            .map(|(key, value)| (key.as_str(), to_value(value)))
+            .collect::<InlineTable>()
+            .into(),
+    }
+}
+
+/// Verifies that edits keep comments, order and formatting of untouched parts.
+#[test]
+fn test_update_document() {
+    let data = "\
+# pinned for the server
+game_version = '1.20.1' # do not bump
+java_path = 'java'
+
+[mirror]
+# mirror used in CI
+assets = 'https://a/'
+client = 'https://c/'
+
+[mods.sodium]
+version = '0.5'
+";
+    let mut document = parse(data, Path::new("config.toml")).unwrap();
+    let mut table: Table = toml::from_str(data).unwrap();
+    table["game_version"] = Value::String("1.21.1".into());
+    table.remove("java_path");
+    table["mirror"]
+        .as_table_mut()
+        .unwrap()
+        .insert("libraries".into(), Value::String("https://l/".into()));
+    table["mods"].as_table_mut().unwrap().insert(
+        "lithium".into(),
+        Value::Table(toml::from_str("version = '0.11'").unwrap()),
+    );
+
+    update(&mut document, &table).unwrap();
+    assert_eq!(
+        document.to_string(),
+        "\
+# pinned for the server
+game_version = \"1.21.1\" # do not bump
+
+[mirror]
+# mirror used in CI
+assets = 'https://a/'
+client = 'https://c/'
+libraries = \"https://l/\"
+
+[mods.sodium]
+version = '0.5'
+
+[mods.lithium]
+version = \"0.11\"
+"
+    );
+}
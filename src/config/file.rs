@@ -0,0 +1,252 @@
+//! Crash-safe file writes and cross-process instance locking.
+//!
+//! Configuration files are written to a temporary file in the same directory
+//! and renamed over the target, so a crash never leaves a half-written file.
+//! An advisory lock on `.gluon.lock` in the instance root keeps two `gluon`
+//! processes from working on the same instance at once, and a lock next to
+//! shared files like the account store serializes their updates.
+
+use anyhow::{Context, Result};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Weak};
+
+/// Name of the lock file in the instance root.
+pub(crate) const LOCK_FILE: &str = ".gluon.lock";
+
+thread_local! {
+    /// Locks held by this thread, keyed by absolute instance root or locked file.
+    ///
+    /// Handlers of the same instance in one thread share a single lock, so
+    /// nested reads do not see their own thread as busy. Other threads open the
+    /// lock file again and are excluded by the OS lock like other processes.
+    static HELD_LOCKS: RefCell<BTreeMap<PathBuf, Weak<InstanceLock>>> =
+        const { RefCell::new(BTreeMap::new()) };
+}
+
+/// Writes `contents` to `path` atomically.
+///
+/// The data is written and synced to a temporary file next to `path`, which
+/// is then renamed over `path`. On failure the temporary file is removed and
+/// `path` is left untouched.
+///
+/// # Errors
+/// - `anyhow::Error` if the temporary file cannot be written or renamed
+pub(crate) fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> Result<()> {
+    StagedFile::stage(path, contents)?.commit()
+}
+
+/// Writes `contents` to `path` atomically, readable by the current user only.
+///
+/// Like `write_atomic`, but on Unix the file is created with mode `0600`, for
+/// files holding credentials.
+///
+/// # Errors
+/// - `anyhow::Error` if the temporary file cannot be written or renamed
+pub(crate) fn write_private(path: &Path, contents: impl AsRef<[u8]>) -> Result<()> {
+    StagedFile::stage_private(path, contents)?.commit()
+}
+
+/// A file written to a temporary path, waiting to be renamed over its target.
+///
+/// Staging every file of a commit first means a serialization or disk error
+/// while staging leaves all targets untouched. The temporary file is removed if the staged
+/// file is dropped without being committed.
+#[derive(Debug)]
+pub(super) struct StagedFile {
+    /// Temporary file holding the new contents.
+    tmp: PathBuf,
+    /// File the contents are committed to.
+    path: PathBuf,
+}
+
+impl StagedFile {
+    /// Writes and syncs `contents` to a temporary file next to `path`.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the temporary file cannot be written
+    pub(super) fn stage(path: &Path, contents: impl AsRef<[u8]>) -> Result<Self> {
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        Self::stage_with(path, contents.as_ref(), &options)
+    }
+
+    /// Like `stage`, but on Unix the file is created with mode `0600`.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the temporary file cannot be written
+    pub(super) fn stage_private(path: &Path, contents: impl AsRef<[u8]>) -> Result<Self> {
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        Self::stage_with(path, contents.as_ref(), &options)
+    }
+
+    /// Writes and syncs `contents` to a temporary file opened with `options`.
+    fn stage_with(path: &Path, contents: &[u8], options: &OpenOptions) -> Result<Self> {
+        let staged = Self {
+            tmp: temp_path(path),
+            path: path.to_path_buf(),
+        };
+        // A leftover temporary file would keep its old mode.
+        let _ = fs::remove_file(&staged.tmp);
+        options
+            .open(&staged.tmp)
+            .and_then(|mut file| {
+                file.write_all(contents.as_ref())?;
+                file.sync_all()
+            })
+            .with_context(|| format!("failed to write '{}'", staged.tmp.display()))?;
+        Ok(staged)
+    }
+
+    /// Renames the temporary file over the target.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the temporary file cannot be renamed
+    pub(super) fn commit(self) -> Result<()> {
+        fs::rename(&self.tmp, &self.path)
+            .with_context(|| format!("failed to write '{}'", self.path.display()))
+    }
+}
+
+impl Drop for StagedFile {
+    /// Removes the temporary file if it was not committed.
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.tmp);
+    }
+}
+
+/// Returns the temporary path used while writing `path`.
+fn temp_path(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map_or_else(|| "config".into(), |name| name.to_string_lossy());
+    path.with_file_name(format!(".{name}.{}.tmp", std::process::id()))
+}
+
+/// Advisory exclusive lock on an instance, released when the last holder drops it.
+#[derive(Debug)]
+pub(super) struct InstanceLock {
+    /// The open lock file; closing it releases the lock.
+    _file: File,
+}
+
+impl InstanceLock {
+    /// Locks the instance rooted at `root`.
+    ///
+    /// Returns the lock already held by this thread if there is one.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if another process or thread holds the lock on the instance
+    /// - `anyhow::Error` if the lock file cannot be opened or locked
+    pub(super) fn acquire(root: &Path) -> Result<Arc<Self>> {
+        let root = std::path::absolute(root)?;
+        let path = root.join(LOCK_FILE);
+        Self::acquire_with(&root, &path, |file| match file.try_lock() {
+            Ok(()) => Ok(()),
+            Err(TryLockError::WouldBlock) => Err(anyhow::anyhow!(
+                "instance '{}' is busy, another gluon process is using it",
+                root.display()
+            )),
+            Err(TryLockError::Error(e)) => {
+                Err(e).with_context(|| format!("failed to lock '{}'", path.display()))
+            }
+        })
+    }
+
+    /// Locks a file shared by all instances, like the account store.
+    ///
+    /// The lock is taken on `.<name>.lock` next to `path`, waiting for other
+    /// processes and threads to release it. Returns the lock already held by
+    /// this thread if there is one.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the lock file cannot be opened or locked
+    pub(super) fn acquire_file(path: &Path) -> Result<Arc<Self>> {
+        let path = std::path::absolute(path)?;
+        let name = path
+            .file_name()
+            .map_or_else(|| "file".into(), |name| name.to_string_lossy());
+        let lock_path = path.with_file_name(format!(".{name}.lock"));
+        if let Some(parent) = lock_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        Self::acquire_with(&path, &lock_path, |file| {
+            file.lock()
+                .with_context(|| format!("failed to lock '{}'", lock_path.display()))
+        })
+    }
+
+    /// Locks `lock_path` with `lock` unless this thread already holds the lock for `key`.
+    fn acquire_with(
+        key: &Path,
+        lock_path: &Path,
+        lock: impl FnOnce(&File) -> Result<()>,
+    ) -> Result<Arc<Self>> {
+        let held = HELD_LOCKS.with_borrow(|held| held.get(key).and_then(Weak::upgrade));
+        if let Some(lock) = held {
+            return Ok(lock);
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(lock_path)
+            .with_context(|| format!("failed to open lock file '{}'", lock_path.display()))?;
+        lock(&file)?;
+        let lock = Arc::new(Self { _file: file });
+        HELD_LOCKS.with_borrow_mut(|held| held.insert(key.to_path_buf(), Arc::downgrade(&lock)));
+        Ok(lock)
+    }
+}
+
+/// Verifies that atomic writes replace the file and leave no temporary file behind.
+#[test]
+fn test_write_atomic() {
+    let dir = std::env::temp_dir().join("gluon-test-write-atomic");
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("config.toml");
+    write_atomic(&path, "a = 1\n").unwrap();
+    write_atomic(&path, "a = 2\n").unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "a = 2\n");
+    assert!(!temp_path(&path).exists());
+}
+
+/// Verifies that the instance lock is shared within the thread and excludes other holders.
+#[test]
+fn test_instance_lock() {
+    let root = std::env::temp_dir().join("gluon-test-instance-lock");
+    fs::create_dir_all(&root).unwrap();
+    let lock = InstanceLock::acquire(&root).unwrap();
+    let again = InstanceLock::acquire(&root).unwrap();
+    assert!(Arc::ptr_eq(&lock, &again));
+    let thread_root = root.clone();
+    let busy = std::thread::spawn(move || InstanceLock::acquire(&thread_root).is_err());
+    assert!(busy.join().unwrap());
+
+    let other = File::open(root.join(LOCK_FILE)).unwrap();
+    assert!(other.try_lock().is_err());
+    drop((lock, again));
+    assert!(other.try_lock().is_ok());
+}
+
+/// Verifies that shared files are locked next to the file and re-entrant within the thread.
+#[test]
+fn test_file_lock() {
+    let dir = std::env::temp_dir().join("gluon-test-file-lock");
+    let path = dir.join("accounts.toml");
+    let lock = InstanceLock::acquire_file(&path).unwrap();
+    let again = InstanceLock::acquire_file(&path).unwrap();
+    assert!(Arc::ptr_eq(&lock, &again));
+
+    let other = File::open(dir.join(".accounts.toml.lock")).unwrap();
+    assert!(other.try_lock().is_err());
+    drop((lock, again));
+    assert!(other.try_lock().is_ok());
+}
@@ -0,0 +1,459 @@
+//! Instance registry for managing multiple named game instances.
+//!
+//! The registry is stored in `instances.toml` inside the user config directory
+//! and maps instance names to their root directories (the directory containing
+//! `config.toml`). It also remembers the active instance, which is used when no
+//! instance is selected explicitly and no `config.toml` is found from the
+//! current directory.
+
+use super::file::write_atomic;
+use super::migrate::{self, SchemaKind};
+use super::path::{user_config_dir, user_data_dir};
+use super::{ConfigHandler, ConfigLayers, ConfigPaths};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::RwLock;
+use walkdir::WalkDir;
+
+/// Game directory subdirectories copied to every clone of an instance.
+const CLONED_DIRS: [&str; 2] = ["mods", "natives"];
+
+/// Instance selected for this process, usually from the `--instance` flag.
+static SELECTED_INSTANCE: RwLock<Option<String>> = RwLock::new(None);
+
+/// Returns the instance selected for this process, if any.
+pub(super) fn selected_instance() -> Option<String> {
+    SELECTED_INSTANCE
+        .read()
+        .map_or(None, |selected| selected.clone())
+}
+
+/// A registered instance.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct InstanceEntry {
+    /// Absolute path of the instance root, the directory containing `config.toml`.
+    pub path: String,
+}
+
+impl InstanceEntry {
+    /// Returns the configuration file paths of this instance.
+    #[must_use]
+    pub fn paths(&self) -> ConfigPaths {
+        ConfigPaths::from_root(Path::new(&self.path))
+    }
+}
+
+/// Registry of named instances.
+///
+/// # Example
+/// ```
+/// use gluon::config::InstanceRegistry;
+///
+/// let mut registry = InstanceRegistry::default();
+/// registry.add("survival", "/games/survival").unwrap();
+/// registry.rename("survival", "vanilla").unwrap();
+/// assert_eq!(registry.active.as_deref(), Some("vanilla"));
+/// assert!(registry.get("survival").is_err());
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InstanceRegistry {
+    /// Name of the instance used when no other instance can be resolved.
+    pub active: Option<String>,
+    /// Registered instances keyed by name.
+    #[serde(default)]
+    pub instances: BTreeMap<String, InstanceEntry>,
+}
+
+impl InstanceRegistry {
+    /// Returns the path of the registry file, `instances.toml` in the user config directory.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the user config directory cannot be determined
+    pub fn registry_path() -> Result<PathBuf> {
+        Ok(user_config_dir()?.join("instances.toml"))
+    }
+
+    /// Returns the default root directory for a new instance with the given name.
+    ///
+    /// New instances live under `instances/<name>` in the user data directory.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the user data directory cannot be determined
+    pub fn default_instance_dir(name: &str) -> Result<PathBuf> {
+        Ok(user_data_dir()?.join("instances").join(name))
+    }
+
+    /// Reads the registry from the user config directory.
+    ///
+    /// Returns an empty registry if the registry file does not exist yet.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the user config directory cannot be determined
+    /// - `anyhow::Error` if the registry file cannot be read or contains invalid TOML
+    pub fn read() -> Result<Self> {
+        Self::read_from(&Self::registry_path()?)
+    }
+
+    /// Reads the registry from the given file.
+    ///
+    /// Returns an empty registry if the file does not exist.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the file cannot be read or contains invalid TOML
+    pub fn read_from(path: &Path) -> Result<Self> {
+        if !fs::exists(path)? {
+            return Ok(Self::default());
+        }
+        let data = fs::read_to_string(path)?;
+        toml::from_str(&data)
+            .with_context(|| format!("invalid instance registry '{}'", path.display()))
+    }
+
+    /// Writes the registry to the user config directory.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the user config directory cannot be determined or created
+    /// - `anyhow::Error` if the registry file cannot be written
+    pub fn write(&self) -> Result<()> {
+        self.write_to(&Self::registry_path()?)
+    }
+
+    /// Writes the registry to the given file, creating parent directories as needed.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the parent directory cannot be created
+    /// - `anyhow::Error` if the registry cannot be serialized or written
+    pub fn write_to(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        write_atomic(path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Returns the instance registered under the given name.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if no instance with this name is registered
+    pub fn get(&self, name: &str) -> Result<&InstanceEntry> {
+        self.instances.get(name).ok_or_else(|| {
+            anyhow::anyhow!(
+                "instance '{name}' not found, run 'gluon instance list' to see instances"
+            )
+        })
+    }
+
+    /// Registers an instance rooted at the given directory.
+    ///
+    /// The first registered instance becomes the active one. The name may only
+    /// contain ASCII letters, digits, `-`, `_` and `.`.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the name is invalid or already registered
+    pub fn add(&mut self, name: &str, path: impl Into<String>) -> Result<()> {
+        validate_name(name)?;
+        if self.instances.contains_key(name) {
+            return Err(anyhow::anyhow!("instance '{name}' already exists"));
+        }
+        self.instances
+            .insert(name.to_owned(), InstanceEntry { path: path.into() });
+        if self.active.is_none() {
+            self.active = Some(name.to_owned());
+        }
+        Ok(())
+    }
+
+    /// Unregisters an instance and returns its entry.
+    ///
+    /// Clears the active instance if it was the removed one. Files of the
+    /// instance are left untouched.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if no instance with this name is registered
+    pub fn remove(&mut self, name: &str) -> Result<InstanceEntry> {
+        let entry = self
+            .instances
+            .remove(name)
+            .ok_or_else(|| anyhow::anyhow!("instance '{name}' not found"))?;
+        if self.active.as_deref() == Some(name) {
+            self.active = None;
+        }
+        Ok(entry)
+    }
+
+    /// Renames an instance, keeping it active if it was.
+    ///
+    /// Only the registry entry is renamed; the instance directory is not moved.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the old name is not registered
+    /// - `anyhow::Error` if the new name is invalid or already registered
+    pub fn rename(&mut self, old: &str, new: &str) -> Result<()> {
+        validate_name(new)?;
+        if self.instances.contains_key(new) {
+            return Err(anyhow::anyhow!("instance '{new}' already exists"));
+        }
+        let entry = self.remove_keep_active(old)?;
+        self.instances.insert(new.to_owned(), entry);
+        if self.active.as_deref() == Some(old) {
+            self.active = Some(new.to_owned());
+        }
+        Ok(())
+    }
+
+    /// Makes the given instance the active one.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if no instance with this name is registered
+    pub fn switch(&mut self, name: &str) -> Result<()> {
+        self.get(name)?;
+        self.active = Some(name.to_owned());
+        Ok(())
+    }
+
+    /// Removes an entry without touching the active pointer.
+    fn remove_keep_active(&mut self, name: &str) -> Result<InstanceEntry> {
+        self.instances
+            .remove(name)
+            .ok_or_else(|| anyhow::anyhow!("instance '{name}' not found"))
+    }
+}
+
+/// Checks that an instance name is usable as a registry key and directory name.
+fn validate_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name != "."
+        && name != ".."
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!(
+            "invalid instance name '{name}': only ASCII letters, digits, '-', '_' and '.' are allowed"
+        ))
+    }
+}
+
+impl ConfigHandler {
+    /// Selects the named instance for all later `ConfigHandler::read` calls in this process.
+    ///
+    /// Passing `None` clears the selection, so `read` falls back to searching
+    /// from the current directory and then to the active instance.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use gluon::config::ConfigHandler;
+    ///
+    /// ConfigHandler::select_instance(Some("survival"));
+    /// let config = ConfigHandler::read().unwrap();
+    /// ```
+    pub fn select_instance(name: Option<&str>) {
+        if let Ok(mut selected) = SELECTED_INSTANCE.write() {
+            *selected = name.map(ToOwned::to_owned);
+        }
+    }
+
+    /// Reads the configuration of a registered instance.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the registry cannot be read
+    /// - `anyhow::Error` if no instance with this name is registered
+    /// - `anyhow::Error` if the instance configuration cannot be read
+    pub fn read_instance(name: &str) -> Result<Self> {
+        let paths = InstanceRegistry::read()?.get(name)?.paths();
+        ConfigHandler::read_from_paths(paths)
+    }
+
+    /// Creates a new instance and registers it under the given name.
+    ///
+    /// The instance is initialized at `path`, or at the default instance
+    /// directory inside the user data directory if `path` is `None`. An
+    /// existing directory that already contains `config.toml` is registered
+    /// as-is. Returns the root directory of the instance.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use gluon::config::ConfigHandler;
+    ///
+    /// let root = ConfigHandler::create_instance("survival", None).unwrap();
+    /// println!("created instance at {}", root.display());
+    /// ```
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the name is invalid or already registered
+    /// - `anyhow::Error` if the instance directory cannot be created
+    /// - `anyhow::Error` if the configuration files cannot be written
+    /// - `anyhow::Error` if the registry cannot be read or written
+    pub fn create_instance(name: &str, path: Option<&Path>) -> Result<PathBuf> {
+        let mut registry = InstanceRegistry::read()?;
+        validate_name(name)?;
+        if registry.instances.contains_key(name) {
+            return Err(anyhow::anyhow!("instance '{name}' already exists"));
+        }
+
+        let root = match path {
+            Some(path) => std::path::absolute(path)?,
+            None => InstanceRegistry::default_instance_dir(name)?,
+        };
+        fs::create_dir_all(&root)?;
+        if !root.join("config.toml").exists() {
+            ConfigHandler::init_for_paths(ConfigPaths::from_root(&root))?;
+        }
+
+        let root_str = root
+            .to_str()
+            .with_context(|| format!("Failed to convert path to string: {}", root.display()))?;
+        registry.add(name, root_str)?;
+        registry.write()?;
+        Ok(root)
+    }
+
+    /// Clones a registered instance and registers the copy under a new name.
+    ///
+    /// The clone gets the `config.toml` and `config.lock` of the source
+    /// instance and a copy of its `mods/` and `natives/` directories, plus the
+    /// game directory subdirectories named in `extra_dirs`, such as `saves` or
+    /// `config`. Accounts live in the account store, so the clone uses the
+    /// same account as the source. The clone shares the game root
+    /// of the source, so libraries, assets and versions are not downloaded
+    /// again, and an absolute `game_dir` is replaced by the root of the clone.
+    /// The clone is created at `path`, or at the default instance directory if
+    /// `path` is `None`. Returns the root directory of the clone.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use gluon::config::ConfigHandler;
+    ///
+    /// let root = ConfigHandler::clone_instance("survival", "survival-16g", None, &["saves"])?;
+    /// println!("cloned instance to {}", root.display());
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the source instance is not registered or cannot be read
+    /// - `anyhow::Error` if the new name is invalid or already registered
+    /// - `anyhow::Error` if an entry of `extra_dirs` is not a single directory name
+    /// - `anyhow::Error` if the clone directory already contains `config.toml`
+    /// - `anyhow::Error` if the files cannot be copied or written
+    /// - `anyhow::Error` if the registry cannot be read or written
+    pub fn clone_instance(
+        src: &str,
+        dst: &str,
+        path: Option<&Path>,
+        extra_dirs: &[&str],
+    ) -> Result<PathBuf> {
+        let mut registry = InstanceRegistry::read()?;
+        let source = ConfigHandler::read_from_paths(registry.get(src)?.paths())?;
+        validate_name(dst)?;
+        if registry.instances.contains_key(dst) {
+            return Err(anyhow::anyhow!("instance '{dst}' already exists"));
+        }
+        for dir in extra_dirs {
+            let mut components = Path::new(dir).components();
+            if !matches!(
+                (components.next(), components.next()),
+                (Some(Component::Normal(_)), None)
+            ) {
+                return Err(anyhow::anyhow!(
+                    "'{dir}' is not a directory name inside the game directory"
+                ));
+            }
+        }
+
+        let root = match path {
+            Some(path) => std::path::absolute(path)?,
+            None => InstanceRegistry::default_instance_dir(dst)?,
+        };
+        if root.join("config.toml").exists() {
+            return Err(anyhow::anyhow!(
+                "config.toml already exists in '{}'",
+                root.display()
+            ));
+        }
+        fs::create_dir_all(&root)?;
+
+        let paths = ConfigPaths::from_root(&root);
+        let (document, table) = migrate::read_document(source.config_path(), SchemaKind::Runtime)?;
+        let layers = ConfigLayers::load(paths.user_config(), document, table)?;
+        let mut config = layers.effective()?;
+        let game_root: PathBuf = Path::new(&source.get_absolute_game_root()?)
+            .components()
+            .collect();
+        config.game_root = Some(
+            game_root
+                .to_str()
+                .with_context(|| {
+                    format!("Failed to convert path to string: {}", game_root.display())
+                })?
+                .to_owned(),
+        );
+        if Path::new(&config.game_dir).is_absolute() {
+            config.game_dir = "./".into();
+        }
+        let clone = ConfigHandler::create(paths, layers, config, source.locked_config().clone())?;
+
+        let from = PathBuf::from(source.get_absolute_game_dir()?);
+        let to = PathBuf::from(clone.get_absolute_game_dir()?);
+        if from != to {
+            for dir in CLONED_DIRS.iter().chain(extra_dirs) {
+                copy_dir(&from.join(dir), &to.join(dir))?;
+            }
+        }
+
+        let root_str = root
+            .to_str()
+            .with_context(|| format!("Failed to convert path to string: {}", root.display()))?;
+        registry.add(dst, root_str)?;
+        registry.write()?;
+        Ok(root)
+    }
+}
+
+/// Copies a directory recursively, doing nothing if it does not exist.
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    if !from.is_dir() {
+        return Ok(());
+    }
+    for entry in WalkDir::new(from) {
+        let entry = entry?;
+        let target = to.join(entry.path().strip_prefix(from)?);
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&target)?;
+        } else if entry.file_type().is_file() {
+            fs::copy(entry.path(), &target)
+                .with_context(|| format!("failed to copy '{}'", entry.path().display()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Verifies registry operations and that the registry survives a write/read round trip.
+#[test]
+fn test_instance_registry() {
+    let file = std::env::temp_dir()
+        .join("gluon-test-instance-registry")
+        .join("instances.toml");
+    let mut registry = InstanceRegistry::default();
+    registry.add("a", "/games/a").unwrap();
+    registry.add("b", "/games/b").unwrap();
+    assert!(registry.add("a", "/games/other").is_err());
+    assert!(registry.add("../escape", "/games/escape").is_err());
+    assert_eq!(registry.active.as_deref(), Some("a"));
+
+    registry.switch("b").unwrap();
+    registry.rename("b", "c").unwrap();
+    assert_eq!(registry.active.as_deref(), Some("c"));
+    assert!(registry.switch("b").is_err());
+
+    registry.write_to(&file).unwrap();
+    let mut registry = InstanceRegistry::read_from(&file).unwrap();
+    assert_eq!(registry.get("c").unwrap().path, "/games/b");
+    registry.remove("c").unwrap();
+    assert!(registry.active.is_none());
+    assert_eq!(registry.instances.len(), 1);
+}
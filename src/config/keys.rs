@@ -0,0 +1,220 @@
+//! Dotted-key access to the runtime configuration.
+//!
+//! Keys use TOML dotted-key syntax, like `max_memory_size`, `mirror.assets` or
+//! `mods."fabric-api".version`. Every change is validated by deserializing the
+//! resulting `RuntimeConfig`, so a value of the wrong type or an unknown key is
+//! rejected before anything is written.
+
+use super::{ConfigHandler, RuntimeConfig};
+use anyhow::{Context, Result};
+use toml::{Table, Value};
+use toml_edit::Key;
+
+impl ConfigHandler {
+    /// Returns the effective value of a dotted key.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use gluon::config::ConfigHandler;
+    ///
+    /// let config = ConfigHandler::read()?;
+    /// println!("{}", config.get_value("mirror.assets")?);
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the key is not a valid dotted key
+    /// - `anyhow::Error` if the key is not set
+    pub fn get_value(&self, key: &str) -> Result<Value> {
+        let path = parse_key(key)?;
+        lookup(&Table::try_from(self.config())?, &path)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("config key '{key}' not found"))
+    }
+
+    /// Sets a dotted key of the runtime configuration.
+    ///
+    /// `value` is taken as-is for keys holding a string and parsed as a TOML
+    /// value otherwise, for example `4096`, `true` or `{ Fabric = "0.16.14" }`.
+    /// Values that are not valid TOML, or whose parsed type the key does not
+    /// accept, are taken as strings. The change is written by the next
+    /// `commit`.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use gluon::config::ConfigHandler;
+    ///
+    /// let mut config = ConfigHandler::read()?;
+    /// config.set_value("max_memory_size", "4096")?;
+    /// config.set_value("mirror.assets", "https://bmclapi2.bangbang93.com/assets/")?;
+    /// config.commit()?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the key is not a valid dotted key
+    /// - `anyhow::Error` if the key is not part of the runtime configuration
+    /// - `anyhow::Error` if the value has the wrong type for the key
+    pub fn set_value(&mut self, key: &str, value: &str) -> Result<()> {
+        let path = parse_key(key)?;
+        let table = Table::try_from(self.config())?;
+        let parsed = parse_value(value, lookup(&table, &path));
+        let config = match set_in(table.clone(), &path, parsed.clone(), key) {
+            // Unset keys give no type hint, so a string like `1.0` may have
+            // been read as a number; retry before reporting the typed error.
+            Err(err) if !parsed.is_str() => {
+                set_in(table, &path, Value::String(value.to_owned()), key).map_err(|_| err)?
+            }
+            result => result?,
+        };
+        if lookup(&Table::try_from(&config)?, &path).is_none() {
+            return Err(anyhow::anyhow!("unknown config key '{key}'"));
+        }
+        *self.config_mut() = config;
+        Ok(())
+    }
+
+    /// Removes a dotted key from the instance configuration.
+    ///
+    /// The key falls back to the user config or the built-in default. Keys
+    /// without a default, like a mod, are removed from the configuration. The
+    /// change is written by the next `commit`.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use gluon::config::ConfigHandler;
+    ///
+    /// let mut config = ConfigHandler::read()?;
+    /// config.unset_value("java_path")?;
+    /// config.commit()?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the key is not a valid dotted key
+    /// - `anyhow::Error` if the key is not set in the instance configuration
+    /// - `anyhow::Error` if the configuration is invalid without the key
+    pub fn unset_value(&mut self, key: &str) -> Result<()> {
+        let path = parse_key(key)?;
+        let mut table = Table::try_from(self.config())?;
+        if lookup(&table, &path).is_none() {
+            return Err(anyhow::anyhow!("config key '{key}' not found"));
+        }
+        match self.layers.base_value(&path) {
+            Some(value) => insert(&mut table, &path, value)?,
+            None => remove(&mut table, &path),
+        }
+        let config = validate(table, key)?;
+        self.layers.unset_instance(&path);
+        *self.config_mut() = config;
+        Ok(())
+    }
+}
+
+/// Splits a dotted key into its segments.
+fn parse_key(key: &str) -> Result<Vec<String>> {
+    let keys = Key::parse(key).with_context(|| format!("invalid config key '{key}'"))?;
+    Ok(keys.iter().map(|key| key.get().to_owned()).collect())
+}
+
+/// Returns the value at a key path.
+pub(super) fn lookup<'a>(table: &'a Table, path: &[String]) -> Option<&'a Value> {
+    let (last, parents) = path.split_last()?;
+    let mut table = table;
+    for key in parents {
+        table = table.get(key)?.as_table()?;
+    }
+    table.get(last)
+}
+
+/// Sets the value at a key path, creating missing tables on the way.
+pub(super) fn insert(table: &mut Table, path: &[String], value: Value) -> Result<()> {
+    let Some((last, parents)) = path.split_last() else {
+        return Err(anyhow::anyhow!("empty config key"));
+    };
+    let mut table = table;
+    for key in parents {
+        table = table
+            .entry(key.clone())
+            .or_insert_with(|| Value::Table(Table::new()))
+            .as_table_mut()
+            .ok_or_else(|| anyhow::anyhow!("'{key}' is not a table"))?;
+    }
+    table.insert(last.clone(), value);
+    Ok(())
+}
+
+/// Removes the value at a key path.
+pub(super) fn remove(table: &mut Table, path: &[String]) {
+    let Some((last, parents)) = path.split_last() else {
+        return;
+    };
+    let mut table = table;
+    for key in parents {
+        match table.get_mut(key).and_then(Value::as_table_mut) {
+            Some(inner) => table = inner,
+            None => return,
+        }
+    }
+    table.remove(last);
+}
+
+/// Parses a value given on the command line for a key whose value is `current`.
+fn parse_value(value: &str, current: Option<&Value>) -> Value {
+    let parsed = toml::from_str::<Table>(&format!("value = {value}"))
+        .ok()
+        .and_then(|mut table| table.remove("value"));
+    match parsed {
+        Some(parsed) if !matches!(current, Some(Value::String(_))) || parsed.is_table() => parsed,
+        _ => Value::String(value.to_owned()),
+    }
+}
+
+/// Sets the value at a key path and validates the resulting configuration.
+fn set_in(mut table: Table, path: &[String], value: Value, key: &str) -> Result<RuntimeConfig> {
+    insert(&mut table, path, value).with_context(|| format!("cannot set config key '{key}'"))?;
+    validate(table, key)
+}
+
+/// Deserializes a changed configuration, naming `key` in the error.
+pub(super) fn validate(table: Table, key: &str) -> Result<RuntimeConfig> {
+    Value::Table(table)
+        .try_into()
+        .with_context(|| format!("invalid value for config key '{key}'"))
+}
+
+/// Verifies dotted-key parsing, type validation and falling back on unset.
+#[test]
+fn test_config_keys() {
+    let mut handle = ConfigHandler::default();
+    handle.set_value("max_memory_size", "4096").unwrap();
+    handle.set_value("game_version", "1.21").unwrap();
+    handle
+        .set_value("mods.\"fabric-api\".version", "0.92.0")
+        .unwrap();
+    handle.set_value("mods.sodium.version", "1.0").unwrap();
+    handle.set_value("game_root", "21").unwrap();
+    assert_eq!(handle.config().max_memory_size, 4096);
+    assert_eq!(handle.config().game_version, "1.21");
+    assert_eq!(
+        handle.get_value("mods.\"fabric-api\".version").unwrap(),
+        Value::String("0.92.0".into())
+    );
+    assert_eq!(
+        handle.get_value("mods.sodium.version").unwrap(),
+        Value::String("1.0".into())
+    );
+    assert_eq!(handle.config().game_root.as_deref(), Some("21"));
+
+    assert!(handle.set_value("max_memory_size", "lots").is_err());
+    assert!(handle.set_value("mirror.unknown", "https://a/").is_err());
+    assert!(handle.get_value("mirror.unknown").is_err());
+
+    handle.unset_value("max_memory_size").unwrap();
+    assert_eq!(
+        handle.config().max_memory_size,
+        RuntimeConfig::default().max_memory_size
+    );
+    handle.unset_value("mods.\"fabric-api\"").unwrap();
+    assert!(handle.get_value("mods.\"fabric-api\"").is_err());
+}
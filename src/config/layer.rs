@@ -0,0 +1,388 @@
+//! Layered runtime configuration.
+//!
+//! The effective `RuntimeConfig` of an instance is merged from three layers,
+//! each one overriding the previous:
+//!
+//! 1. built-in defaults (`RuntimeConfig::default`)
+//! 2. the user config, `config.toml` in the user config directory
+//! 3. the instance `config.toml`
+//!
+//! Overrides from the environment or the command line are merged on top, but
+//! are never written back.
+//!
+//! Tables are merged key by key, any other value replaces the one of the lower
+//! layer. When the configuration is written back, the instance file keeps the
+//! keys it already had plus the values that differ from the lower layers, so
+//! instances only store what they override. The instance file is edited in
+//! place, keeping its comments and formatting.
+
+use super::keys;
+use super::migrate::{self, SchemaKind};
+use super::path::user_config_dir;
+use super::RuntimeConfig;
+use anyhow::{Context, Result};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+use toml_edit::DocumentMut;
+
+/// Keys whose tables replace the lower layer as a whole, like the loader enum.
+const REPLACE_KEYS: &[&str] = &["loader"];
+
+/// A layer of the runtime configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigLayer {
+    /// Built-in defaults.
+    Default,
+    /// `config.toml` in the user config directory.
+    User,
+    /// `config.toml` of the instance.
+    Instance,
+    /// Environment variable or command line override.
+    Override,
+}
+
+impl fmt::Display for ConfigLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigLayer::Default => write!(f, "default"),
+            ConfigLayer::User => write!(f, "user"),
+            ConfigLayer::Instance => write!(f, "instance"),
+            ConfigLayer::Override => write!(f, "override"),
+        }
+    }
+}
+
+/// A value of the effective configuration and the layer it comes from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigEntry {
+    /// Dotted key of the value, for example `mirror.assets`.
+    pub key: String,
+    /// The effective value.
+    pub value: Value,
+    /// The layer that provides the value.
+    pub layer: ConfigLayer,
+}
+
+/// The layers an instance's runtime configuration is merged from.
+#[derive(Debug, Clone)]
+pub(super) struct ConfigLayers {
+    /// Built-in defaults.
+    defaults: Table,
+    /// User config, empty if the file does not exist.
+    user: Table,
+    /// Path of the user config if it exists.
+    user_path: Option<PathBuf>,
+    /// Instance config as read from disk.
+    instance: Table,
+    /// Instance config document as read from disk, with its formatting.
+    document: DocumentMut,
+    /// Overrides for this invocation, never written.
+    overrides: Table,
+}
+
+impl Default for ConfigLayers {
+    /// Creates layers without a user config where the instance stores every value.
+    fn default() -> Self {
+        let defaults = Table::try_from(RuntimeConfig::default()).unwrap_or_default();
+        Self {
+            instance: defaults.clone(),
+            defaults,
+            user: Table::new(),
+            user_path: None,
+            document: DocumentMut::new(),
+            overrides: Table::new(),
+        }
+    }
+}
+
+impl ConfigLayers {
+    /// Returns the path of the user config, `config.toml` in the user config directory.
+    pub(super) fn user_config_path() -> Result<PathBuf> {
+        Ok(user_config_dir()?.join("config.toml"))
+    }
+
+    /// Loads the defaults and the user config on top of which `instance` is applied.
+    ///
+    /// `document` is the instance file `instance` was read from. A missing user
+    /// config, or a `user_path` of `None`, leaves the user layer empty. An outdated user config is migrated in place, like the
+    /// instance files.
+    pub(super) fn load(
+        user_path: Option<PathBuf>,
+        document: DocumentMut,
+        instance: Table,
+    ) -> Result<Self> {
+        let defaults = Table::try_from(RuntimeConfig::default())?;
+        let user_path = user_path.filter(|path| path.is_file());
+        let user = match &user_path {
+            Some(path) => migrate::read_versioned(path, SchemaKind::Runtime)
+                .with_context(|| format!("invalid user config '{}'", path.display()))?,
+            None => Table::new(),
+        };
+        Ok(Self {
+            defaults,
+            user,
+            user_path,
+            instance,
+            document,
+            overrides: Table::new(),
+        })
+    }
+
+    /// Loads the layers for an instance that is about to be created.
+    ///
+    /// The new instance stores every default the user config does not provide,
+    /// so values set in the user config stay inherited.
+    pub(super) fn for_new_instance(user_path: Option<PathBuf>) -> Result<Self> {
+        let mut layers = Self::load(user_path, DocumentMut::new(), Table::new())?;
+        layers.instance = layers
+            .defaults
+            .iter()
+            .filter(|(key, _)| !layers.user.contains_key(*key))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        Ok(layers)
+    }
+
+    /// Returns the path of the user config if it exists.
+    pub(super) fn user_path(&self) -> Option<&Path> {
+        self.user_path.as_deref()
+    }
+
+    /// Merges all layers into the effective runtime configuration.
+    pub(super) fn effective(&self) -> Result<RuntimeConfig> {
+        let mut table = self.stored();
+        merge(&mut table, &self.overrides);
+        Value::Table(table)
+            .try_into()
+            .context("invalid configuration after merging the user and instance config")
+    }
+
+    /// Serializes the instance layer for the given effective configuration.
+    ///
+    /// Only the changed values of the instance document are edited.
+    pub(super) fn instance_toml(&self, config: &RuntimeConfig) -> Result<String> {
+        Ok(migrate::versioned_document(&self.document, &self.instance_table(config)?)?.to_string())
+    }
+
+    /// Replaces the instance layer with what was written for the given effective configuration.
+    pub(super) fn set_instance(&mut self, config: &RuntimeConfig) -> Result<()> {
+        self.instance = self.instance_table(config)?;
+        self.document = migrate::versioned_document(&self.document, &self.instance)?;
+        Ok(())
+    }
+
+    /// Returns the value of a key path in the user config or the defaults.
+    pub(super) fn base_value(&self, path: &[String]) -> Option<Value> {
+        keys::lookup(&self.base(), path).cloned()
+    }
+
+    /// Overrides a key path for this invocation without writing it.
+    pub(super) fn set_override(&mut self, path: &[String], value: Value) {
+        // The path always has a table parent, so inserting cannot fail.
+        let _ = keys::insert(&mut self.overrides, path, value);
+    }
+
+    /// Removes a key path from the instance layer, so it is no longer written.
+    pub(super) fn unset_instance(&mut self, path: &[String]) {
+        keys::remove(&mut self.instance, path);
+    }
+
+    /// Lists every value of the given effective configuration with its layer.
+    pub(super) fn entries(&self, config: &RuntimeConfig) -> Result<Vec<ConfigEntry>> {
+        let effective = Table::try_from(config)?;
+        let instance = overrides(&effective, &self.base(), &self.instance);
+        let mut entries = Vec::new();
+        collect_entries(&effective, &mut Vec::new(), &mut |path, value| {
+            let layer = if contains_path(&self.overrides, path) {
+                ConfigLayer::Override
+            } else if contains_path(&instance, path) {
+                ConfigLayer::Instance
+            } else if contains_path(&self.user, path) {
+                ConfigLayer::User
+            } else {
+                ConfigLayer::Default
+            };
+            entries.push(ConfigEntry {
+                key: dotted_key(path),
+                value: value.clone(),
+                layer,
+            });
+        });
+        Ok(entries)
+    }
+
+    /// Returns the values the instance has to store for the given effective configuration.
+    ///
+    /// Overridden values that were not changed since are replaced by the
+    /// values they override.
+    fn instance_table(&self, config: &RuntimeConfig) -> Result<Table> {
+        let mut effective = Table::try_from(config)?;
+        let stored = self.stored();
+        collect_entries(&self.overrides, &mut Vec::new(), &mut |path, value| {
+            if keys::lookup(&effective, path) == Some(value) {
+                match keys::lookup(&stored, path) {
+                    Some(value) => {
+                        let _ = keys::insert(&mut effective, path, value.clone());
+                    }
+                    None => keys::remove(&mut effective, path),
+                }
+            }
+        });
+        Ok(overrides(&effective, &self.base(), &self.instance))
+    }
+
+    /// Merges the defaults, the user config and the instance config.
+    fn stored(&self) -> Table {
+        let mut table = self.base();
+        merge(&mut table, &self.instance);
+        table
+    }
+
+    /// Merges the defaults and the user config.
+    fn base(&self) -> Table {
+        let mut table = self.defaults.clone();
+        merge(&mut table, &self.user);
+        table
+    }
+}
+
+impl RuntimeConfig {
+    /// Reads the configuration shared by all instances.
+    ///
+    /// These are the built-in defaults merged with the user config, which is
+    /// what commands working outside of an instance use.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use gluon::config::RuntimeConfig;
+    /// let config = RuntimeConfig::read_user()?;
+    /// println!("{}", config.java_path);
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the user config cannot be read or is invalid
+    pub fn read_user() -> Result<Self> {
+        let user_path = ConfigLayers::user_config_path().ok();
+        ConfigLayers::load(user_path, DocumentMut::new(), Table::new())?.effective()
+    }
+}
+
+/// Merges `over` into `base`, recursing into tables present in both.
+fn merge(base: &mut Table, over: &Table) {
+    for (key, value) in over {
+        match (base.get_mut(key), value) {
+            (Some(Value::Table(base)), Value::Table(over))
+                if !REPLACE_KEYS.contains(&key.as_str()) =>
+            {
+                merge(base, over);
+            }
+            _ => {
+                base.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+/// Returns the part of `effective` that has to be stored on top of `base`.
+///
+/// Keys present in `existing` are always kept, other keys only if their value
+/// differs from `base`.
+fn overrides(effective: &Table, base: &Table, existing: &Table) -> Table {
+    let mut table = Table::new();
+    for (key, value) in effective {
+        let kept = existing.get(key);
+        match (value, base.get(key)) {
+            (Value::Table(value), Some(Value::Table(base)))
+                if !REPLACE_KEYS.contains(&key.as_str()) =>
+            {
+                let empty = Table::new();
+                let existing = kept.and_then(Value::as_table).unwrap_or(&empty);
+                let sub = overrides(value, base, existing);
+                if !sub.is_empty() || kept.is_some() {
+                    table.insert(key.clone(), Value::Table(sub));
+                }
+            }
+            (value, base) => {
+                if kept.is_some() || base != Some(value) {
+                    table.insert(key.clone(), value.clone());
+                }
+            }
+        }
+    }
+    table
+}
+
+/// Calls `f` with the key path of every non-table value in `table`.
+fn collect_entries(table: &Table, path: &mut Vec<String>, f: &mut impl FnMut(&[String], &Value)) {
+    for (key, value) in table {
+        path.push(key.clone());
+        if let Value::Table(table) = value {
+            collect_entries(table, path, f);
+        } else {
+            f(path, value);
+        }
+        path.pop();
+    }
+}
+
+/// Checks whether a value exists at the given key path.
+fn contains_path(table: &Table, path: &[String]) -> bool {
+    let Some((first, rest)) = path.split_first() else {
+        return true;
+    };
+    match table.get(first) {
+        Some(Value::Table(table)) => contains_path(table, rest),
+        Some(_) => rest.is_empty(),
+        None => false,
+    }
+}
+
+/// Joins a key path with dots, quoting segments that are not bare keys.
+fn dotted_key(path: &[String]) -> String {
+    path.iter()
+        .map(|segment| {
+            let bare = !segment.is_empty()
+                && segment
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'));
+            if bare {
+                segment.clone()
+            } else {
+                Value::String(segment.clone()).to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Verifies merging of layers and that only overrides are written to the instance.
+#[test]
+fn test_config_layers() {
+    let layers = ConfigLayers {
+        user: toml::from_str("java_path = '/opt/java'\n[mirror]\nassets = 'https://a/'").unwrap(),
+        instance: toml::from_str("max_memory_size = 2000\nloader = { Fabric = '0.16' }").unwrap(),
+        ..ConfigLayers::default()
+    };
+
+    let mut config = layers.effective().unwrap();
+    assert_eq!(config.java_path, "/opt/java");
+    assert_eq!(config.max_memory_size, 2000);
+    assert_eq!(config.mirror.assets.primary(), "https://a/");
+    assert_eq!(config.game_version, RuntimeConfig::default().game_version);
+
+    config.game_version = "1.21.1".into();
+    let table = layers.instance_table(&config).unwrap();
+    let keys: Vec<&str> = table.keys().map(String::as_str).collect();
+    assert_eq!(keys, ["max_memory_size", "game_version", "loader"]);
+
+    let entries = layers.entries(&config).unwrap();
+    let layer_of = |key: &str| entries.iter().find(|e| e.key == key).unwrap().layer;
+    assert_eq!(layer_of("java_path"), ConfigLayer::User);
+    assert_eq!(layer_of("mirror.assets"), ConfigLayer::User);
+    assert_eq!(layer_of("mirror.client"), ConfigLayer::Default);
+    assert_eq!(layer_of("loader.Fabric"), ConfigLayer::Instance);
+    assert_eq!(layer_of("game_version"), ConfigLayer::Instance);
+    assert_eq!(dotted_key(&["mods".into(), "a b".into()]), "mods.\"a b\"");
+}
@@ -3,6 +3,7 @@
 //! Contains auto-generated configuration with exact mod versions, file names,
 //! and checksums. These configurations should not be manually edited.
 
+use super::{MCLoader, RuntimeConfig};
 use clap::Subcommand;
 use mc_api::official;
 use modrinth_api::Version;
@@ -114,14 +115,65 @@ impl LockedModConfig {
     }
 }
 
-/// Locked configuration with resolved mod information.
+/// A locked download with the URL it was resolved to and its checksum.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct LockedFile {
+    /// Official download URL; mirrors replace its domain.
+    pub url: String,
+    /// SHA1 checksum of the file, if the metadata provides one.
+    pub sha1: Option<String>,
+}
+
+/// Locked game files of an instance.
+///
+/// Written by `install_mc` and used instead of the metadata endpoints as long
+/// as `game_version`, `vanilla` and `loader` in config.toml stay the same, so
+/// every install of the instance gets identical files.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct LockedGame {
+    /// `game_version` the files were resolved for.
+    pub game_version: String,
+    /// `vanilla` version the files were resolved for.
+    pub vanilla: String,
+    /// Loader the files were resolved for.
+    pub loader: MCLoader,
+    /// SHA1 checksum of the installed version JSON in its canonical form,
+    /// serialized with sorted keys.
+    pub version_sha1: String,
+    /// The official version JSON the installed version JSON is based on.
+    pub vanilla_json: LockedFile,
+    /// The loader profile merged into the official version JSON, as JSON.
+    pub loader_profile: Option<String>,
+    /// The asset index.
+    pub asset_index: LockedFile,
+    /// The client JAR.
+    pub client: LockedFile,
+    /// Libraries of the version JSON for all platforms, keyed by their path
+    /// inside the libraries directory.
+    pub libraries: BTreeMap<String, LockedFile>,
+}
+
+impl LockedGame {
+    /// Returns `true` if the files were resolved for the game configured in `config`.
+    #[must_use]
+    pub fn matches(&self, config: &RuntimeConfig) -> bool {
+        self.game_version == config.game_version
+            && self.vanilla == config.vanilla
+            && self.loader == config.loader
+    }
+}
+
+/// Locked configuration with resolved mod and game information.
 ///
-/// Contains the exact versions, file names, and checksums for all configured mods.
+/// Contains the exact versions, file names, and checksums for all configured
+/// mods and the installed game files.
 /// This file is auto-generated and should not be manually edited.
 #[derive(Debug, Deserialize, Serialize, Clone, Default)]
 pub struct LockedConfig {
     /// Mod configurations keyed by mod name.
     pub mods: Option<BTreeMap<String, LockedModConfig>>,
+    /// Game files written by the last install, see `LockedGame`.
+    pub game: Option<LockedGame>,
 }
 
 impl LockedConfig {
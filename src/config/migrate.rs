@@ -0,0 +1,216 @@
+//! Schema versioning and migration of configuration files.
+//!
+//! Every file written by gluon carries a top-level `schema_version`; files
+//! without one are version 0. When a file older than [`SCHEMA_VERSION`] is read,
+//! the migrations from its version up to the current one are applied in order,
+//! the original file is kept as `<file>.bak-v<version>` and the upgraded file is
+//! written back, keeping the comments and formatting of the original. Files
+//! with a newer version than this binary are rejected instead of being misread.
+
+use super::document;
+use super::file::write_atomic;
+use super::MCMirror;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+use toml_edit::DocumentMut;
+
+/// Schema version of the configuration files written by this binary.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Key of the schema version in every configuration file.
+const SCHEMA_VERSION_KEY: &str = "schema_version";
+
+/// Upgrades a table by one schema version.
+type Migration = fn(&mut Table);
+
+/// The kind of configuration file, which selects its migration chain.
+#[derive(Debug, Clone, Copy)]
+pub(super) enum SchemaKind {
+    /// `config.toml` of an instance or the user config.
+    Runtime,
+    /// `config.lock`.
+    Locked,
+    /// `account.toml`.
+    Account,
+    /// `accounts.toml`, the account store.
+    AccountStore,
+}
+
+impl SchemaKind {
+    /// Returns the migrations of this kind, where index `n` upgrades version `n` to `n + 1`.
+    fn migrations(self) -> &'static [Migration] {
+        match self {
+            SchemaKind::Runtime => &[fill_mirror_endpoints],
+            SchemaKind::Locked | SchemaKind::Account | SchemaKind::AccountStore => &[unchanged],
+        }
+    }
+}
+
+/// Removes the schema version from `table` and applies the missing migrations.
+///
+/// Returns the schema version the table had before migrating. `path` is only
+/// used in error messages.
+pub(super) fn upgrade(table: &mut Table, kind: SchemaKind, path: &Path) -> Result<u32> {
+    let version = match table.remove(SCHEMA_VERSION_KEY) {
+        None => 0,
+        Some(Value::Integer(version)) => u32::try_from(version)
+            .with_context(|| format!("invalid schema_version in '{}'", path.display()))?,
+        Some(_) => {
+            return Err(anyhow::anyhow!(
+                "invalid schema_version in '{}', expected an integer",
+                path.display()
+            ))
+        }
+    };
+    if version > SCHEMA_VERSION {
+        return Err(anyhow::anyhow!(
+            "'{}' has schema version {version}, but this gluon only supports up to \
+             {SCHEMA_VERSION}, please update gluon",
+            path.display()
+        ));
+    }
+    for migration in &kind.migrations()[usize::try_from(version)?..] {
+        migration(table);
+    }
+    Ok(version)
+}
+
+/// Reads a configuration file, migrating it in place if its schema is outdated.
+///
+/// The returned table does not contain `schema_version`.
+pub(super) fn read_versioned(path: &Path, kind: SchemaKind) -> Result<Table> {
+    Ok(read_document(path, kind)?.1)
+}
+
+/// Reads a configuration file as a document and a table, migrating it in place if needed.
+///
+/// The returned document is the file as written after migrating, the returned
+/// table holds its values without `schema_version`.
+pub(super) fn read_document(path: &Path, kind: SchemaKind) -> Result<(DocumentMut, Table)> {
+    let data = fs::read_to_string(path)?;
+    let mut document = document::parse(&data, path)?;
+    let mut table: Table =
+        toml::from_str(&data).with_context(|| format!("invalid TOML in '{}'", path.display()))?;
+    let version = upgrade(&mut table, kind, path)?;
+    if version < SCHEMA_VERSION {
+        let backup = backup_path(path, version);
+        if !backup.exists() {
+            fs::copy(path, &backup)?;
+        }
+        document = versioned_document(&document, &table)?;
+        write_atomic(path, document.to_string())?;
+        eprintln!(
+            "Migrated '{}' from schema version {version} to {SCHEMA_VERSION}, backup at '{}'",
+            path.display(),
+            backup.display()
+        );
+    }
+    Ok((document, table))
+}
+
+/// Serializes a table with the current schema version as its first key.
+pub(super) fn versioned_toml(table: &Table) -> Result<String> {
+    let mut versioned = Table::new();
+    versioned.insert(
+        SCHEMA_VERSION_KEY.into(),
+        Value::Integer(SCHEMA_VERSION.into()),
+    );
+    versioned.extend(table.clone());
+    Ok(toml::to_string_pretty(&versioned)?)
+}
+
+/// Edits a copy of `document` to hold `table` with the current schema version.
+///
+/// Comments and formatting of `document` are kept, a newly added schema
+/// version becomes the first key.
+pub(super) fn versioned_document(document: &DocumentMut, table: &Table) -> Result<DocumentMut> {
+    let mut versioned = Table::new();
+    versioned.insert(
+        SCHEMA_VERSION_KEY.into(),
+        Value::Integer(SCHEMA_VERSION.into()),
+    );
+    versioned.extend(table.clone());
+    let mut document = document.clone();
+    document::update(&mut document, &versioned)?;
+    document.sort_values_by(|a, _, b, _| {
+        (b.get() == SCHEMA_VERSION_KEY).cmp(&(a.get() == SCHEMA_VERSION_KEY))
+    });
+    Ok(document)
+}
+
+/// Serializes a value with the current schema version as its first key.
+pub(super) fn to_versioned_toml<T: Serialize>(value: &T) -> Result<String> {
+    versioned_toml(&Table::try_from(value)?)
+}
+
+/// Returns the backup path of a file before migrating it from `version`.
+fn backup_path(path: &Path, version: u32) -> PathBuf {
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(format!(".bak-v{version}"));
+    PathBuf::from(backup)
+}
+
+/// Version 0 to 1: nothing changes besides the added schema version.
+fn unchanged(_: &mut Table) {}
+
+/// Version 0 to 1: fills mirror endpoints missing from older files.
+///
+/// Older files lack the `NeoForge` endpoints. They are filled from the preset
+/// whose endpoints the mirror already uses, so a BMCLAPI mirror stays on BMCLAPI.
+fn fill_mirror_endpoints(table: &mut Table) {
+    let Some(Value::Table(mirror)) = table.get_mut("mirror") else {
+        return;
+    };
+    if !mirror.contains_key("version_manifest") {
+        return;
+    }
+    for preset in [MCMirror::official_mirror(), MCMirror::bmcl_mirror()] {
+        let Ok(preset) = Table::try_from(preset) else {
+            continue;
+        };
+        if mirror
+            .iter()
+            .any(|(key, value)| preset.get(key) == Some(value))
+        {
+            for (key, value) in preset {
+                mirror.entry(key).or_insert(value);
+            }
+            return;
+        }
+    }
+}
+
+/// Verifies that old files are migrated with a backup and newer files are rejected.
+#[test]
+fn test_migrate() {
+    let dir = std::env::temp_dir().join("gluon-test-migrate");
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("config.toml");
+    let _ = fs::remove_file(backup_path(&path, 0));
+    fs::write(
+        &path,
+        "# my mirror\n[mirror]\nversion_manifest = 'https://bmclapi2.bangbang93.com/'\n",
+    )
+    .unwrap();
+
+    let table = read_versioned(&path, SchemaKind::Runtime).unwrap();
+    assert!(!table.contains_key(SCHEMA_VERSION_KEY));
+    assert_eq!(
+        table["mirror"]["neoforge_neoforge"].as_str(),
+        Some(MCMirror::bmcl_mirror().neoforge_neoforge.primary())
+    );
+    assert!(backup_path(&path, 0).exists());
+    let data = fs::read_to_string(&path).unwrap();
+    assert!(data.starts_with("schema_version = 1\n# my mirror\n[mirror]\n"));
+    let written: Table = toml::from_str(&data).unwrap();
+    assert_eq!(
+        written[SCHEMA_VERSION_KEY].as_integer(),
+        Some(SCHEMA_VERSION.into())
+    );
+
+    let mut newer: Table = toml::from_str("schema_version = 999").unwrap();
+    assert!(upgrade(&mut newer, SchemaKind::Locked, &path).is_err());
+}
@@ -0,0 +1,505 @@
+//! Named mirrors.
+//!
+//! Besides the built-in `official` and `bmclapi` mirrors, named mirrors can be
+//! defined under `[mirrors.<name>]` in the user or instance config. A named
+//! mirror may define only some endpoints, so endpoints of different mirrors can
+//! be combined, for example BMCLAPI assets with the official version manifest:
+//!
+//! ```toml
+//! [mirrors.company]
+//! assets = "https://mirror.example.com/assets/"
+//! libraries = "https://mirror.example.com/maven/"
+//! ```
+//!
+//! Each endpoint of the current mirror is a URL or an ordered list of URLs.
+//! Downloads fail over to the next URL when a mirror is down or serves a file
+//! with the wrong hash:
+//!
+//! ```toml
+//! [mirror]
+//! assets = ["https://bmclapi2.bangbang93.com/assets/", "https://resources.download.minecraft.net/"]
+//! ```
+
+use super::{ConfigHandler, MCMirror, RuntimeConfig};
+use anyhow::{Context, Result};
+use log::warn;
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Names of the endpoints of a mirror, in the order of the `MCMirror` fields.
+pub const MIRROR_ENDPOINTS: &[&str] = &[
+    "version_manifest",
+    "assets",
+    "client",
+    "libraries",
+    "fabric_meta",
+    "fabric_maven",
+    "neoforge_forge",
+    "neoforge_neoforge",
+];
+
+/// Names of the built-in mirrors.
+pub const BUILTIN_MIRRORS: &[&str] = &["official", "bmclapi"];
+
+/// Endpoint URLs of a named mirror keyed by endpoint name.
+pub type MirrorDefinition = BTreeMap<String, String>;
+
+/// Ordered URLs of one mirror endpoint, the first one being the primary URL.
+///
+/// Written as a plain string when there is a single URL and as a list of
+/// strings otherwise. The list is never empty.
+///
+/// # Example
+/// ```
+/// use gluon::config::MirrorUrls;
+/// let mut urls = MirrorUrls::from("https://bmclapi2.bangbang93.com/maven/");
+/// urls.push_fallback("https://libraries.minecraft.net/");
+/// let (url, fallback_urls) = urls.join("org/ow2/asm/asm/9.6/asm-9.6.jar");
+/// assert_eq!(url, "https://bmclapi2.bangbang93.com/maven/org/ow2/asm/asm/9.6/asm-9.6.jar");
+/// assert_eq!(fallback_urls, ["https://libraries.minecraft.net/org/ow2/asm/asm/9.6/asm-9.6.jar"]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MirrorUrls(Vec<String>);
+
+impl MirrorUrls {
+    /// Returns the primary URL.
+    #[must_use]
+    pub fn primary(&self) -> &str {
+        &self.0[0]
+    }
+
+    /// Returns all URLs, primary URL first.
+    #[must_use]
+    pub fn urls(&self) -> &[String] {
+        &self.0
+    }
+
+    /// Appends a fallback URL, unless it is already one of the URLs.
+    pub fn push_fallback(&mut self, url: &str) {
+        if !self.0.iter().any(|known| known == url) {
+            self.0.push(url.to_owned());
+        }
+    }
+
+    /// Appends `path` to every URL, returning the primary URL and the fallback URLs.
+    #[must_use]
+    pub fn join(&self, path: &str) -> (String, Vec<String>) {
+        let mut urls = self.0.iter().map(|url| url.clone() + path);
+        let primary = urls.next().unwrap_or_default();
+        (primary, urls.collect())
+    }
+
+    /// Calls `f` with each URL until it succeeds.
+    ///
+    /// Used for metadata that is fetched directly instead of by the installer.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use gluon::config::MCMirror;
+    /// use mc_api::official::VersionManifest;
+    ///
+    /// let mirror = MCMirror::bmcl_mirror();
+    /// let manifest = mirror.version_manifest.try_each(VersionManifest::fetch)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    /// - `anyhow::Error` with the last failure if `f` fails for every URL
+    pub fn try_each<T>(&self, mut f: impl FnMut(&str) -> Result<T>) -> Result<T> {
+        let (last, fallbacks) = self.0.split_last().context("mirror endpoint has no URL")?;
+        for url in fallbacks {
+            match f(url) {
+                Ok(value) => return Ok(value),
+                Err(e) => warn!("mirror {url} failed: {e:#}, trying the next mirror"),
+            }
+        }
+        f(last).with_context(|| format!("all mirrors failed: {self}"))
+    }
+}
+
+impl From<&str> for MirrorUrls {
+    fn from(url: &str) -> Self {
+        Self(vec![url.to_owned()])
+    }
+}
+
+impl fmt::Display for MirrorUrls {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0.join(", "))
+    }
+}
+
+impl Serialize for MirrorUrls {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0.as_slice() {
+            [url] => url.serialize(serializer),
+            urls => urls.serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for MirrorUrls {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Urls {
+            One(String),
+            Many(Vec<String>),
+        }
+        match Urls::deserialize(deserializer)? {
+            Urls::One(url) => Ok(Self(vec![url])),
+            Urls::Many(urls) if !urls.is_empty() => Ok(Self(urls)),
+            Urls::Many(_) => Err(D::Error::custom(
+                "expected a URL or a non-empty list of URLs",
+            )),
+        }
+    }
+}
+
+impl MCMirror {
+    /// Returns the URLs of an endpoint by name.
+    ///
+    /// # Example
+    /// ```
+    /// use gluon::config::MCMirror;
+    /// let mirror = MCMirror::official_mirror();
+    /// assert_eq!(mirror.endpoint("assets"), Some(&mirror.assets));
+    /// assert_eq!(mirror.endpoint("unknown"), None);
+    /// ```
+    #[must_use]
+    pub fn endpoint(&self, name: &str) -> Option<&MirrorUrls> {
+        let urls = match name {
+            "version_manifest" => &self.version_manifest,
+            "assets" => &self.assets,
+            "client" => &self.client,
+            "libraries" => &self.libraries,
+            "fabric_meta" => &self.fabric_meta,
+            "fabric_maven" => &self.fabric_maven,
+            "neoforge_forge" => &self.neoforge_forge,
+            "neoforge_neoforge" => &self.neoforge_neoforge,
+            _ => return None,
+        };
+        Some(urls)
+    }
+
+    /// Sets the URL of an endpoint by name, dropping its fallback URLs.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the endpoint name is unknown
+    pub fn set_endpoint(&mut self, name: &str, url: &str) -> Result<()> {
+        *self.endpoint_mut(name)? = url.into();
+        Ok(())
+    }
+
+    /// Sets all URLs of an endpoint by name.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the endpoint name is unknown
+    pub fn set_endpoint_urls(&mut self, name: &str, urls: MirrorUrls) -> Result<()> {
+        *self.endpoint_mut(name)? = urls;
+        Ok(())
+    }
+
+    /// Appends a fallback URL to an endpoint by name.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the endpoint name is unknown
+    pub fn add_fallback(&mut self, name: &str, url: &str) -> Result<()> {
+        self.endpoint_mut(name)?.push_fallback(url);
+        Ok(())
+    }
+
+    /// Returns the URLs of an endpoint by name for editing.
+    fn endpoint_mut(&mut self, name: &str) -> Result<&mut MirrorUrls> {
+        let urls = match name {
+            "version_manifest" => &mut self.version_manifest,
+            "assets" => &mut self.assets,
+            "client" => &mut self.client,
+            "libraries" => &mut self.libraries,
+            "fabric_meta" => &mut self.fabric_meta,
+            "fabric_maven" => &mut self.fabric_maven,
+            "neoforge_forge" => &mut self.neoforge_forge,
+            "neoforge_neoforge" => &mut self.neoforge_neoforge,
+            _ => return Err(unknown_endpoint(name)),
+        };
+        Ok(urls)
+    }
+
+    /// Returns the primary URLs of all endpoints as a mirror definition.
+    #[must_use]
+    pub fn to_definition(&self) -> MirrorDefinition {
+        MIRROR_ENDPOINTS
+            .iter()
+            .filter_map(|name| {
+                Some((
+                    (*name).to_owned(),
+                    self.endpoint(name)?.primary().to_owned(),
+                ))
+            })
+            .collect()
+    }
+}
+
+impl RuntimeConfig {
+    /// Returns the endpoints of a named mirror.
+    ///
+    /// Mirrors defined in `mirrors` take precedence over the built-in mirrors.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if no mirror with this name exists
+    pub fn named_mirror(&self, name: &str) -> Result<MirrorDefinition> {
+        if let Some(definition) = self.mirrors.get(name) {
+            return Ok(definition.clone());
+        }
+        match name {
+            "official" => Ok(MCMirror::official_mirror().to_definition()),
+            "bmclapi" => Ok(MCMirror::bmcl_mirror().to_definition()),
+            _ => Err(anyhow::anyhow!(
+                "mirror '{name}' not found, run 'gluon mirror list' to see mirrors"
+            )),
+        }
+    }
+
+    /// Returns the names of all mirrors, built-in mirrors first.
+    #[must_use]
+    pub fn mirror_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = BUILTIN_MIRRORS.iter().map(ToString::to_string).collect();
+        names.extend(
+            self.mirrors
+                .keys()
+                .filter(|name| !BUILTIN_MIRRORS.contains(&name.as_str()))
+                .cloned(),
+        );
+        names
+    }
+
+    /// Defines a named mirror, replacing an existing definition with the same name.
+    ///
+    /// # Example
+    /// ```
+    /// use gluon::config::{MirrorDefinition, RuntimeConfig};
+    /// let mut config = RuntimeConfig::default();
+    /// let definition = MirrorDefinition::from([(
+    ///     "assets".to_string(),
+    ///     "https://mirror.example.com/assets/".to_string(),
+    /// )]);
+    /// config.add_mirror("company", definition).unwrap();
+    /// config.use_mirror("company", &[]).unwrap();
+    /// assert_eq!(config.mirror.assets.primary(), "https://mirror.example.com/assets/");
+    /// ```
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the definition is empty or has an unknown endpoint
+    pub fn add_mirror(&mut self, name: &str, definition: MirrorDefinition) -> Result<()> {
+        if definition.is_empty() {
+            return Err(anyhow::anyhow!("mirror '{name}' defines no endpoints"));
+        }
+        if let Some(endpoint) = definition
+            .keys()
+            .find(|endpoint| !MIRROR_ENDPOINTS.contains(&endpoint.as_str()))
+        {
+            return Err(unknown_endpoint(endpoint));
+        }
+        self.mirrors.insert(name.to_owned(), definition);
+        Ok(())
+    }
+
+    /// Removes a user-defined mirror.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if no user-defined mirror with this name exists
+    pub fn remove_mirror(&mut self, name: &str) -> Result<()> {
+        self.mirrors
+            .remove(name)
+            .map(|_| ())
+            .ok_or_else(|| anyhow::anyhow!("mirror '{name}' is not a user-defined mirror"))
+    }
+
+    /// Switches endpoints of the current mirror to a named mirror.
+    ///
+    /// Only the given endpoints are switched, or every endpoint the named
+    /// mirror defines if `endpoints` is empty. Other endpoints are kept.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if no mirror with this name exists
+    /// - `anyhow::Error` if an endpoint is unknown or not defined by the mirror
+    pub fn use_mirror(&mut self, name: &str, endpoints: &[String]) -> Result<()> {
+        for (endpoint, url) in self.mirror_urls(name, endpoints)? {
+            self.mirror.set_endpoint(&endpoint, &url)?;
+        }
+        Ok(())
+    }
+
+    /// Appends a named mirror as fallback to endpoints of the current mirror.
+    ///
+    /// Endpoints are selected like in [`RuntimeConfig::use_mirror`]. Downloads
+    /// from these endpoints try the named mirror after the current URLs fail.
+    ///
+    /// # Example
+    /// ```
+    /// use gluon::config::{MCMirror, RuntimeConfig};
+    /// let mut config = RuntimeConfig::default();
+    /// config.use_mirror("bmclapi", &[]).unwrap();
+    /// config.add_fallback_mirror("official", &[]).unwrap();
+    /// assert_eq!(
+    ///     config.mirror.assets.urls(),
+    ///     [MCMirror::bmcl_mirror().assets.primary(), MCMirror::official_mirror().assets.primary()]
+    /// );
+    /// ```
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if no mirror with this name exists
+    /// - `anyhow::Error` if an endpoint is unknown or not defined by the mirror
+    pub fn add_fallback_mirror(&mut self, name: &str, endpoints: &[String]) -> Result<()> {
+        for (endpoint, url) in self.mirror_urls(name, endpoints)? {
+            self.mirror.add_fallback(&endpoint, &url)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the names of the mirrors defining `url` for an endpoint.
+    #[must_use]
+    pub fn mirrors_of_url(&self, endpoint: &str, url: &str) -> Vec<String> {
+        self.mirror_names()
+            .into_iter()
+            .filter(|name| {
+                self.named_mirror(name).is_ok_and(|definition| {
+                    definition.get(endpoint).map(String::as_str) == Some(url)
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the URLs a named mirror defines for the given endpoints.
+    ///
+    /// All endpoints of the mirror are returned if `endpoints` is empty.
+    fn mirror_urls(&self, name: &str, endpoints: &[String]) -> Result<MirrorDefinition> {
+        let mut definition = self.named_mirror(name)?;
+        if endpoints.is_empty() {
+            return Ok(definition);
+        }
+        endpoints
+            .iter()
+            .map(|endpoint| {
+                let url = definition.remove(endpoint).ok_or_else(|| {
+                    if MIRROR_ENDPOINTS.contains(&endpoint.as_str()) {
+                        anyhow::anyhow!("mirror '{name}' does not define endpoint '{endpoint}'")
+                    } else {
+                        unknown_endpoint(endpoint)
+                    }
+                })?;
+                Ok((endpoint.clone(), url))
+            })
+            .collect()
+    }
+}
+
+impl ConfigHandler {
+    /// Removes a user-defined mirror from the instance config.
+    ///
+    /// Mirrors defined in the user config are shared by every instance and
+    /// are not removed, since only the instance config is written. The
+    /// change is written by the next `commit`.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the mirror is defined in the user config
+    /// - `anyhow::Error` if no user-defined mirror with this name exists
+    pub fn remove_mirror(&mut self, name: &str) -> Result<()> {
+        let path = ["mirrors".to_owned(), name.to_owned()];
+        if self.layers.base_value(&path).is_some() {
+            let user_config = self.user_config_path().map_or_else(
+                || "the user config".to_owned(),
+                |path| format!("the user config '{}'", path.display()),
+            );
+            return Err(anyhow::anyhow!(
+                "mirror '{name}' is defined in {user_config}, remove it there"
+            ));
+        }
+        self.config_mut().remove_mirror(name)
+    }
+}
+
+/// Returns the error for an unknown endpoint name.
+fn unknown_endpoint(name: &str) -> anyhow::Error {
+    anyhow::anyhow!(
+        "unknown mirror endpoint '{name}', expected one of: {}",
+        MIRROR_ENDPOINTS.join(", ")
+    )
+}
+
+/// Verifies mixing endpoints of named mirrors.
+#[test]
+fn test_named_mirrors() {
+    let mut config = RuntimeConfig::default();
+    config
+        .use_mirror("bmclapi", &["assets".to_owned()])
+        .unwrap();
+    assert_eq!(config.mirror.assets, MCMirror::bmcl_mirror().assets);
+    assert_eq!(
+        config.mirror.version_manifest,
+        MCMirror::official_mirror().version_manifest
+    );
+    assert_eq!(
+        config.mirrors_of_url("assets", config.mirror.assets.primary()),
+        ["bmclapi"]
+    );
+    assert!(config
+        .use_mirror("bmclapi", &["unknown".to_owned()])
+        .is_err());
+
+    let company = MirrorDefinition::from([("libraries".to_owned(), "https://m/".to_owned())]);
+    config.add_mirror("company", company).unwrap();
+    assert!(config
+        .use_mirror("company", &["assets".to_owned()])
+        .is_err());
+    config.use_mirror("company", &[]).unwrap();
+    assert_eq!(config.mirror.libraries.primary(), "https://m/");
+    assert_eq!(config.mirror.assets, MCMirror::bmcl_mirror().assets);
+    assert_eq!(config.mirror_names(), ["official", "bmclapi", "company"]);
+    assert!(config.remove_mirror("official").is_err());
+}
+
+/// Verifies reading, writing and failing over between endpoint URLs.
+#[test]
+fn test_mirror_fallbacks() {
+    let mut config = RuntimeConfig::default();
+    config.use_mirror("bmclapi", &[]).unwrap();
+    config
+        .add_fallback_mirror("official", &["libraries".to_owned()])
+        .unwrap();
+    config
+        .add_fallback_mirror("official", &["libraries".to_owned()])
+        .unwrap();
+    let official = MCMirror::official_mirror().libraries;
+    assert_eq!(config.mirror.libraries.urls().len(), 2);
+    assert_eq!(config.mirror.libraries.urls()[1], official.primary());
+
+    let written = toml::to_string(&config).unwrap();
+    assert!(written.contains("libraries = [\"https://bmclapi2.bangbang93.com/maven/\", "));
+    assert!(written.contains("assets = \"https://bmclapi2.bangbang93.com/assets/\""));
+    let read: RuntimeConfig = toml::from_str(&written).unwrap();
+    assert_eq!(read.mirror.libraries, config.mirror.libraries);
+    assert!(toml::Value::Array(Vec::new())
+        .try_into::<MirrorUrls>()
+        .is_err());
+
+    let tried = config
+        .mirror
+        .libraries
+        .try_each(|url| {
+            if url == official.primary() {
+                Ok(url.to_owned())
+            } else {
+                Err(anyhow::anyhow!("down"))
+            }
+        })
+        .unwrap();
+    assert_eq!(tried, official.primary());
+    assert!(config
+        .mirror
+        .assets
+        .try_each(|_| Err::<(), _>(anyhow::anyhow!("down")))
+        .is_err());
+}
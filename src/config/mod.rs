@@ -6,15 +6,39 @@
 //! ## Components
 //!
 //! - [`RuntimeConfig`]: User-editable configuration for game settings and mods
-//! - [`LockedConfig`]: Auto-generated configuration with exact mod versions
+//! - [`LockedConfig`]: Auto-generated configuration with exact mod versions and game files
 //! - [`UserAccount`]: Authentication information (offline or Microsoft)
+//! - [`AccountStore`]: Accounts shared by all instances and the active account
+//! - [`InstanceRegistry`]: Named instances and the active instance
+//! - [`ConfigEntry`]: A value of the effective configuration and its [`ConfigLayer`]
 //! - [`ConfigHandler`]: Main handler for reading and writing all configurations
 //!
 //! ## Configuration Files
 //!
 //! - `config.toml`: User-editable runtime configuration
-//! - `config.lock`: Auto-generated locked configuration with exact mod versions
-//! - `account.toml`: User account and authentication information
+//! - `config.lock`: Auto-generated locked configuration with exact mod versions and game files
+//! - `accounts.toml`: Account store in the user config directory
+//! - `instances.toml`: Instance registry in the user config directory
+//! - `templates/<name>.toml`: Templates for new instances in the user config directory
+//!
+//! Changes are only written by `ConfigHandler::commit` (or `transaction`), not
+//! when a handler is dropped, unless autocommit is enabled with
+//! `ConfigHandler::set_autocommit`.
+//!
+//! Files are written atomically, and a handler holds an advisory lock on its
+//! instance (`.gluon.lock`) for its whole lifetime, so concurrent `gluon`
+//! processes cannot clobber each other's writes.
+//!
+//! `config.toml`, `config.lock` and `accounts.toml` carry a `schema_version`.
+//! Older files are migrated in place when read, keeping a `<file>.bak-v<version>`
+//! backup, and files newer than [`SCHEMA_VERSION`] are rejected.
+//!
+//! ## Configuration Layers
+//!
+//! The runtime configuration is merged from the built-in defaults, the user
+//! `config.toml` in the user config directory, and the instance `config.toml`,
+//! each overriding the previous one. Instances only store the values they
+//! override.
 //!
 //! # Example
 //! ```no_run
@@ -23,26 +47,53 @@
 //! let mut config = ConfigHandler::read()?;
 //! println!("Game version: {}", config.config().game_version);
 //! config.add_mod_local("fabric-api.jar")?;
-//! config.write_all()?;
+//! config.commit()?;
 //! # Ok::<(), anyhow::Error>(())
 //! ```
 
 mod account;
+mod document;
+mod file;
+mod instance;
+mod keys;
+mod layer;
 mod locked;
+mod migrate;
+mod mirror;
 mod mod_manage;
+mod overrides;
 mod path;
 mod runtime;
+mod store;
+mod template;
 mod user;
+mod vault;
 
 // Re-export public types
-pub use locked::{LockedConfig, LockedModConfig, VersionType};
-pub use runtime::{MCLoader, MCMirror, ModConfig, RuntimeConfig};
+pub(crate) use file::LOCK_FILE;
+pub use instance::{InstanceEntry, InstanceRegistry};
+pub use layer::{ConfigEntry, ConfigLayer};
+pub use locked::{LockedConfig, LockedFile, LockedGame, LockedModConfig, VersionType};
+pub use migrate::SCHEMA_VERSION;
+pub use mirror::{MirrorDefinition, MirrorUrls, BUILTIN_MIRRORS, MIRROR_ENDPOINTS};
+pub use overrides::{RuntimeOverrides, ENV_GAME_DIR, ENV_JAVA_PATH, ENV_MAX_MEMORY_SIZE};
+pub use path::{user_config_dir, user_data_dir};
+pub use runtime::{
+    Hooks, MCLoader, MCMirror, ModConfig, RuntimeConfig, BUILTIN_JVM_PRESETS, DEFAULT_JVM_ARGS,
+};
+pub use store::{AccountStore, Encryption};
+pub use template::templates_dir;
 pub use user::UserAccount;
+pub use vault::{read_passphrase, ENV_PASSPHRASE};
 
 use anyhow::Result;
+use file::{InstanceLock, StagedFile};
+use layer::ConfigLayers;
+use migrate::SchemaKind;
 use std::fs;
 use std::ops::{Deref, DerefMut};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 /// Mutable access counter wrapper.
 ///
@@ -88,6 +139,18 @@ impl<T> Mac<T> {
     pub fn has_mut_accessed(&self) -> bool {
         self.has_mut_accessed
     }
+
+    /// Replaces the wrapped value without setting the mutable access flag.
+    #[inline]
+    pub fn set_unmarked(&mut self, value: T) {
+        self.value = value;
+    }
+
+    /// Clears the mutable access flag after the value was written.
+    #[inline]
+    pub fn reset(&mut self) {
+        self.has_mut_accessed = false;
+    }
 }
 
 impl<T> From<T> for Mac<T> {
@@ -115,16 +178,21 @@ impl<T> DerefMut for Mac<T> {
 
 /// Configuration file paths.
 ///
-/// Stores the paths for the three configuration files:
-/// config.toml, config.lock, and account.toml.
+/// Stores the paths for the configuration files of an instance: config.toml,
+/// config.lock, and the legacy account.toml, which is moved into the account
+/// store when the instance is read.
 #[derive(Debug, Clone)]
 pub struct ConfigPaths {
     /// Path to config.toml file.
     config: String,
     /// Path to config.lock file.
     locked_config: String,
-    /// Path to account.toml file.
+    /// Path to the legacy account.toml file.
     user_account: String,
+    /// Path to the account store, `AccountStore::store_path` if `None`.
+    account_store: Option<PathBuf>,
+    /// Path to the user config, `config.toml` in the user config directory if `None`.
+    user_config: Option<PathBuf>,
 }
 
 impl Default for ConfigPaths {
@@ -134,21 +202,134 @@ impl Default for ConfigPaths {
             config: "config.toml".into(),
             locked_config: "config.lock".into(),
             user_account: "account.toml".into(),
+            account_store: None,
+            user_config: None,
         }
     }
 }
 
-/// # Writing with Mutable Access
-///
-/// When a mutable reference is used with `ConfigHandler.config`, the `config` field is
-/// updated and written to the file upon `ConfigHandler`'s drop.
-/// In contrast, the `locked_config` field will not write.
-///
+impl ConfigPaths {
+    /// Returns the instance root, the directory containing config.toml.
+    fn root(&self) -> PathBuf {
+        match Path::new(&self.config).parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        }
+    }
+
+    /// Creates paths for the configuration files inside the given instance root.
+    ///
+    /// # Example
+    /// ```
+    /// use gluon::config::ConfigPaths;
+    /// use std::path::Path;
+    ///
+    /// let paths = ConfigPaths::from_root(Path::new("/games/survival"));
+    /// ```
+    #[must_use]
+    pub fn from_root(root: &Path) -> Self {
+        Self {
+            config: root.join("config.toml").display().to_string(),
+            locked_config: root.join("config.lock").display().to_string(),
+            user_account: root.join("account.toml").display().to_string(),
+            account_store: None,
+            user_config: None,
+        }
+    }
+
+    /// Uses the given account store instead of the one in the user config directory.
+    ///
+    /// # Example
+    /// ```
+    /// use gluon::config::ConfigPaths;
+    /// use std::path::Path;
+    ///
+    /// let paths = ConfigPaths::from_root(Path::new("/games/survival"))
+    ///     .with_account_store(Path::new("/games/accounts.toml"));
+    /// ```
+    #[must_use]
+    pub fn with_account_store(mut self, path: &Path) -> Self {
+        self.account_store = Some(path.to_path_buf());
+        self
+    }
+
+    /// Uses the given user config instead of the one in the user config directory.
+    ///
+    /// # Example
+    /// ```
+    /// use gluon::config::ConfigPaths;
+    /// use std::path::Path;
+    ///
+    /// let paths = ConfigPaths::from_root(Path::new("/games/survival"))
+    ///     .with_user_config(Path::new("/games/config.toml"));
+    /// ```
+    #[must_use]
+    pub fn with_user_config(mut self, path: &Path) -> Self {
+        self.user_config = Some(path.to_path_buf());
+        self
+    }
+
+    /// Returns the path of the account store.
+    fn account_store(&self) -> Result<PathBuf> {
+        match &self.account_store {
+            Some(path) => Ok(path.clone()),
+            None => AccountStore::store_path(),
+        }
+    }
+
+    /// Returns the path of the user config, `None` if the user config directory is unknown.
+    fn user_config(&self) -> Option<PathBuf> {
+        match &self.user_config {
+            Some(path) => Some(path.clone()),
+            None => ConfigLayers::user_config_path().ok(),
+        }
+    }
+
+    /// Resolves the configuration paths of the instance this process works on.
+    ///
+    /// The instance is resolved in this order:
+    /// 1. the instance selected with `ConfigHandler::select_instance` (`--instance`)
+    /// 2. the first directory containing `config.toml`, searching upward from the
+    ///    current directory
+    /// 3. the active instance of the instance registry
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the instance registry cannot be read
+    /// - `anyhow::Error` if the selected instance is not registered
+    /// - `anyhow::Error` if no instance can be resolved
+    pub fn resolve() -> Result<Self> {
+        if let Some(name) = instance::selected_instance() {
+            return Ok(InstanceRegistry::read()?.get(&name)?.paths());
+        }
+        if let Some(root) = ConfigHandler::try_find_config_root() {
+            return Ok(Self::from_root(&root));
+        }
+        let registry = InstanceRegistry::read()?;
+        if let Some(active) = registry.active.as_deref() {
+            return Ok(registry.get(active)?.paths());
+        }
+        Err(anyhow::anyhow!(
+            "config.toml not found, run 'gluon init' or 'gluon instance create <name>' first"
+        ))
+    }
+}
+
 /// Main handler for managing all launcher configurations.
 ///
 /// Provides methods for reading, writing, and modifying runtime config,
-/// locked config, and user account information. Automatically writes
-/// modified configurations when dropped.
+/// locked config, and user account information.
+///
+/// # Writing with Mutable Access
+///
+/// Mutable access through `config_mut`, `locked_config_mut` or
+/// `user_account_mut` marks that file as modified. Modified files are written
+/// by `commit` or `transaction`, which return write errors to the caller.
+/// Dropping a handler discards uncommitted changes unless autocommit was
+/// enabled with `set_autocommit`.
+///
+/// Handlers created by `read` or `init` hold an advisory lock on their
+/// instance until the last clone is dropped. Other processes and threads fail
+/// to read the instance with an "instance is busy" error in the meantime.
 #[derive(Debug, Clone)]
 pub struct ConfigHandler {
     /// Runtime configuration with mutable access tracking.
@@ -159,6 +340,12 @@ pub struct ConfigHandler {
     user_account: Mac<UserAccount>,
     /// Paths to configuration files.
     paths: ConfigPaths,
+    /// Layers the runtime configuration is merged from.
+    layers: ConfigLayers,
+    /// Lock on the instance, shared by clones of this handler.
+    _lock: Option<Arc<InstanceLock>>,
+    /// Whether modified configurations are committed on drop.
+    autocommit: bool,
 }
 
 impl Default for ConfigHandler {
@@ -169,6 +356,9 @@ impl Default for ConfigHandler {
             locked_config: Mac::new(LockedConfig::default()),
             user_account: Mac::new(UserAccount::default()),
             paths: ConfigPaths::default(),
+            layers: ConfigLayers::default(),
+            _lock: None,
+            autocommit: false,
         }
     }
 }
@@ -184,7 +374,7 @@ impl ConfigHandler {
     /// Returns a mutable reference to the runtime configuration.
     ///
     /// Accessing the config mutably marks it as modified, causing it to be
-    /// written when the handler is dropped.
+    /// written by the next `commit`.
     #[inline]
     pub fn config_mut(&mut self) -> &mut RuntimeConfig {
         &mut self.config
@@ -200,13 +390,17 @@ impl ConfigHandler {
     /// Returns a mutable reference to the locked configuration.
     ///
     /// Accessing the locked config mutably marks it as modified, causing it to be
-    /// written when the handler is dropped.
+    /// written by the next `commit`.
     #[inline]
     pub fn locked_config_mut(&mut self) -> &mut LockedConfig {
         &mut self.locked_config
     }
 
-    /// Returns a reference to the user account.
+    /// Returns a reference to the account this instance uses.
+    ///
+    /// This is the stored account selected by the `account` setting, or the
+    /// active account of the account store. Without stored accounts, it is an
+    /// unsaved offline account.
     #[inline]
     #[must_use]
     pub fn user_account(&self) -> &UserAccount {
@@ -216,7 +410,7 @@ impl ConfigHandler {
     /// Returns a mutable reference to the user account.
     ///
     /// Accessing the user account mutably marks it as modified, causing it to be
-    /// written when the handler is dropped.
+    /// written to the account store by the next `commit`.
     #[inline]
     pub fn user_account_mut(&mut self) -> &mut UserAccount {
         &mut self.user_account
@@ -249,6 +443,8 @@ impl ConfigHandler {
     /// Creates configuration files at the specified paths if they don't exist.
     /// If `config.toml` already exists (found by searching upward from the
     /// config file location), initialization is skipped (idempotent).
+    /// Values provided by the user config are left out of the new `config.toml`
+    /// so they stay inherited.
     ///
     /// Returns `true` if initialization was actually performed, `false` if
     /// configuration already exists and was skipped.
@@ -261,55 +457,94 @@ impl ConfigHandler {
     /// ```
     ///
     /// # Errors
+    /// - `anyhow::Error` if the user config contains invalid TOML
+    /// - `anyhow::Error` if the instance is locked by another process
     /// - `anyhow::Error` if the configuration files cannot be written.
     pub fn init_for_paths(paths: ConfigPaths) -> Result<bool> {
         if path::try_find_config_root_from(Path::new(&paths.config)).is_some() {
             return Ok(false);
         }
+        let layers = ConfigLayers::for_new_instance(paths.user_config())?;
+        let config = layers.effective()?;
+        ConfigHandler::create(paths, layers, config, LockedConfig::default())?;
+        Ok(true)
+    }
+
+    /// Writes all configuration files of a new instance and returns its handler.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the instance is locked by another process
+    /// - `anyhow::Error` if the configuration files cannot be written
+    fn create(
+        paths: ConfigPaths,
+        layers: ConfigLayers,
+        config: RuntimeConfig,
+        locked_config: LockedConfig,
+    ) -> Result<Self> {
+        let lock = InstanceLock::acquire(&paths.root())?;
         let handle = Self {
-            config: Mac::new(RuntimeConfig::default()),
-            locked_config: Mac::new(LockedConfig::default()),
+            config: Mac::new(config),
+            locked_config: Mac::new(locked_config),
             user_account: Mac::new(UserAccount::default()),
             paths,
+            layers,
+            _lock: Some(lock),
+            autocommit: false,
         };
         handle.write_all()?;
-        Ok(true)
+        Ok(handle)
     }
 
-    /// Reads configuration files by searching for config.toml upward from current directory.
+    /// Reads the configuration files of the current instance.
     ///
-    /// Searches upward from the current working directory until it finds config.toml,
-    /// then uses that directory as the root for all configuration files.
-    /// Reads config.toml, config.lock, and account.toml. If config.lock or
-    /// account.toml don't exist, they are created with default values.
+    /// The instance is resolved by `ConfigPaths::resolve`: the selected instance
+    /// (`--instance`), then the first config.toml found searching upward from the
+    /// current directory, then the active instance of the registry.
+    /// Reads config.toml and config.lock, and the account from the account
+    /// store. If config.lock doesn't exist, it is created with default values.
     ///
     /// # Errors
-    /// - `anyhow::Error` if config.toml is not found (search reaches filesystem root)
+    /// - `anyhow::Error` if no instance can be resolved
+    /// - `anyhow::Error` if the instance is locked by another process
     /// - `anyhow::Error` if config.toml contains invalid TOML
     /// - `anyhow::Error` if configuration validation fails
     pub fn read() -> Result<Self> {
-        let root = Self::try_find_config_root()
-            .ok_or_else(|| anyhow::anyhow!("config.toml not found, run 'gluon init' first"))?;
-        let paths = ConfigPaths {
-            config: root.join("config.toml").display().to_string(),
-            locked_config: root.join("config.lock").display().to_string(),
-            user_account: root.join("account.toml").display().to_string(),
-        };
-        ConfigHandler::read_from_paths(paths)
+        ConfigHandler::read_from_paths(ConfigPaths::resolve()?)
     }
 
     /// Reads configuration files from custom paths.
     ///
-    /// Reads config.toml, config.lock, and account.toml from the specified paths.
-    /// If config.lock or account.toml don't exist, they are created with default values.
+    /// Reads config.toml and config.lock from the specified paths, and the
+    /// account selected by the `account` setting, or the active account, from
+    /// the account store. If config.lock doesn't exist, it is created with
+    /// default values. A legacy account.toml is moved into the account store.
+    /// The runtime configuration is config.toml merged over the user config and
+    /// the built-in defaults. Files with an older `schema_version` are migrated
+    /// in place, keeping a backup of the original. Overrides from the
+    /// `GLUON_*` environment variables are not applied, see
+    /// `ConfigHandler::apply_overrides`. The instance stays locked until the
+    /// returned handler and all its clones are dropped.
     ///
     /// # Errors
     /// - `anyhow::Error` if config.toml does not exist
-    /// - `anyhow::Error` if config.toml contains invalid TOML
+    /// - `anyhow::Error` if the instance is locked by another process
+    /// - `anyhow::Error` if config.toml or the user config contains invalid TOML
+    /// - `anyhow::Error` if the account store cannot be read or written
+    /// - `anyhow::Error` if a file has a newer schema version than this binary
+    /// - `anyhow::Error` if a migrated file or its backup cannot be written
     /// - `anyhow::Error` if configuration validation fails
     pub fn read_from_paths(paths: ConfigPaths) -> Result<Self> {
-        let config = fs::read_to_string(&paths.config)?;
-        let config: RuntimeConfig = toml::from_str(&config)?;
+        if !Path::new(&paths.config).exists() {
+            return Err(anyhow::anyhow!(
+                "config.toml not found at '{}'",
+                paths.config
+            ));
+        }
+        let lock = InstanceLock::acquire(&paths.root())?;
+        let (document, config) =
+            migrate::read_document(Path::new(&paths.config), SchemaKind::Runtime)?;
+        let layers = ConfigLayers::load(paths.user_config(), document, config)?;
+        let config = layers.effective()?;
 
         if let Some(mods) = config.mods.as_ref() {
             for (name, conf) in mods {
@@ -323,88 +558,215 @@ impl ConfigHandler {
         let config = Mac::new(config);
 
         let locked_config = if fs::exists(&paths.locked_config).is_ok_and(|x| x) {
-            let data = fs::read_to_string(&paths.locked_config)?;
-            Mac::new(toml::from_str(&data)?)
+            let data =
+                migrate::read_versioned(Path::new(&paths.locked_config), SchemaKind::Locked)?;
+            Mac::new(toml::Value::Table(data).try_into()?)
         } else {
             Mac::new(LockedConfig::default())
         };
 
-        let user_account = if fs::exists(&paths.user_account).is_ok_and(|x| x) {
-            Mac::new(toml::from_str(&fs::read_to_string(&paths.user_account)?)?)
-        } else {
-            Mac::new(UserAccount::default())
-        };
-
-        Ok(ConfigHandler {
+        let mut handle = ConfigHandler {
             config,
             locked_config,
-            user_account,
+            user_account: Mac::new(UserAccount::default()),
             paths,
-        })
+            layers,
+            _lock: Some(lock),
+            autocommit: false,
+        };
+        let store = handle.migrate_legacy_account()?;
+        handle.select_stored_account(&store);
+        Ok(handle)
+    }
+
+    /// Returns the path of the instance `config.toml`.
+    #[must_use]
+    pub fn config_path(&self) -> &Path {
+        Path::new(&self.paths.config)
+    }
+
+    /// Returns the path of the user config if it exists.
+    #[must_use]
+    pub fn user_config_path(&self) -> Option<&Path> {
+        self.layers.user_path()
+    }
+
+    /// Lists every value of the effective runtime configuration with the layer it comes from.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use gluon::config::ConfigHandler;
+    ///
+    /// let config = ConfigHandler::read()?;
+    /// for entry in config.effective_entries()? {
+    ///     println!("{} = {} ({})", entry.key, entry.value, entry.layer);
+    /// }
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the configuration cannot be serialized to TOML
+    pub fn effective_entries(&self) -> Result<Vec<ConfigEntry>> {
+        self.layers.entries(self.config.get())
     }
 
     /// Writes all configuration files to disk.
     ///
-    /// Writes the runtime config, locked config, and user account to their
-    /// respective files, overwriting any existing content. Also manages mod file
-    /// enabling/disabling based on configuration.
+    /// Writes the runtime config and locked config to their respective files,
+    /// overwriting any existing content. The account is only written to the
+    /// account store when it was modified. Also manages mod file
+    /// enabling/disabling based on configuration. Unlike `commit`, the
+    /// modification flags are left untouched.
     ///
     /// # Errors
     /// - `anyhow::Error` if configuration files cannot be written
     /// - `anyhow::Error` if configuration cannot be serialized to TOML
     /// - `anyhow::Error` if mod file enabling/disabling fails
     pub fn write_all(&self) -> Result<()> {
-        fs::write(
-            &self.paths.config,
-            toml::to_string_pretty(self.config.get())?,
-        )?;
-        fs::write(
-            &self.paths.locked_config,
-            toml::to_string_pretty(self.locked_config.get())?,
-        )?;
-        fs::write(
-            &self.paths.user_account,
-            toml::to_string_pretty(self.user_account())?,
-        )?;
-
-        let mod_dir = Path::new(&self.get_absolute_game_dir()?).join("mods");
-        if fs::metadata(mod_dir).is_ok() {
-            self.disable_unuse_mods()?;
-            self.enable_used_mods()?;
-        }
-        Ok(())
+        self.write_files(false)
     }
 
     /// Writes only modified configuration files to disk.
     ///
-    /// Only writes files that have been accessed mutably since the last write.
-    /// Also manages mod file enabling/disabling based on configuration.
+    /// Only writes files that have been accessed mutably since the last commit.
+    /// Also manages mod file enabling/disabling based on configuration. Unlike
+    /// `commit`, the modification flags are left untouched.
     ///
     /// # Errors
     /// - `anyhow::Error` if modified configuration cannot be written
     /// - `anyhow::Error` if configuration cannot be serialized to TOML
     /// - `anyhow::Error` if mod file enabling/disabling fails
     pub fn write_with_mut(&self) -> Result<()> {
-        if self.config.has_mut_accessed() {
+        self.write_files(true)
+    }
+
+    /// Returns `true` if any configuration was modified since the last commit.
+    #[must_use]
+    pub fn has_changes(&self) -> bool {
+        self.config.has_mut_accessed()
+            || self.locked_config.has_mut_accessed()
+            || self.user_account.has_mut_accessed()
+    }
+
+    /// Writes all modified configuration files and marks them as unmodified.
+    ///
+    /// Every modified file is first written to a temporary file, so a
+    /// serialization error or a failure while writing them leaves the files on
+    /// disk untouched. The temporary files are then renamed over the originals
+    /// one at a time; if a rename fails, the files renamed before it keep their
+    /// new contents. Afterwards mod files are enabled or disabled based on the
+    /// configuration, which a failed commit may leave half done until the next
+    /// commit syncs them again.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use gluon::config::ConfigHandler;
+    ///
+    /// let mut config = ConfigHandler::read()?;
+    /// config.config_mut().max_memory_size = 4096;
+    /// config.commit()?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if configuration cannot be serialized to TOML
+    /// - `anyhow::Error` if modified configuration cannot be written
+    /// - `anyhow::Error` if mod file enabling/disabling fails
+    pub fn commit(&mut self) -> Result<()> {
+        self.write_files(true)?;
+        self.layers.set_instance(self.config.get())?;
+        self.config.reset();
+        self.locked_config.reset();
+        self.user_account.reset();
+        Ok(())
+    }
+
+    /// Runs `f` on this handler and commits its changes.
+    ///
+    /// If `f` or the commit fails, the runtime config, locked config and user
+    /// account are rolled back to their state before the transaction and the
+    /// error is returned. Only this in-memory state is rolled back: files that
+    /// `f` touches directly, and files a failed commit already renamed into
+    /// place, keep their new contents (see `commit`).
+    ///
+    /// # Example
+    /// ```no_run
+    /// use gluon::config::ConfigHandler;
+    ///
+    /// let mut config = ConfigHandler::read()?;
+    /// config.transaction(|config| {
+    ///     config.add_mod_local("sodium.jar")?;
+    ///     config.config_mut().max_memory_size = 6144;
+    ///     Ok(())
+    /// })?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    /// - `anyhow::Error` returned by `f`
+    /// - `anyhow::Error` if the changes cannot be committed
+    pub fn transaction<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        let snapshot = (
+            self.config.clone(),
+            self.locked_config.clone(),
+            self.user_account.clone(),
+            self.layers.clone(),
+        );
+        let result = f(self).and_then(|value| {
+            self.commit()?;
+            Ok(value)
+        });
+        if result.is_err() {
+            (
+                self.config,
+                self.locked_config,
+                self.user_account,
+                self.layers,
+            ) = snapshot;
+        }
+        result
+    }
+
+    /// Enables or disables committing modified configurations when the handler is dropped.
+    ///
+    /// Disabled by default. Errors cannot be returned from `drop`, so they are
+    /// only printed to stderr; call `commit` to handle them.
+    pub fn set_autocommit(&mut self, autocommit: bool) {
+        self.autocommit = autocommit;
+    }
+
+    /// Stages the configuration files, renames them into place and syncs the mod files.
+    fn write_files(&self, only_modified: bool) -> Result<()> {
+        let mut staged = Vec::new();
+        if !only_modified || self.config.has_mut_accessed() {
             log::debug!("write config");
-            fs::write(
-                &self.paths.config,
-                toml::to_string_pretty(self.config.get())?,
-            )?;
+            staged.push(StagedFile::stage(
+                Path::new(&self.paths.config),
+                self.layers.instance_toml(self.config.get())?,
+            )?);
         }
-        if self.locked_config.has_mut_accessed() {
+        if !only_modified || self.locked_config.has_mut_accessed() {
             log::debug!("write locked config");
-            fs::write(
-                &self.paths.locked_config,
-                toml::to_string_pretty(self.locked_config.get())?,
-            )?;
+            staged.push(StagedFile::stage(
+                Path::new(&self.paths.locked_config),
+                migrate::to_versioned_toml(self.locked_config.get())?,
+            )?);
         }
-        if self.user_account.has_mut_accessed() {
-            log::debug!("write user account");
-            fs::write(
-                &self.paths.user_account,
-                toml::to_string_pretty(self.user_account())?,
-            )?;
+        // The account store is shared by all instances, so it is re-read under
+        // its lock and only written when this instance changed its account.
+        let _store_lock = if self.user_account.has_mut_accessed() {
+            log::debug!("write account store");
+            let path = self.paths.account_store()?;
+            let lock = AccountStore::lock_file(&path)?;
+            let mut store = AccountStore::read_from(&path)?;
+            store.update(self.user_account().clone());
+            staged.push(StagedFile::stage_private(&path, store.to_toml()?)?);
+            Some(lock)
+        } else {
+            None
+        };
+        for file in staged {
+            file.commit()?;
         }
 
         let mod_dir = Path::new(&self.get_absolute_game_dir()?).join("mods");
@@ -418,18 +780,47 @@ impl ConfigHandler {
 }
 
 impl Drop for ConfigHandler {
-    /// Automatically writes modified configurations when dropped.
+    /// Commits modified configurations if autocommit is enabled.
     ///
-    /// Calls `write_with_mut()` to persist any configuration changes that were
-    /// made through mutable access.
-    ///
-    /// # Panics
-    /// Panics if writing fails.
-    #[inline]
+    /// Write errors are printed to stderr, since they cannot be returned.
     fn drop(&mut self) {
-        if let Err(e) = self.write_with_mut() {
-            eprintln!("Failed to write config on drop: {e}");
-            std::process::exit(1);
+        if self.autocommit && self.has_changes() {
+            if let Err(e) = self.commit() {
+                eprintln!("Failed to write config on drop: {e}");
+            }
         }
     }
 }
+
+/// Verifies that a failed transaction rolls back and a successful one is written.
+#[test]
+fn test_transaction() {
+    let root = std::env::temp_dir().join("gluon-test-transaction");
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(&root).unwrap();
+    let paths = ConfigPaths::from_root(&root)
+        .with_account_store(&root.join("accounts.toml"))
+        .with_user_config(&root.join("user.toml"));
+    ConfigHandler::init_for_paths(paths.clone()).unwrap();
+
+    let mut handle = ConfigHandler::read_from_paths(paths.clone()).unwrap();
+    let original = handle.config().max_memory_size;
+    let result: Result<()> = handle.transaction(|handle| {
+        handle.config_mut().max_memory_size = 1;
+        Err(anyhow::anyhow!("abort"))
+    });
+    assert!(result.is_err());
+    assert_eq!(handle.config().max_memory_size, original);
+    assert!(!handle.has_changes());
+
+    handle
+        .transaction(|handle| {
+            handle.config_mut().max_memory_size = 1234;
+            Ok(())
+        })
+        .unwrap();
+    assert!(!handle.has_changes());
+    drop(handle);
+    let handle = ConfigHandler::read_from_paths(paths).unwrap();
+    assert_eq!(handle.config().max_memory_size, 1234);
+}
@@ -0,0 +1,172 @@
+//! One-off overrides of runtime settings.
+//!
+//! Overrides come from `GLUON_*` environment variables and command line flags
+//! such as `gluon run --java`. They are applied on top of the layered
+//! configuration when launching the game and are never written to
+//! `config.toml`.
+
+use super::keys;
+use super::ConfigHandler;
+use anyhow::{Context, Result};
+use std::env;
+use std::path::PathBuf;
+use toml::{Table, Value};
+
+/// Environment variable overriding `java_path`.
+pub const ENV_JAVA_PATH: &str = "GLUON_JAVA_PATH";
+/// Environment variable overriding `max_memory_size`, in MB.
+pub const ENV_MAX_MEMORY_SIZE: &str = "GLUON_MAX_MEMORY_SIZE";
+/// Environment variable overriding `game_dir`.
+pub const ENV_GAME_DIR: &str = "GLUON_GAME_DIR";
+
+/// Runtime settings overridden for one invocation.
+///
+/// # Example
+/// ```no_run
+/// use gluon::config::{ConfigHandler, RuntimeOverrides};
+///
+/// let mut config = ConfigHandler::read()?;
+/// config.apply_overrides(&RuntimeOverrides {
+///     max_memory_size: Some(2048),
+///     ..RuntimeOverrides::default()
+/// })?;
+/// assert_eq!(config.config().max_memory_size, 2048);
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RuntimeOverrides {
+    /// Path to the Java executable.
+    pub java_path: Option<String>,
+    /// Maximum memory allocation in MB.
+    pub max_memory_size: Option<u32>,
+    /// Game directory, relative paths are resolved against the current directory.
+    pub game_dir: Option<PathBuf>,
+}
+
+impl RuntimeOverrides {
+    /// Reads overrides from the `GLUON_JAVA_PATH`, `GLUON_MAX_MEMORY_SIZE` and
+    /// `GLUON_GAME_DIR` environment variables.
+    ///
+    /// Unset or empty variables are ignored.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if `GLUON_MAX_MEMORY_SIZE` is not a number
+    pub fn from_env() -> Result<Self> {
+        let var = |name| env::var(name).ok().filter(|value| !value.is_empty());
+        Ok(Self {
+            java_path: var(ENV_JAVA_PATH),
+            max_memory_size: var(ENV_MAX_MEMORY_SIZE)
+                .map(|value| value.parse())
+                .transpose()
+                .with_context(|| format!("invalid {ENV_MAX_MEMORY_SIZE}, expected a size in MB"))?,
+            game_dir: var(ENV_GAME_DIR).map(PathBuf::from),
+        })
+    }
+
+    /// Returns these overrides with unset values taken from `fallback`.
+    #[must_use]
+    pub fn or(self, fallback: Self) -> Self {
+        Self {
+            java_path: self.java_path.or(fallback.java_path),
+            max_memory_size: self.max_memory_size.or(fallback.max_memory_size),
+            game_dir: self.game_dir.or(fallback.game_dir),
+        }
+    }
+
+    /// Returns the overrides as values keyed by config key.
+    fn to_table(&self) -> Result<Table> {
+        let mut table = Table::new();
+        if let Some(java_path) = &self.java_path {
+            table.insert("java_path".into(), Value::String(java_path.clone()));
+        }
+        if let Some(max_memory_size) = self.max_memory_size {
+            table.insert(
+                "max_memory_size".into(),
+                Value::Integer(max_memory_size.into()),
+            );
+        }
+        if let Some(game_dir) = &self.game_dir {
+            let game_dir = std::path::absolute(game_dir)?;
+            let game_dir = game_dir.to_str().with_context(|| {
+                format!("Failed to convert path to string: {}", game_dir.display())
+            })?;
+            table.insert("game_dir".into(), Value::String(game_dir.to_owned()));
+        }
+        Ok(table)
+    }
+}
+
+impl ConfigHandler {
+    /// Applies overrides on top of the runtime configuration.
+    ///
+    /// Overridden values are used by this handler only: `commit` writes the
+    /// values they replaced, unless the key is changed after the override.
+    /// Apply them to a clone of the handler used for launching, since every
+    /// other path should see the persisted configuration.
+    ///
+    /// When the game directory is overridden and `game_root` is not set, the
+    /// game root stays at the configured game directory, so libraries, assets
+    /// and versions are still found.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the game directory cannot be made absolute
+    /// - `anyhow::Error` if the overridden configuration is invalid
+    pub fn apply_overrides(&mut self, overrides: &RuntimeOverrides) -> Result<()> {
+        let mut values = overrides.to_table()?;
+        if values.contains_key("game_dir") && self.config().game_root.is_none() {
+            let game_root = self.get_absolute_game_root()?;
+            values.insert("game_root".into(), Value::String(game_root));
+        }
+        let mut table = Table::try_from(self.config())?;
+        for (key, value) in values {
+            let path = [key];
+            keys::insert(&mut table, &path, value.clone())?;
+            self.layers.set_override(&path, value);
+        }
+        self.config
+            .set_unmarked(keys::validate(table, "overrides")?);
+        Ok(())
+    }
+}
+
+/// Verifies that overrides apply to the config but are not written back.
+#[test]
+fn test_overrides() {
+    let mut handle = ConfigHandler::default();
+    let written = handle.layers.instance_toml(handle.config()).unwrap();
+    handle
+        .apply_overrides(&RuntimeOverrides {
+            java_path: Some("/opt/java/bin/java".into()),
+            max_memory_size: Some(1024),
+            game_dir: None,
+        })
+        .unwrap();
+    assert_eq!(handle.config().java_path, "/opt/java/bin/java");
+    assert_eq!(handle.config().max_memory_size, 1024);
+    assert!(!handle.has_changes());
+    assert_eq!(
+        handle.layers.instance_toml(handle.config()).unwrap(),
+        written
+    );
+
+    handle.config_mut().max_memory_size = 4096;
+    let written = handle.layers.instance_toml(handle.config()).unwrap();
+    assert!(written.contains("max_memory_size = 4096"));
+    assert!(!written.contains("/opt/java"));
+}
+
+/// Verifies that overriding the game directory keeps the configured game root.
+#[test]
+fn test_overrides_game_root() {
+    let mut handle = ConfigHandler::default();
+    let game_root = handle.get_absolute_game_root().unwrap();
+    handle
+        .apply_overrides(&RuntimeOverrides {
+            game_dir: Some("/tmp/gluon-override".into()),
+            ..RuntimeOverrides::default()
+        })
+        .unwrap();
+    assert_eq!(handle.config().game_dir, "/tmp/gluon-override");
+    assert_eq!(handle.get_absolute_game_root().unwrap(), game_root);
+    assert!(!handle.has_changes());
+}
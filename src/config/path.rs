@@ -2,20 +2,61 @@
 
 use super::ConfigHandler;
 use anyhow::{Context, Result};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+/// Environment variable overriding the user-level gluon directory.
+const GLUON_HOME_ENV: &str = "GLUON_HOME";
+
+/// Returns the user-level configuration directory of gluon.
+///
+/// This is `$GLUON_HOME` if the variable is set, otherwise the platform
+/// configuration directory joined with `gluon` (e.g. `~/.config/gluon` on Linux).
+/// The directory is not created.
+///
+/// # Errors
+/// - `anyhow::Error` if the platform configuration directory cannot be determined
+pub fn user_config_dir() -> Result<PathBuf> {
+    if let Some(home) = std::env::var_os(GLUON_HOME_ENV) {
+        return Ok(PathBuf::from(home));
+    }
+    dirs::config_dir()
+        .map(|dir| dir.join("gluon"))
+        .ok_or_else(|| anyhow::anyhow!("cannot determine the user config directory"))
+}
+
+/// Returns the user-level data directory of gluon.
+///
+/// This is `$GLUON_HOME` if the variable is set, otherwise the platform data
+/// directory joined with `gluon` (e.g. `~/.local/share/gluon` on Linux).
+/// The directory is not created.
+///
+/// # Errors
+/// - `anyhow::Error` if the platform data directory cannot be determined
+pub fn user_data_dir() -> Result<PathBuf> {
+    if let Some(home) = std::env::var_os(GLUON_HOME_ENV) {
+        return Ok(PathBuf::from(home));
+    }
+    dirs::data_dir()
+        .map(|dir| dir.join("gluon"))
+        .ok_or_else(|| anyhow::anyhow!("cannot determine the user data directory"))
+}
 
 /// Searches upward for `config.toml` starting from the given path.
 ///
 /// The given path may be a directory or a file. Returns the directory
 /// containing `config.toml`, or `None` if the filesystem root is reached
-/// without finding it.
-pub(super) fn try_find_config_root_from(path: &Path) -> Option<std::path::PathBuf> {
+/// without finding it. The user config directory is skipped, its `config.toml`
+/// holds user defaults rather than an instance.
+pub(super) fn try_find_config_root_from(path: &Path) -> Option<PathBuf> {
     // Relative paths have no directory component to climb up from (e.g. "config.toml"
     // pops to an empty path), so normalize to an absolute path first. `absolute` does
     // not touch the filesystem and does not require the path to exist.
     let mut current = std::path::absolute(path).ok()?;
+    let user_dir = user_config_dir()
+        .ok()
+        .and_then(|dir| std::path::absolute(dir).ok());
     loop {
-        if current.join("config.toml").exists() {
+        if current.join("config.toml").exists() && user_dir.as_ref() != Some(&current) {
             return Some(current);
         }
         if !current.pop() {
@@ -30,7 +71,7 @@ impl ConfigHandler {
     /// Returns the directory containing config.toml, or `None` if the current
     /// directory cannot be determined or the filesystem root is reached
     /// without finding it.
-    pub(crate) fn try_find_config_root() -> Option<std::path::PathBuf> {
+    pub(crate) fn try_find_config_root() -> Option<PathBuf> {
         let cwd = std::env::current_dir().ok()?;
         try_find_config_root_from(&cwd)
     }
@@ -49,6 +90,43 @@ impl ConfigHandler {
     /// - Returns an error if the parent directory of `config.toml` cannot be determined
     /// - Returns an error if the resolved path cannot be converted to a valid string
     pub fn get_absolute_game_dir(&self) -> Result<String> {
+        self.resolve_config_relative(&self.config.game_dir)
+    }
+
+    /// Gets the absolute path to the instance directory, the one containing `config.toml`.
+    ///
+    /// # Errors
+    /// - Returns an error if the current directory cannot be determined
+    /// - Returns an error if the resolved path cannot be converted to a valid string
+    pub fn get_absolute_instance_dir(&self) -> Result<String> {
+        let path = std::path::absolute(self.paths.root())?;
+        let path_str = path
+            .to_str()
+            .with_context(|| format!("Failed to convert path to string: {}", path.display()))?;
+        Ok(path_str.to_string())
+    }
+
+    /// Gets the absolute path to the shared game root.
+    ///
+    /// The game root holds `libraries/`, `assets/` and `versions/`, which can be
+    /// shared by several instances, while mods, saves and natives stay in the game
+    /// directory. If `game_root` is not set in `config.toml`, the game directory
+    /// is used. Relative paths are resolved like `game_dir`.
+    ///
+    /// # Errors
+    /// - Returns an error if the parent directory of `config.toml` cannot be determined
+    /// - Returns an error if the resolved path cannot be converted to a valid string
+    pub fn get_absolute_game_root(&self) -> Result<String> {
+        match &self.config.game_root {
+            Some(game_root) => self.resolve_config_relative(game_root),
+            None => self.get_absolute_game_dir(),
+        }
+    }
+
+    /// Resolves a path relative to the directory containing `config.toml`.
+    ///
+    /// Absolute paths are returned as-is.
+    fn resolve_config_relative(&self, dir: &str) -> Result<String> {
         let config_path = Path::new(&self.paths.config)
             .parent()
             .with_context(|| {
@@ -57,9 +135,9 @@ impl ConfigHandler {
                     self.paths.config
                 )
             })?;
-        let game_dir = Path::new(&self.config.game_dir);
+        let game_dir = Path::new(dir);
         if game_dir.is_absolute() {
-            return Ok(self.config.game_dir.clone());
+            return Ok(dir.to_owned());
         }
         let path = config_path.join(game_dir);
         let path_str = path
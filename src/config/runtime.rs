@@ -3,32 +3,64 @@
 //! Contains user-configurable settings including mirror URLs, mod loaders,
 //! mod configurations, and the main runtime configuration.
 
+use super::{MirrorDefinition, MirrorUrls};
+use anyhow::Result;
+use mc_oauth::{MinecraftAuthenticator, ENV_CLIENT_ID};
 use modrinth_api::Version;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 
+/// JVM arguments used when no preset is selected.
+pub const DEFAULT_JVM_ARGS: &[&str] = &[
+    "-Xmn256m",
+    "-XX:+UseG1GC",
+    "-XX:-UseAdaptiveSizePolicy",
+    "-XX:-OmitStackTraceInFastThrow",
+    "-Dfml.ignoreInvalidMinecraftCertificates=True",
+    "-Dfml.ignorePatchDiscrepancies=True",
+    "-Dlog4j2.formatMsgNoLookups=true",
+    "-XX:HeapDumpPath=MojangTricksIntelDriversForPerformance_javaw.exe_minecraft.exe.heapdump",
+];
+
+/// JVM presets built into gluon, selectable with `jvm_preset`.
+///
+/// `default` is [`DEFAULT_JVM_ARGS`]; `zgc` uses the Z garbage collector,
+/// which is generational by default since Java 23.
+pub const BUILTIN_JVM_PRESETS: &[(&str, &[&str])] = &[
+    ("default", DEFAULT_JVM_ARGS),
+    (
+        "zgc",
+        &[
+            "-XX:+UseZGC",
+            "-XX:-OmitStackTraceInFastThrow",
+            "-Dlog4j2.formatMsgNoLookups=true",
+        ],
+    ),
+];
+
 /// Mirror URLs for downloading Minecraft resources.
 ///
 /// Contains endpoints for downloading version manifests, assets, client files,
-/// libraries, and Fabric loader components from various mirror sources.
+/// libraries, and Fabric loader components from various mirror sources. Each
+/// endpoint holds a primary URL and optional fallback URLs.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct MCMirror {
-    /// URL for version manifest downloads.
-    pub version_manifest: String,
-    /// URL for asset downloads.
-    pub assets: String,
-    /// URL for client downloads.
-    pub client: String,
-    /// URL for library downloads.
-    pub libraries: String,
-    /// URL for Fabric metadata downloads.
-    pub fabric_meta: String,
-    /// URL for Fabric Maven downloads.
-    pub fabric_maven: String,
-    /// URL for `NeoForgeForge` downloads.
-    pub neoforge_forge: String,
-    /// URL for `NeoForgeNeoForge` downloads.
-    pub neoforge_neoforge: String,
+    /// URLs for version manifest downloads.
+    pub version_manifest: MirrorUrls,
+    /// URLs for asset downloads.
+    pub assets: MirrorUrls,
+    /// URLs for client downloads.
+    pub client: MirrorUrls,
+    /// URLs for library downloads.
+    pub libraries: MirrorUrls,
+    /// URLs for Fabric metadata downloads.
+    pub fabric_meta: MirrorUrls,
+    /// URLs for Fabric Maven downloads.
+    pub fabric_maven: MirrorUrls,
+    /// URLs for `NeoForgeForge` downloads.
+    pub neoforge_forge: MirrorUrls,
+    /// URLs for `NeoForgeNeoForge` downloads.
+    pub neoforge_neoforge: MirrorUrls,
 }
 
 impl MCMirror {
@@ -73,7 +105,7 @@ impl MCMirror {
 ///
 /// Represents the mod loader to use with the game, such as Fabric.
 /// The `None` variant indicates vanilla Minecraft without any mod loader.
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub enum MCLoader {
     /// No mod loader (vanilla Minecraft).
     None,
@@ -128,6 +160,23 @@ impl ModConfig {
     }
 }
 
+/// Shell commands run around the game, see `gluon::runtime::run_hooks`.
+///
+/// Each list is run in order with `sh -c` (`cmd /C` on Windows) in the game
+/// directory.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct Hooks {
+    /// Commands run before the game is launched. A failing command aborts the launch.
+    #[serde(default)]
+    pub pre_launch: Vec<String>,
+    /// Commands run after the game exited.
+    #[serde(default)]
+    pub post_exit: Vec<String>,
+    /// Commands run after the game was installed.
+    #[serde(default)]
+    pub post_install: Vec<String>,
+}
+
 /// Runtime configuration for the Minecraft launcher.
 ///
 /// Contains user-configurable settings for game launching, including memory,
@@ -139,6 +188,10 @@ pub struct RuntimeConfig {
     /// Path to the game directory. To get `game_dir`, use `ConfigHandler::get_absolute_game_dir`
     /// instead.
     pub(crate) game_dir: String,
+    /// Path to the shared game root holding `libraries/`, `assets/` and `versions/`.
+    /// Defaults to `game_dir` when unset. To get the game root, use
+    /// `ConfigHandler::get_absolute_game_root` instead.
+    pub(crate) game_root: Option<String>,
     /// Minecraft versions directory string.
     pub game_version: String,
     /// Path to Java executable.
@@ -149,8 +202,41 @@ pub struct RuntimeConfig {
     pub loader: MCLoader,
     /// Mirror URLs for downloads.
     pub mirror: MCMirror,
+    /// User-defined named mirrors, see `RuntimeConfig::use_mirror`.
+    #[serde(default)]
+    pub mirrors: BTreeMap<String, MirrorDefinition>,
     /// Mod configurations keyed by mod name.
     pub mods: Option<BTreeMap<String, ModConfig>>,
+    /// Minimum memory allocation in MB, passed as `-Xms`.
+    #[serde(default)]
+    pub min_memory_size: Option<u32>,
+    /// Name of the JVM preset providing the default JVM arguments, from
+    /// `jvm_presets` or [`BUILTIN_JVM_PRESETS`]. Uses [`DEFAULT_JVM_ARGS`] when unset.
+    #[serde(default)]
+    pub jvm_preset: Option<String>,
+    /// User-defined JVM presets keyed by name.
+    #[serde(default)]
+    pub jvm_presets: BTreeMap<String, Vec<String>>,
+    /// Extra JVM arguments, added after the default and version JVM arguments.
+    #[serde(default)]
+    pub jvm_args: Vec<String>,
+    /// Drops the default or preset JVM arguments, so only `jvm_args` are added.
+    #[serde(default)]
+    pub replace_default_jvm_args: bool,
+    /// Extra game arguments, added after the version game arguments.
+    #[serde(default)]
+    pub game_args: Vec<String>,
+    /// Commands run before launch, after exit and after install.
+    #[serde(default)]
+    pub hooks: Hooks,
+    /// Player name or UUID of the stored account used by this instance, see
+    /// [`AccountStore`](super::AccountStore). Uses the active account when unset.
+    #[serde(default)]
+    pub account: Option<String>,
+    /// Azure application client ID used for Microsoft login instead of the one
+    /// built into gluon. The `AZURE_CLIENT_ID` environment variable overrides it.
+    #[serde(default)]
+    pub microsoft_client_id: Option<String>,
 }
 
 impl RuntimeConfig {
@@ -201,6 +287,92 @@ impl RuntimeConfig {
             }
         }
     }
+
+    /// Returns the JVM arguments that precede the version JVM arguments.
+    ///
+    /// These are the memory arguments, followed by the arguments of the
+    /// selected JVM preset unless `replace_default_jvm_args` is set. User
+    /// presets take precedence over built-in presets of the same name.
+    ///
+    /// # Example
+    /// ```
+    /// use gluon::config::RuntimeConfig;
+    /// let mut config = RuntimeConfig::default();
+    /// config.max_memory_size = 4096;
+    /// config.min_memory_size = Some(1024);
+    /// config.jvm_preset = Some("zgc".into());
+    /// let args = config.base_jvm_args().unwrap();
+    /// assert_eq!(args[..3], ["-Xmx4096m", "-Xms1024m", "-XX:+UseZGC"]);
+    ///
+    /// config.replace_default_jvm_args = true;
+    /// assert_eq!(config.base_jvm_args().unwrap(), ["-Xmx4096m", "-Xms1024m"]);
+    /// ```
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if `min_memory_size` is larger than `max_memory_size`
+    /// - `anyhow::Error` if the selected JVM preset does not exist
+    pub fn base_jvm_args(&self) -> Result<Vec<String>> {
+        let mut args = vec![format!("-Xmx{}m", self.max_memory_size)];
+        if let Some(min_memory_size) = self.min_memory_size {
+            if min_memory_size > self.max_memory_size {
+                return Err(anyhow::anyhow!(
+                    "min_memory_size ({min_memory_size}) is larger than max_memory_size ({})",
+                    self.max_memory_size
+                ));
+            }
+            args.push(format!("-Xms{min_memory_size}m"));
+        }
+        if self.replace_default_jvm_args {
+            return Ok(args);
+        }
+        match self.jvm_preset.as_deref() {
+            None => args.extend(DEFAULT_JVM_ARGS.iter().map(ToString::to_string)),
+            Some(name) => {
+                if let Some(preset) = self.jvm_presets.get(name) {
+                    args.extend(preset.iter().cloned());
+                } else if let Some((_, preset)) = BUILTIN_JVM_PRESETS
+                    .iter()
+                    .find(|(builtin, _)| *builtin == name)
+                {
+                    args.extend(preset.iter().map(ToString::to_string));
+                } else {
+                    return Err(anyhow::anyhow!(
+                        "unknown JVM preset '{name}', define it in jvm_presets"
+                    ));
+                }
+            }
+        }
+        Ok(args)
+    }
+
+    /// Returns the authenticator for Microsoft login and token refresh.
+    ///
+    /// The client ID is taken from the `AZURE_CLIENT_ID` environment variable,
+    /// then from `microsoft_client_id`, then from the build.
+    ///
+    /// # Example
+    /// ```
+    /// use gluon::config::RuntimeConfig;
+    /// let mut config = RuntimeConfig::default();
+    /// config.microsoft_client_id = Some("00000000-0000-0000-0000-000000000000".into());
+    /// let authenticator = config.microsoft_authenticator()?;
+    /// # if std::env::var("AZURE_CLIENT_ID").is_err() {
+    /// assert_eq!(authenticator.client_id(), "00000000-0000-0000-0000-000000000000");
+    /// # }
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if no client ID is set in the environment, the
+    ///   configuration or the build
+    pub fn microsoft_authenticator(&self) -> Result<MinecraftAuthenticator> {
+        MinecraftAuthenticator::from_env_or(self.microsoft_client_id.as_deref()).map_err(|_| {
+            anyhow::anyhow!(
+                "no Azure client ID for Microsoft login, set microsoft_client_id in \
+                 config.toml or the {ENV_CLIENT_ID} environment variable"
+            )
+        })
+    }
 }
 
 impl Default for RuntimeConfig {
@@ -212,12 +384,23 @@ impl Default for RuntimeConfig {
         RuntimeConfig {
             max_memory_size: 5000,
             game_dir: "./".into(),
+            game_root: None,
             game_version: "no_game_version".into(),
             java_path: "java".into(),
             vanilla: "no game vanilla version".into(),
             loader: MCLoader::None,
             mirror: MCMirror::official_mirror(),
+            mirrors: BTreeMap::new(),
             mods: None,
+            min_memory_size: None,
+            jvm_preset: None,
+            jvm_presets: BTreeMap::new(),
+            jvm_args: Vec::new(),
+            replace_default_jvm_args: false,
+            game_args: Vec::new(),
+            hooks: Hooks::default(),
+            account: None,
+            microsoft_client_id: None,
         }
     }
 }
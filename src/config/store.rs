@@ -0,0 +1,541 @@
+//! Account store shared by all instances.
+//!
+//! Accounts are stored in `accounts.toml` inside the user config directory,
+//! keyed by their UUID, so a Microsoft login is done once and used by every
+//! instance. The store remembers the active account, which instances use
+//! unless their `account` setting selects another one.
+//!
+//! The store can be locked with a passphrase, see [`AccountStore::lock`]. The
+//! tokens of a locked store are only kept encrypted on disk, while player
+//! names and UUIDs stay readable so accounts can be listed and selected
+//! without the passphrase. The store file is readable by the current user only.
+//!
+//! Changes go through [`AccountStore::modify`], which holds a lock next to the
+//! store file while it is read, changed and written, so concurrent `gluon`
+//! processes, e.g. two instances refreshing their tokens, do not overwrite
+//! each other's accounts.
+
+use super::file::{write_private, InstanceLock};
+use super::migrate::{self, SchemaKind};
+use super::path::user_config_dir;
+use super::vault::{self, forget_passphrase, read_passphrase};
+use super::UserAccount;
+use anyhow::Result;
+use chacha20poly1305::XChaCha20Poly1305;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use zeroize::Zeroizing;
+
+/// Value sealed in `Encryption::check` to tell a wrong passphrase apart.
+const CHECK_VALUE: &[u8] = b"gluon account store";
+
+/// Stored accounts and the active account.
+///
+/// # Example
+/// ```
+/// use gluon::config::{AccountStore, UserAccount};
+///
+/// let mut store = AccountStore::default();
+/// let main = store.add(UserAccount::new_offline("Steve")).user_uuid.clone();
+/// store.add(UserAccount::new_offline("Alex"));
+/// store.switch("Steve").unwrap();
+/// assert_eq!(store.active.as_deref(), Some(main.as_str()));
+/// assert_eq!(store.remove("Alex").unwrap().user_name, "Alex");
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AccountStore {
+    /// UUID of the account used by instances that do not select an account.
+    pub active: Option<String>,
+    /// Passphrase encryption of the tokens, `None` if tokens are stored in plain text.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encryption: Option<Encryption>,
+    /// Stored accounts keyed by UUID.
+    #[serde(default)]
+    pub accounts: BTreeMap<String, UserAccount>,
+}
+
+/// Key derivation settings of a locked account store.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Encryption {
+    /// Hex encoded salt of the Argon2id key derivation.
+    pub salt: String,
+    /// A known value sealed with the key, used to detect a wrong passphrase.
+    pub check: String,
+}
+
+/// Plain tokens of an account, sealed into `UserAccount::sealed_tokens`.
+#[derive(Serialize, Deserialize)]
+struct Tokens {
+    access_token: Option<String>,
+    refresh_token: Option<String>,
+}
+
+impl AccountStore {
+    /// Returns the path of the store file, `accounts.toml` in the user config directory.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the user config directory cannot be determined
+    pub fn store_path() -> Result<PathBuf> {
+        Ok(user_config_dir()?.join("accounts.toml"))
+    }
+
+    /// Reads the store from the user config directory.
+    ///
+    /// Returns an empty store if the store file does not exist yet.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the user config directory cannot be determined
+    /// - `anyhow::Error` if the store file cannot be read or contains invalid TOML
+    pub fn read() -> Result<Self> {
+        Self::read_from(&Self::store_path()?)
+    }
+
+    /// Reads the store from the given file.
+    ///
+    /// Returns an empty store if the file does not exist.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the file cannot be read or contains invalid TOML
+    /// - `anyhow::Error` if the file has a newer schema version than this binary
+    pub fn read_from(path: &Path) -> Result<Self> {
+        if !fs::exists(path)? {
+            return Ok(Self::default());
+        }
+        let data = migrate::read_versioned(path, SchemaKind::AccountStore)?;
+        Ok(toml::Value::Table(data).try_into()?)
+    }
+
+    /// Changes the store in the user config directory and writes it back.
+    ///
+    /// The store is read, passed to `f` and written while holding the store
+    /// lock, see `modify_at`. Nothing is written if `f` fails.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use gluon::config::{AccountStore, UserAccount};
+    ///
+    /// AccountStore::modify(|store| {
+    ///     store.add(UserAccount::new_offline("Steve"));
+    ///     Ok(())
+    /// })?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the user config directory cannot be determined
+    /// - `anyhow::Error` returned by `f`
+    /// - `anyhow::Error` if the store cannot be locked, read or written
+    pub fn modify<T>(f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        Self::modify_at(&Self::store_path()?, f)
+    }
+
+    /// Changes the store in the given file and writes it back.
+    ///
+    /// An exclusive lock next to the file is held from reading the store until
+    /// it is written, waiting for other processes and threads changing the store.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` returned by `f`
+    /// - `anyhow::Error` if the store cannot be locked, read or written
+    pub fn modify_at<T>(path: &Path, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        let _lock = Self::lock_file(path)?;
+        let mut store = Self::read_from(path)?;
+        let value = f(&mut store)?;
+        store.write_to(path)?;
+        Ok(value)
+    }
+
+    /// Takes the lock guarding read-modify-write cycles of the store in `path`.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the lock file cannot be created or locked
+    pub(super) fn lock_file(path: &Path) -> Result<Arc<InstanceLock>> {
+        InstanceLock::acquire_file(path)
+    }
+
+    /// Writes the store to the user config directory.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the user config directory cannot be determined or created
+    /// - `anyhow::Error` if the store file cannot be written
+    pub fn write(&self) -> Result<()> {
+        self.write_to(&Self::store_path()?)
+    }
+
+    /// Writes the store to the given file, creating parent directories as needed.
+    ///
+    /// On Unix the file is readable by the current user only. Plain tokens in
+    /// a locked store are sealed first, see `to_toml`.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the parent directory cannot be created
+    /// - `anyhow::Error` if the tokens cannot be sealed
+    /// - `anyhow::Error` if the store cannot be serialized or written
+    pub fn write_to(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        write_private(path, self.to_toml()?)
+    }
+
+    /// Serializes the store as written to disk.
+    ///
+    /// If the store is locked, plain tokens, e.g. of an account that was just
+    /// added or refreshed, are sealed with the passphrase from
+    /// `read_passphrase`, which is only asked for in that case.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the passphrase cannot be read or is wrong
+    /// - `anyhow::Error` if the store cannot be serialized
+    pub(super) fn to_toml(&self) -> Result<String> {
+        let mut store = self.clone();
+        if self.is_locked() && self.accounts.values().any(has_plain_tokens) {
+            let cipher = self.cipher_from_passphrase()?;
+            for account in store.accounts.values_mut() {
+                seal_tokens(&cipher, account)?;
+            }
+        }
+        migrate::to_versioned_toml(&store)
+    }
+
+    /// Moves an offline account to the UUID vanilla servers derive from its name.
+    ///
+    /// Accounts created by older versions of gluon have a random UUID, so
+    /// offline-mode servers see a new player each time the account is
+    /// recreated. Accounts given explicitly are moved even if their UUID was
+    /// chosen by the user. The active account follows the move. Returns the old and
+    /// the new UUID, or `None` if the account already has the name-based UUID.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if no account or several accounts match, see `resolve`
+    /// - `anyhow::Error` if the account is not an offline account
+    /// - `anyhow::Error` if another account already has the name-based UUID
+    pub fn migrate_offline_uuid(&mut self, account: &str) -> Result<Option<(String, String)>> {
+        let uuid = self.resolve(account)?;
+        let stored = &self.accounts[&uuid];
+        if stored.user_type != "offline" {
+            return Err(anyhow::anyhow!(
+                "'{}' is not an offline account, its UUID belongs to the Microsoft account",
+                stored.user_name
+            ));
+        }
+        let offline_uuid = UserAccount::offline_uuid(&stored.user_name);
+        if offline_uuid == uuid {
+            return Ok(None);
+        }
+        if self.accounts.contains_key(&offline_uuid) {
+            return Err(anyhow::anyhow!(
+                "another account already has the offline UUID of '{}', remove it first",
+                stored.user_name
+            ));
+        }
+        let mut moved = self.accounts.remove(&uuid).unwrap_or_default();
+        moved.user_uuid.clone_from(&offline_uuid);
+        moved.custom_uuid = false;
+        if self.active.as_ref() == Some(&uuid) {
+            self.active = Some(offline_uuid.clone());
+        }
+        self.accounts.insert(offline_uuid.clone(), moved);
+        Ok(Some((uuid, offline_uuid)))
+    }
+
+    /// Tells whether the tokens of this store are encrypted with a passphrase.
+    #[must_use]
+    pub fn is_locked(&self) -> bool {
+        self.encryption.is_some()
+    }
+
+    /// Encrypts the tokens of every account with a passphrase.
+    ///
+    /// The key is derived from the passphrase with Argon2id and a new random
+    /// salt, and tokens are sealed with XChaCha20-Poly1305. Accounts added to
+    /// a locked store are sealed when the store is written.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the store is already locked
+    /// - `anyhow::Error` if the key derivation or encryption fails
+    pub fn lock(&mut self, passphrase: &str) -> Result<()> {
+        if self.is_locked() {
+            return Err(anyhow::anyhow!("the account store is already locked"));
+        }
+        let salt = vault::new_salt();
+        let cipher = vault::cipher(passphrase, &salt)?;
+        let check = vault::seal(&cipher, CHECK_VALUE)?;
+        for account in self.accounts.values_mut() {
+            seal_tokens(&cipher, account)?;
+        }
+        self.encryption = Some(Encryption { salt, check });
+        Ok(())
+    }
+
+    /// Decrypts the tokens of every account and removes the passphrase.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the store is not locked
+    /// - `anyhow::Error` if the passphrase is wrong or a token cannot be decrypted
+    pub fn unlock(&mut self, passphrase: &str) -> Result<()> {
+        let cipher = self
+            .cipher(passphrase)?
+            .ok_or_else(|| anyhow::anyhow!("the account store is not locked"))?;
+        for account in self.accounts.values_mut() {
+            open_tokens(&cipher, account)?;
+        }
+        self.encryption = None;
+        Ok(())
+    }
+
+    /// Decrypts the tokens of an account of this store, in memory only.
+    ///
+    /// Does nothing if the account has no sealed tokens. The passphrase is
+    /// read with `read_passphrase`.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the passphrase cannot be read or is wrong
+    /// - `anyhow::Error` if the tokens cannot be decrypted
+    pub fn open_account(&self, account: &mut UserAccount) -> Result<()> {
+        if account.sealed_tokens.is_none() {
+            return Ok(());
+        }
+        if !self.is_locked() {
+            return Err(anyhow::anyhow!(
+                "account '{}' has encrypted tokens but the account store is not locked",
+                account.user_name
+            ));
+        }
+        open_tokens(&self.cipher_from_passphrase()?, account)
+    }
+
+    /// Returns the cipher of a locked store for the passphrase of this process.
+    ///
+    /// A wrong passphrase is forgotten, so the next attempt asks again.
+    fn cipher_from_passphrase(&self) -> Result<XChaCha20Poly1305> {
+        let passphrase = read_passphrase(false)?;
+        match self.cipher(&passphrase) {
+            Ok(Some(cipher)) => Ok(cipher),
+            Ok(None) => Err(anyhow::anyhow!("the account store is not locked")),
+            Err(e) => {
+                forget_passphrase();
+                Err(e)
+            }
+        }
+    }
+
+    /// Returns the cipher of a locked store, or `None` if the store is not locked.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the passphrase is wrong
+    fn cipher(&self, passphrase: &str) -> Result<Option<XChaCha20Poly1305>> {
+        let Some(encryption) = &self.encryption else {
+            return Ok(None);
+        };
+        let cipher = vault::cipher(passphrase, &encryption.salt)?;
+        vault::open(&cipher, &encryption.check)?;
+        Ok(Some(cipher))
+    }
+
+    /// Stores an account and makes it the active one.
+    ///
+    /// An account with the same UUID is replaced, so logging in again
+    /// updates the stored tokens. Returns the stored account.
+    pub fn add(&mut self, account: UserAccount) -> &UserAccount {
+        let uuid = account.user_uuid.clone();
+        self.active = Some(uuid.clone());
+        self.accounts.insert(uuid.clone(), account);
+        &self.accounts[&uuid]
+    }
+
+    /// Stores an account, keeping the active account unless there is none.
+    ///
+    /// An account with the same UUID is replaced.
+    pub fn update(&mut self, account: UserAccount) {
+        let uuid = account.user_uuid.clone();
+        self.active.get_or_insert_with(|| uuid.clone());
+        self.accounts.insert(uuid, account);
+    }
+
+    /// Returns the active account, if any.
+    #[must_use]
+    pub fn active_account(&self) -> Option<&UserAccount> {
+        self.active
+            .as_ref()
+            .and_then(|uuid| self.accounts.get(uuid))
+    }
+
+    /// Returns the UUID of the account given by UUID or player name.
+    ///
+    /// UUIDs match with or without dashes, player names ignore case.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if no account matches
+    /// - `anyhow::Error` if the player name matches several accounts
+    pub fn resolve(&self, account: &str) -> Result<String> {
+        let simple = account.replace('-', "").to_ascii_lowercase();
+        if let Some(uuid) = self
+            .accounts
+            .keys()
+            .find(|uuid| uuid.replace('-', "").to_ascii_lowercase() == simple)
+        {
+            return Ok(uuid.clone());
+        }
+        let matches: Vec<&String> = self
+            .accounts
+            .iter()
+            .filter(|(_, stored)| stored.user_name.eq_ignore_ascii_case(account))
+            .map(|(uuid, _)| uuid)
+            .collect();
+        match matches[..] {
+            [uuid] => Ok(uuid.clone()),
+            [] => Err(anyhow::anyhow!(
+                "account '{account}' not found, run 'gluon account list' to see accounts"
+            )),
+            _ => Err(anyhow::anyhow!(
+                "several accounts are named '{account}', select one by UUID"
+            )),
+        }
+    }
+
+    /// Returns the account given by UUID or player name.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if no account or several accounts match, see `resolve`
+    pub fn get(&self, account: &str) -> Result<&UserAccount> {
+        let uuid = self.resolve(account)?;
+        self.accounts
+            .get(&uuid)
+            .ok_or_else(|| anyhow::anyhow!("account '{account}' not found"))
+    }
+
+    /// Makes the account given by UUID or player name the active one.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if no account or several accounts match, see `resolve`
+    pub fn switch(&mut self, account: &str) -> Result<()> {
+        self.active = Some(self.resolve(account)?);
+        Ok(())
+    }
+
+    /// Removes the account given by UUID or player name and returns it.
+    ///
+    /// Clears the active account if it was the removed one.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if no account or several accounts match, see `resolve`
+    pub fn remove(&mut self, account: &str) -> Result<UserAccount> {
+        let uuid = self.resolve(account)?;
+        if self.active.as_ref() == Some(&uuid) {
+            self.active = None;
+        }
+        self.accounts
+            .remove(&uuid)
+            .ok_or_else(|| anyhow::anyhow!("account '{account}' not found"))
+    }
+}
+
+/// Tells whether an account holds tokens in plain text.
+fn has_plain_tokens(account: &UserAccount) -> bool {
+    account.access_token.is_some() || account.refresh_token.is_some()
+}
+
+/// Moves the plain tokens of an account into `sealed_tokens`.
+fn seal_tokens(cipher: &XChaCha20Poly1305, account: &mut UserAccount) -> Result<()> {
+    if !has_plain_tokens(account) {
+        return Ok(());
+    }
+    let tokens = Zeroizing::new(serde_json::to_vec(&Tokens {
+        access_token: account.access_token.take(),
+        refresh_token: account.refresh_token.take(),
+    })?);
+    account.sealed_tokens = Some(vault::seal(cipher, &tokens)?);
+    Ok(())
+}
+
+/// Moves the tokens sealed in `sealed_tokens` back into the plain token fields.
+fn open_tokens(cipher: &XChaCha20Poly1305, account: &mut UserAccount) -> Result<()> {
+    let Some(sealed) = &account.sealed_tokens else {
+        return Ok(());
+    };
+    let tokens: Tokens = serde_json::from_slice(&vault::open(cipher, sealed)?)?;
+    account.access_token = tokens.access_token;
+    account.refresh_token = tokens.refresh_token;
+    account.sealed_tokens = None;
+    Ok(())
+}
+
+/// Verifies account lookup by UUID and name and that the store survives a write/read round trip.
+#[test]
+fn test_account_store() {
+    let file = std::env::temp_dir()
+        .join("gluon-test-account-store")
+        .join("accounts.toml");
+    let mut store = AccountStore::default();
+    let steve = store.add(UserAccount::new_offline("Steve")).clone();
+    let mut alt = UserAccount::new_offline("steve");
+    alt.user_uuid = "069a79f4-44e9-4726-a5be-fca90e38aaf5".into();
+    store.add(alt);
+    assert!(store.resolve("Steve").is_err());
+    assert_eq!(
+        store.resolve("069A79F444E94726A5BEFCA90E38AAF5").unwrap(),
+        "069a79f4-44e9-4726-a5be-fca90e38aaf5"
+    );
+    store.switch(&steve.user_uuid).unwrap();
+    assert_eq!(store.active_account().unwrap().user_uuid, steve.user_uuid);
+
+    let mut msa = UserAccount::new_offline("Alex");
+    msa.user_type = "msa".into();
+    msa.access_token = Some("access-secret".into());
+    msa.refresh_token = Some("refresh-secret".into());
+    store.update(msa);
+    store.lock("correct horse").unwrap();
+    assert!(store.lock("correct horse").is_err());
+
+    store.write_to(&file).unwrap();
+    let data = fs::read_to_string(&file).unwrap();
+    assert!(!data.contains("secret"));
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(&file).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+    let mut store = AccountStore::read_from(&file).unwrap();
+    assert_eq!(store.accounts.len(), 3);
+    assert!(store.get("Alex").unwrap().access_token.is_none());
+    assert!(store.unlock("battery staple").is_err());
+    store.unlock("correct horse").unwrap();
+    assert_eq!(
+        store.get("Alex").unwrap().refresh_token.as_deref(),
+        Some("refresh-secret")
+    );
+    store.remove(&steve.user_uuid).unwrap();
+    assert!(store.active.is_none());
+    assert_eq!(store.get("steve").unwrap().user_name, "steve");
+    assert!(store.get("Bob").is_err());
+
+    let old = store.resolve("steve").unwrap();
+    let new = UserAccount::offline_uuid("steve");
+    assert_eq!(
+        store.migrate_offline_uuid("steve").unwrap(),
+        Some((old, new.clone()))
+    );
+    assert_eq!(store.resolve("steve").unwrap(), new);
+    assert_eq!(store.migrate_offline_uuid("steve").unwrap(), None);
+    assert!(store.migrate_offline_uuid("Alex").is_err());
+
+    let mut custom = UserAccount::new_offline("Herobrine");
+    custom.user_uuid = "f84c6a79-0a4e-45e0-879b-cd49ebd4c4e2".into();
+    custom.custom_uuid = true;
+    assert!(!custom.has_random_offline_uuid());
+    store.add(custom);
+    let new = UserAccount::offline_uuid("Herobrine");
+    assert_eq!(
+        store
+            .migrate_offline_uuid("Herobrine")
+            .unwrap()
+            .map(|(_, new)| new),
+        Some(new)
+    );
+    assert!(!store.get("Herobrine").unwrap().custom_uuid);
+}
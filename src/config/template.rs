@@ -0,0 +1,125 @@
+//! Templates for new instances.
+//!
+//! A template is an instance `config.toml` kept in the `templates` directory
+//! of the user config directory, for example `templates/modded.toml`. A new
+//! instance initialized from a template starts with a copy of it instead of
+//! the built-in defaults, including its comments and mods.
+
+use super::migrate::{self, SchemaKind};
+use super::path::user_config_dir;
+use super::{document, ConfigHandler, ConfigLayers, ConfigPaths, LockedConfig};
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Returns the directory holding instance templates, `templates` in the user config directory.
+///
+/// # Errors
+/// - `anyhow::Error` if the user config directory cannot be determined
+pub fn templates_dir() -> Result<PathBuf> {
+    Ok(user_config_dir()?.join("templates"))
+}
+
+/// Resolves a template given by name or by path.
+///
+/// `template` is a path if it contains a path separator or ends with `.toml`,
+/// otherwise it names `<name>.toml` in the templates directory.
+fn template_path(template: &str) -> Result<PathBuf> {
+    let path = Path::new(template);
+    if path.components().count() > 1
+        || path
+            .extension()
+            .is_some_and(|extension| extension == "toml")
+    {
+        return Ok(path.to_path_buf());
+    }
+    let path = templates_dir()?.join(format!("{template}.toml"));
+    if !path.is_file() {
+        return Err(anyhow::anyhow!(
+            "template '{template}' not found at '{}'",
+            path.display()
+        ));
+    }
+    Ok(path)
+}
+
+impl ConfigHandler {
+    /// Initializes a new instance from a template.
+    ///
+    /// Like `init_for_paths`, but the new `config.toml` is a copy of the
+    /// template rather than the built-in defaults. `template` is either the
+    /// name of a file in the templates directory, without `.toml`, or the path
+    /// of any instance `config.toml`. Templates with an older schema are
+    /// migrated in the new instance only.
+    ///
+    /// Returns `true` if initialization was actually performed, `false` if
+    /// configuration already exists and was skipped.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use gluon::config::{ConfigHandler, ConfigPaths};
+    ///
+    /// let initialized = ConfigHandler::init_from_template(ConfigPaths::default(), "modded")?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the template cannot be found or read
+    /// - `anyhow::Error` if the template or the user config contains invalid TOML
+    /// - `anyhow::Error` if the template has a newer schema version than this binary
+    /// - `anyhow::Error` if configuration validation fails
+    /// - `anyhow::Error` if the instance is locked by another process
+    /// - `anyhow::Error` if the configuration files cannot be written
+    pub fn init_from_template(paths: ConfigPaths, template: &str) -> Result<bool> {
+        if super::path::try_find_config_root_from(Path::new(&paths.config)).is_some() {
+            return Ok(false);
+        }
+        let path = template_path(template)?;
+        let data = fs::read_to_string(&path)
+            .with_context(|| format!("failed to read template '{}'", path.display()))?;
+        let document = document::parse(&data, &path)?;
+        let mut table = toml::from_str(&data)
+            .with_context(|| format!("invalid TOML in '{}'", path.display()))?;
+        migrate::upgrade(&mut table, SchemaKind::Runtime, &path)?;
+        let layers = ConfigLayers::load(paths.user_config(), document, table)?;
+        let config = layers
+            .effective()
+            .with_context(|| format!("invalid template '{}'", path.display()))?;
+        ConfigHandler::create(paths, layers, config, LockedConfig::default())?;
+        Ok(true)
+    }
+}
+
+/// Verifies that a template is copied with its comments and resolved by name or path.
+#[test]
+fn test_init_from_template() {
+    let dir = std::env::temp_dir().join("gluon-test-template");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let template = dir.join("modded.toml");
+    fs::write(
+        &template,
+        "# shared settings\ngame_version = \"fabric-1.21\"\nmax_memory_size = 8192\n",
+    )
+    .unwrap();
+
+    assert_eq!(
+        template_path("modded.toml").unwrap(),
+        Path::new("modded.toml")
+    );
+    assert!(template_path("missing-template").is_err());
+
+    let root = dir.join("instance");
+    fs::create_dir_all(&root).unwrap();
+    let paths = ConfigPaths::from_root(&root)
+        .with_account_store(&dir.join("accounts.toml"))
+        .with_user_config(&dir.join("user.toml"));
+    let template = template.to_str().unwrap();
+    assert!(ConfigHandler::init_from_template(paths.clone(), template).unwrap());
+    let data = fs::read_to_string(root.join("config.toml")).unwrap();
+    assert!(data.contains("# shared settings"));
+    let handle = ConfigHandler::read_from_paths(paths.clone()).unwrap();
+    assert_eq!(handle.config().max_memory_size, 8192);
+    drop(handle);
+    assert!(!ConfigHandler::init_from_template(paths, template).unwrap());
+}
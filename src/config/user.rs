@@ -1,82 +1,163 @@
 //! User account structures for the Minecraft launcher.
 //!
-//! Contains user authentication information for both offline mode
-//! and Microsoft account authentication.
+//! Contains user authentication information for offline mode, Microsoft
+//! account authentication and third-party Yggdrasil servers.
 
+use crate::yggdrasil::YggdrasilClient;
 use mc_oauth::MinecraftAuthenticator;
+use md5::{Digest, Md5};
 use serde::{Deserialize, Serialize};
 use std::time::SystemTime;
-use uuid::Uuid;
+use uuid::{Builder, Uuid};
 
 /// User account information for authentication.
 ///
-/// Contains user details and access token for either offline mode
-/// or Microsoft account authentication.
+/// Contains user details and access token for offline mode, Microsoft
+/// account or Yggdrasil authentication.
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct UserAccount {
     /// Display username.
     pub user_name: String,
-    /// Account type ("offline" or "msa").
+    /// Account type ("offline", "msa" or "yggdrasil").
     pub user_type: String,
     /// User UUID string.
     pub user_uuid: String,
-    /// Access token for Microsoft accounts.
+    /// Access token for Microsoft and Yggdrasil accounts.
     pub access_token: Option<String>,
     /// Microsoft refresh token for token refresh (rotation); only present for Microsoft accounts.
     pub refresh_token: Option<String>,
     /// Unix timestamp (seconds) when the access token expires; only present for Microsoft accounts.
     pub token_expires_at: Option<u64>,
+    /// Access and refresh tokens encrypted with the passphrase of a locked
+    /// account store, see `AccountStore::lock`. The plain tokens are `None`
+    /// until the store opens them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sealed_tokens: Option<String>,
+    /// API root of the Yggdrasil server; only present for Yggdrasil accounts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth_server: Option<String>,
+    /// Yggdrasil client token the access token is bound to; only present for
+    /// Yggdrasil accounts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_token: Option<String>,
+    /// Azure client ID the Microsoft tokens were issued to. Refresh tokens only
+    /// work with this client ID, so it is used for every refresh.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub microsoft_client_id: Option<String>,
+    /// Whether the UUID of an offline account was chosen by the user instead
+    /// of derived from its name, so `gluon account migrate` leaves it alone.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub custom_uuid: bool,
 }
 
 impl Default for UserAccount {
-    /// Creates a default offline user account with a generated UUID.
+    /// Creates a default offline user account named `noname`.
     fn default() -> Self {
-        Self {
-            user_name: "noname".to_owned(),
-            user_type: "offline".to_owned(),
-            user_uuid: Uuid::new_v4().to_string(),
-            access_token: None,
-            refresh_token: None,
-            token_expires_at: None,
-        }
+        Self::new_offline("noname")
     }
 }
 
 impl UserAccount {
     /// Creates an offline account with the given username.
     ///
+    /// The UUID is derived from the name like vanilla servers in offline mode
+    /// do, see `offline_uuid`, so recreating the account keeps the player's
+    /// data on such servers.
+    ///
     /// # Example
     /// ```
     /// use gluon::config::UserAccount;
     /// let account = UserAccount::new_offline("Steve");
     /// assert_eq!(account.user_name, "Steve");
     /// assert_eq!(account.user_type, "offline");
+    /// assert_eq!(account.user_uuid, UserAccount::offline_uuid("Steve"));
     /// ```
     #[must_use]
     pub fn new_offline(name: &str) -> Self {
         Self {
             user_name: name.to_owned(),
             user_type: "offline".to_owned(),
-            user_uuid: Uuid::new_v4().to_string(),
+            user_uuid: Self::offline_uuid(name),
             access_token: None,
             refresh_token: None,
             token_expires_at: None,
+            sealed_tokens: None,
+            auth_server: None,
+            client_token: None,
+            microsoft_client_id: None,
+            custom_uuid: false,
         }
     }
 
+    /// Returns the UUID vanilla servers in offline mode give a player name.
+    ///
+    /// This is the name-based (version 3) UUID of the MD5 hash of
+    /// `OfflinePlayer:<name>`, as computed by Java's `UUID.nameUUIDFromBytes`.
+    ///
+    /// # Example
+    /// ```
+    /// use gluon::config::UserAccount;
+    /// assert_eq!(
+    ///     UserAccount::offline_uuid("Notch"),
+    ///     "b50ad385-829d-3141-a216-7e7d7539ba7f"
+    /// );
+    /// ```
+    #[must_use]
+    pub fn offline_uuid(name: &str) -> String {
+        let hash = Md5::digest(format!("OfflinePlayer:{name}"));
+        Builder::from_md5_bytes(hash.into()).into_uuid().to_string()
+    }
+
+    /// Tells whether this is an offline account whose UUID is not the one
+    /// vanilla servers derive from its name, e.g. one created by an older gluon.
+    /// UUIDs chosen by the user, see `custom_uuid`, do not count as random.
+    #[must_use]
+    pub fn has_random_offline_uuid(&self) -> bool {
+        self.user_type == "offline"
+            && !self.custom_uuid
+            && self.user_uuid != Self::offline_uuid(&self.user_name)
+    }
+
     /// Creates a new Microsoft account by authenticating through device code flow.
     ///
     /// This method initiates an interactive authentication process where the user
-    /// must visit a URL and enter a code to authorize the application.
+    /// must visit a URL and enter a code to authorize the application. The client ID
+    /// is read from `AZURE_CLIENT_ID` at runtime if set, see `new_microsoft_with`
+    /// to choose the client ID or the service endpoints.
     ///
     /// # Errors
+    /// - `anyhow::Error` if no client ID is set at runtime or compile time
     /// - `anyhow::Error` if Microsoft device flow initialization fails
     /// - `anyhow::Error` if user authentication times out
     /// - `anyhow::Error` if Xbox Live authentication fails
     /// - `anyhow::Error` if Minecraft authentication fails
     pub fn new_microsoft() -> anyhow::Result<Self> {
+        Self::new_microsoft_with(&MinecraftAuthenticator::from_env()?)
+    }
+
+    /// Creates a new Microsoft account with the given authenticator.
+    ///
+    /// Like `new_microsoft`, but the authenticator decides the client ID and the
+    /// service endpoints, e.g. from `RuntimeConfig::microsoft_authenticator`.
+    /// The client ID is stored with the account, since refreshing its tokens
+    /// needs the same client ID.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use gluon::config::{RuntimeConfig, UserAccount};
+    /// let authenticator = RuntimeConfig::default().microsoft_authenticator()?;
+    /// let account = UserAccount::new_microsoft_with(&authenticator)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if Microsoft device flow initialization fails
+    /// - `anyhow::Error` if user authentication times out
+    /// - `anyhow::Error` if Xbox Live authentication fails
+    /// - `anyhow::Error` if Minecraft authentication fails
+    pub fn new_microsoft_with(authenticator: &MinecraftAuthenticator) -> anyhow::Result<Self> {
         // Step 1: Start device flow
-        let device_flow_state = MinecraftAuthenticator::from_compile_env().start_device_flow()?;
+        let device_flow_state = authenticator.start_device_flow()?;
         println!("{}", device_flow_state.initial_response.message);
 
         // Step 2: Wait for token
@@ -111,6 +192,85 @@ impl UserAccount {
                     .as_secs()
                     + u64::from(minecraft_state.minecraft_token_data.expires_in),
             ),
+            sealed_tokens: None,
+            auth_server: None,
+            client_token: None,
+            microsoft_client_id: Some(authenticator.client_id().to_owned()),
+            custom_uuid: false,
+        })
+    }
+
+    /// Creates a new account by logging in to a Yggdrasil server.
+    ///
+    /// `server` is the API root or a web page pointing to it, see
+    /// `YggdrasilClient::discover`. If the user owns several game profiles,
+    /// `profile` names the one to play with; it may be omitted when the user
+    /// owns a single profile. Yggdrasil access tokens do not expire at a known
+    /// time, they are validated against the server before each launch.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use gluon::config::UserAccount;
+    /// let account = UserAccount::new_yggdrasil("littleskin.cn", "steve@example.com", "secret", None)?;
+    /// assert_eq!(account.user_type, "yggdrasil");
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the server cannot be reached
+    /// - `anyhow::Error` if the credentials are rejected
+    /// - `anyhow::Error` if the user owns no profile, no profile named `profile`,
+    ///   or several profiles and `profile` is `None`
+    pub fn new_yggdrasil(
+        server: &str,
+        username: &str,
+        password: &str,
+        profile: Option<&str>,
+    ) -> anyhow::Result<Self> {
+        let client = YggdrasilClient::discover(server)?;
+        let client_token = Uuid::new_v4().simple().to_string();
+        let mut session = client.authenticate(username, password, &client_token)?;
+        let selected = session.selected_profile.as_ref().map(|x| x.name.as_str());
+        if selected.is_none() || profile.is_some_and(|name| Some(name) != selected) {
+            let chosen = match (profile, session.available_profiles.as_slice()) {
+                (Some(name), profiles) => profiles.iter().find(|x| x.name == name),
+                (None, [profile]) => Some(profile),
+                (None, _) => None,
+            };
+            let Some(chosen) = chosen else {
+                let names: Vec<_> = session
+                    .available_profiles
+                    .iter()
+                    .map(|x| &*x.name)
+                    .collect();
+                if names.is_empty() {
+                    return Err(anyhow::anyhow!(
+                        "{username} has no game profile on the server"
+                    ));
+                }
+                return Err(anyhow::anyhow!(
+                    "choose one of the profiles [{}] of {username} with --profile",
+                    names.join(", ")
+                ));
+            };
+            session = client.refresh(&session.access_token, &session.client_token, Some(chosen))?;
+        }
+        let profile = session
+            .selected_profile
+            .ok_or_else(|| anyhow::anyhow!("the server did not select a profile"))?;
+        Ok(Self {
+            user_name: profile.name,
+            user_type: "yggdrasil".into(),
+            user_uuid: Uuid::try_parse(&profile.id)
+                .map_or(profile.id, |x| x.hyphenated().to_string()),
+            access_token: Some(session.access_token),
+            refresh_token: None,
+            token_expires_at: None,
+            sealed_tokens: None,
+            auth_server: Some(client.api_root().to_owned()),
+            client_token: Some(session.client_token),
+            microsoft_client_id: None,
+            custom_uuid: false,
         })
     }
 }
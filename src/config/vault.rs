@@ -0,0 +1,174 @@
+//! Passphrase encryption of account tokens.
+//!
+//! Tokens are sealed with XChaCha20-Poly1305, an authenticated cipher, under a
+//! key derived from the passphrase with Argon2id. Every sealed value carries
+//! its own random nonce, so the key can seal any number of values. The
+//! passphrase is read once per process, from `GLUON_PASSPHRASE` or from the
+//! terminal.
+
+use anyhow::{Context, Result};
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use dialoguer::theme::ColorfulTheme;
+use dialoguer::Password;
+use std::io::IsTerminal;
+use std::sync::Mutex;
+use zeroize::Zeroizing;
+
+/// Environment variable providing the account store passphrase.
+///
+/// It is removed from the environment of the game, hooks and installer
+/// processes, which must not be able to decrypt the account store.
+pub const ENV_PASSPHRASE: &str = "GLUON_PASSPHRASE";
+
+/// Argon2id memory cost in KiB, time cost and parallelism.
+///
+/// Changing them makes existing stores unreadable, so they are fixed here
+/// rather than taken from the `argon2` defaults.
+const KDF_PARAMS: (u32, u32, u32) = (19 * 1024, 2, 1);
+
+/// Length of the key derivation salt in bytes.
+const SALT_LEN: usize = 16;
+
+/// Length of the XChaCha20-Poly1305 nonce in bytes.
+const NONCE_LEN: usize = 24;
+
+/// Passphrase read earlier in this process.
+static PASSPHRASE: Mutex<Option<Zeroizing<String>>> = Mutex::new(None);
+
+/// Returns the account store passphrase.
+///
+/// Uses `GLUON_PASSPHRASE` if set, otherwise the passphrase given earlier in
+/// this process, otherwise asks for it on the terminal. With `confirm`, a
+/// passphrase typed on the terminal must be entered twice and the one given
+/// earlier is not reused.
+///
+/// # Example
+/// ```no_run
+/// use gluon::config::{read_passphrase, AccountStore};
+///
+/// let passphrase = read_passphrase(true)?;
+/// AccountStore::modify(|store| store.lock(&passphrase))?;
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+///
+/// # Errors
+/// - `anyhow::Error` if `GLUON_PASSPHRASE` is unset and stdin or stdout is not a terminal
+/// - `anyhow::Error` if the prompt fails or is cancelled
+/// - `anyhow::Error` if the passphrase is empty
+pub fn read_passphrase(confirm: bool) -> Result<Zeroizing<String>> {
+    if let Ok(passphrase) = std::env::var(ENV_PASSPHRASE) {
+        return check_passphrase(Zeroizing::new(passphrase));
+    }
+    let mut cached = PASSPHRASE
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    if let (Some(passphrase), false) = (cached.as_ref(), confirm) {
+        return Ok(passphrase.clone());
+    }
+    if !(std::io::stdin().is_terminal() && std::io::stdout().is_terminal()) {
+        return Err(anyhow::anyhow!(
+            "the account store passphrase is needed, set {ENV_PASSPHRASE} or use a terminal"
+        ));
+    }
+    let theme = ColorfulTheme::default();
+    let mut prompt = Password::with_theme(&theme).with_prompt("Account store passphrase");
+    if confirm {
+        prompt = prompt.with_confirmation("Repeat the passphrase", "Passphrases do not match");
+    }
+    let passphrase = check_passphrase(Zeroizing::new(
+        prompt.interact().context("failed to read the passphrase")?,
+    ))?;
+    *cached = Some(passphrase.clone());
+    Ok(passphrase)
+}
+
+/// Forgets the passphrase given earlier in this process, e.g. after it was wrong.
+pub(super) fn forget_passphrase() {
+    *PASSPHRASE
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner) = None;
+}
+
+/// Rejects an empty passphrase.
+fn check_passphrase(passphrase: Zeroizing<String>) -> Result<Zeroizing<String>> {
+    if passphrase.is_empty() {
+        return Err(anyhow::anyhow!(
+            "the account store passphrase cannot be empty"
+        ));
+    }
+    Ok(passphrase)
+}
+
+/// Returns a new random key derivation salt, hex encoded.
+pub(super) fn new_salt() -> String {
+    let mut salt = [0; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    hex::encode(salt)
+}
+
+/// Derives the cipher for a passphrase and a hex encoded salt.
+///
+/// # Errors
+/// - `anyhow::Error` if the salt is not valid hex
+/// - `anyhow::Error` if the key derivation fails
+pub(super) fn cipher(passphrase: &str, salt: &str) -> Result<XChaCha20Poly1305> {
+    let salt = hex::decode(salt).context("invalid salt in the account store")?;
+    let (memory, time, parallelism) = KDF_PARAMS;
+    let params = Params::new(memory, time, parallelism, Some(32))
+        .map_err(|e| anyhow::anyhow!("invalid key derivation parameters: {e}"))?;
+    let mut key = Zeroizing::new([0; 32]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), &salt, key.as_mut())
+        .map_err(|e| anyhow::anyhow!("failed to derive the account store key: {e}"))?;
+    XChaCha20Poly1305::new_from_slice(key.as_ref())
+        .map_err(|e| anyhow::anyhow!("invalid account store key: {e}"))
+}
+
+/// Encrypts a value, returning the hex encoded nonce followed by the ciphertext.
+///
+/// # Errors
+/// - `anyhow::Error` if the encryption fails
+pub(super) fn seal(cipher: &XChaCha20Poly1305, plaintext: &[u8]) -> Result<String> {
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext)
+        .map_err(|e| anyhow::anyhow!("failed to encrypt account tokens: {e}"))?;
+    Ok(hex::encode([nonce.as_slice(), &ciphertext].concat()))
+}
+
+/// Decrypts a value sealed by `seal`.
+///
+/// # Errors
+/// - `anyhow::Error` if the value is not valid hex or too short
+/// - `anyhow::Error` if the value was sealed with another key or was modified
+pub(super) fn open(cipher: &XChaCha20Poly1305, sealed: &str) -> Result<Zeroizing<Vec<u8>>> {
+    let data = hex::decode(sealed).context("invalid sealed value in the account store")?;
+    if data.len() < NONCE_LEN {
+        return Err(anyhow::anyhow!("invalid sealed value in the account store"));
+    }
+    let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+    cipher
+        .decrypt(XNonce::from_slice(nonce), ciphertext)
+        .map(Zeroizing::new)
+        .map_err(|_| anyhow::anyhow!("wrong passphrase for the account store"))
+}
+
+/// Verifies that sealed values open with the same key only.
+#[test]
+fn test_seal_open() {
+    let salt = new_salt();
+    let cipher = cipher("correct horse", &salt).unwrap();
+    let sealed = seal(&cipher, b"token").unwrap();
+    assert_ne!(sealed, seal(&cipher, b"token").unwrap());
+    assert_eq!(open(&cipher, &sealed).unwrap().as_slice(), b"token");
+
+    let wrong = self::cipher("battery staple", &salt).unwrap();
+    assert!(open(&wrong, &sealed).is_err());
+    let mut tampered = sealed.into_bytes();
+    let last = tampered.len() - 1;
+    tampered[last] = if tampered[last] == b'0' { b'1' } else { b'0' };
+    assert!(open(&cipher, &String::from_utf8(tampered).unwrap()).is_err());
+}
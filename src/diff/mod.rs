@@ -0,0 +1,278 @@
+//! Comparison of two instances.
+//!
+//! Compares the runtime settings that decide how the game is launched, the
+//! locked mods and, optionally, the files actually present in the `mods/`
+//! directories. Useful to find out why a setup works in one instance but not
+//! in another.
+
+use crate::config::{ConfigHandler, LockedModConfig, RuntimeConfig};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use sha1::{Digest, Sha1};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+use toml::{Table, Value};
+
+/// Runtime settings that are compared, in the order they are reported.
+const SETTINGS: [&str; 11] = [
+    "game_version",
+    "vanilla",
+    "loader",
+    "java_path",
+    "max_memory_size",
+    "min_memory_size",
+    "jvm_preset",
+    "jvm_presets",
+    "jvm_args",
+    "replace_default_jvm_args",
+    "game_args",
+];
+
+/// A value that differs between two instances.
+///
+/// `left` is `None` if the value only exists in the right instance and
+/// `right` is `None` if it only exists in the left instance.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Difference {
+    /// Setting key, mod name or file name.
+    pub key: String,
+    /// Value in the left instance.
+    pub left: Option<String>,
+    /// Value in the right instance.
+    pub right: Option<String>,
+}
+
+impl Difference {
+    /// Creates a difference if the two values are not equal.
+    fn of(key: &str, left: Option<String>, right: Option<String>) -> Option<Self> {
+        (left != right).then(|| Self {
+            key: key.to_owned(),
+            left,
+            right,
+        })
+    }
+}
+
+/// Differences between two instances.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct InstanceDiff {
+    /// Runtime settings that differ, such as the version, loader and JVM settings.
+    pub settings: Vec<Difference>,
+    /// Locked mods that were added, removed or locked to another version.
+    pub mods: Vec<Difference>,
+    /// Files of the `mods/` directories that differ by SHA-1, if compared.
+    pub files: Option<Vec<Difference>>,
+}
+
+impl InstanceDiff {
+    /// Tells whether the instances have no differences.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.settings.is_empty()
+            && self.mods.is_empty()
+            && self.files.as_ref().is_none_or(Vec::is_empty)
+    }
+}
+
+/// Compares two instances.
+///
+/// Settings are compared on the persisted configuration of each instance, so
+/// handlers should be read without runtime overrides applied.
+/// Mods are compared by name on `config.lock`; a mod differs if its version
+/// or its SHA-1 differs. With `files`, the files of both
+/// `mods/` directories are compared by name and SHA-1.
+///
+/// # Example
+/// ```no_run
+/// use gluon::config::ConfigHandler;
+/// use gluon::diff::diff;
+///
+/// let left = ConfigHandler::read_instance("survival")?;
+/// let right = ConfigHandler::read_instance("survival-test")?;
+/// for difference in diff(&left, &right, false)?.mods {
+///     println!("{}: {:?} -> {:?}", difference.key, difference.left, difference.right);
+/// }
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+///
+/// # Errors
+/// - `anyhow::Error` if a configuration cannot be converted to TOML
+/// - `anyhow::Error` if the game directory of an instance cannot be resolved
+/// - `anyhow::Error` if a mods directory or mod file cannot be read
+pub fn diff(left: &ConfigHandler, right: &ConfigHandler, files: bool) -> Result<InstanceDiff> {
+    let files = if files {
+        Some(diff_maps(
+            &mod_file_hashes(&Path::new(&left.get_absolute_game_dir()?).join("mods"))?,
+            &mod_file_hashes(&Path::new(&right.get_absolute_game_dir()?).join("mods"))?,
+        ))
+    } else {
+        None
+    };
+    Ok(InstanceDiff {
+        settings: diff_settings(left.config(), right.config())?,
+        mods: diff_maps(
+            &locked_mods(left.locked_config().mods.as_ref()),
+            &locked_mods(right.locked_config().mods.as_ref()),
+        ),
+        files,
+    })
+}
+
+/// Compares the runtime settings listed in `SETTINGS`.
+fn diff_settings(left: &RuntimeConfig, right: &RuntimeConfig) -> Result<Vec<Difference>> {
+    let left = Table::try_from(left)?;
+    let right = Table::try_from(right)?;
+    Ok(SETTINGS
+        .iter()
+        .filter_map(|key| {
+            Difference::of(
+                key,
+                left.get(*key).map(Value::to_string),
+                right.get(*key).map(Value::to_string),
+            )
+        })
+        .collect())
+}
+
+/// Describes the locked mods by name, as their version or local file name.
+///
+/// The SHA-1 is added so that rebuilt files of the same version differ too.
+fn locked_mods(mods: Option<&BTreeMap<String, LockedModConfig>>) -> BTreeMap<String, String> {
+    mods.into_iter()
+        .flatten()
+        .map(|(name, locked)| {
+            let version = locked.version.as_ref().unwrap_or(&locked.file_name);
+            let description = match &locked.sha1 {
+                Some(sha1) => format!("{version} (sha1 {})", sha1.get(..8).unwrap_or(sha1)),
+                None => version.clone(),
+            };
+            (name.clone(), description)
+        })
+        .collect()
+}
+
+/// Returns the SHA-1 of every file in a mods directory, keyed by file name.
+///
+/// A missing directory has no files.
+fn mod_file_hashes(dir: &Path) -> Result<BTreeMap<String, String>> {
+    if !dir.is_dir() {
+        return Ok(BTreeMap::new());
+    }
+    let mut hashes = BTreeMap::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_file() {
+            continue;
+        }
+        let path = entry.path();
+        let data =
+            fs::read(&path).with_context(|| format!("failed to read '{}'", path.display()))?;
+        hashes.insert(
+            entry.file_name().to_string_lossy().into_owned(),
+            hex::encode(Sha1::digest(data)),
+        );
+    }
+    Ok(hashes)
+}
+
+/// Compares two maps by key, reporting keys missing on either side and changed values.
+fn diff_maps(left: &BTreeMap<String, String>, right: &BTreeMap<String, String>) -> Vec<Difference> {
+    left.keys()
+        .chain(right.keys())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .filter_map(|key| Difference::of(key, left.get(key).cloned(), right.get(key).cloned()))
+        .collect()
+}
+
+/// Verifies that settings, locked mods and mod files are compared.
+#[test]
+fn test_diff() {
+    let left = RuntimeConfig::default();
+    let mut right = RuntimeConfig {
+        max_memory_size: left.max_memory_size * 2,
+        jvm_preset: Some("zgc".into()),
+        ..RuntimeConfig::default()
+    };
+    right.add_local_mod("sodium.jar");
+    let settings = diff_settings(&left, &right).unwrap();
+    let keys: Vec<_> = settings.iter().map(|d| d.key.as_str()).collect();
+    assert_eq!(keys, ["max_memory_size", "jvm_preset"]);
+    assert_eq!(settings[1].left, None);
+    assert_eq!(settings[1].right.as_deref(), Some("\"zgc\""));
+
+    let locked = |version: &str, sha1: &str| LockedModConfig {
+        file_name: format!("mod-{version}.jar"),
+        version: Some(version.into()),
+        mc_version: "1.21".into(),
+        url: None,
+        sha1: Some(sha1.into()),
+    };
+    let left = BTreeMap::from([
+        ("sodium".to_owned(), locked("0.5", "aaaaaaaaaa")),
+        ("lithium".to_owned(), locked("0.11", "bbbbbbbbbb")),
+    ]);
+    let right = BTreeMap::from([
+        ("sodium".to_owned(), locked("0.6", "cccccccccc")),
+        ("iris".to_owned(), locked("1.7", "dddddddddd")),
+    ]);
+    let mods = diff_maps(&locked_mods(Some(&left)), &locked_mods(Some(&right)));
+    assert_eq!(
+        mods,
+        [
+            Difference {
+                key: "iris".into(),
+                left: None,
+                right: Some("1.7 (sha1 dddddddd)".into()),
+            },
+            Difference {
+                key: "lithium".into(),
+                left: Some("0.11 (sha1 bbbbbbbb)".into()),
+                right: None,
+            },
+            Difference {
+                key: "sodium".into(),
+                left: Some("0.5 (sha1 aaaaaaaa)".into()),
+                right: Some("0.6 (sha1 cccccccc)".into()),
+            },
+        ]
+    );
+
+    let dir = std::env::temp_dir().join("gluon-test-diff");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("a.jar"), "a").unwrap();
+    let hashes = mod_file_hashes(&dir).unwrap();
+    assert_eq!(hashes["a.jar"], "86f7e437faa5a7fce15d1ddcb9eaeaea377667b8");
+    assert!(mod_file_hashes(&dir.join("missing")).unwrap().is_empty());
+}
+
+/// Verifies that persisted settings are compared, not `GLUON_*` overrides.
+#[test]
+fn test_diff_ignores_overrides() {
+    use crate::config::ConfigPaths;
+
+    let dir = std::env::temp_dir().join("gluon-test-diff-overrides");
+    let _ = fs::remove_dir_all(&dir);
+    let paths = |name: &str| {
+        let root = dir.join(name);
+        fs::create_dir_all(&root).unwrap();
+        let paths = ConfigPaths::from_root(&root)
+            .with_account_store(&dir.join("accounts.toml"))
+            .with_user_config(&dir.join("user.toml"));
+        ConfigHandler::init_for_paths(paths.clone()).unwrap();
+        paths
+    };
+    let (left, right) = (paths("left"), paths("right"));
+    let mut handle = ConfigHandler::read_from_paths(right.clone()).unwrap();
+    handle.config_mut().max_memory_size = 1234;
+    handle.commit().unwrap();
+    drop(handle);
+
+    let left = ConfigHandler::read_from_paths(left).unwrap();
+    let right = ConfigHandler::read_from_paths(right).unwrap();
+    let settings = diff(&left, &right, false).unwrap().settings;
+    let keys: Vec<_> = settings.iter().map(|d| d.key.as_str()).collect();
+    assert_eq!(keys, ["max_memory_size"]);
+}
@@ -0,0 +1,367 @@
+//! Instance health check.
+//!
+//! Looks for the problems that otherwise only show up as Java stack traces at
+//! launch: a missing or mismatched Java binary, missing version files, missing
+//! or corrupted mods and an unusable account. Every problem comes with a
+//! command that fixes it.
+
+use crate::config::{ConfigHandler, UserAccount};
+use anyhow::{Context, Result};
+use installer::ShaCompare;
+use mc_api::official::Version;
+use std::cmp::Ordering;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::SystemTime;
+
+/// How serious a finding is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// The check passed.
+    Ok,
+    /// The game may still launch, but something should be looked at.
+    Warning,
+    /// The game will not launch or not work correctly.
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Ok => "ok",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+/// The result of a single check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    /// Name of the check, e.g. `java`.
+    pub check: &'static str,
+    /// How serious the finding is.
+    pub severity: Severity,
+    /// What was found.
+    pub message: String,
+    /// Command fixing the problem, if any.
+    pub fix: Option<String>,
+}
+
+impl Finding {
+    /// Creates a passed check.
+    fn ok(check: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            check,
+            severity: Severity::Ok,
+            message: message.into(),
+            fix: None,
+        }
+    }
+
+    /// Creates a problem of the given severity with a fix command.
+    fn problem(
+        check: &'static str,
+        severity: Severity,
+        message: impl Into<String>,
+        fix: impl Into<String>,
+    ) -> Self {
+        Self {
+            check,
+            severity,
+            message: message.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+/// Checks the health of an instance.
+///
+/// Runs these checks, in this order:
+///
+/// - `java`: the Java binary runs and its major version matches the
+///   `javaVersion` of the version JSON
+/// - `version`: the version JSON is installed and valid
+/// - `client`: the client jar is installed
+/// - `mods`: every locked mod in use exists with the locked SHA-1
+/// - `account`: a Microsoft account has an access token that has not expired
+///
+/// # Example
+/// ```no_run
+/// use gluon::config::ConfigHandler;
+/// use gluon::doctor::{diagnose, Severity};
+///
+/// let config = ConfigHandler::read()?;
+/// for finding in diagnose(&config)? {
+///     if finding.severity != Severity::Ok {
+///         println!("{}: {}", finding.check, finding.message);
+///     }
+/// }
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+///
+/// # Errors
+/// - `anyhow::Error` if the game directory or game root cannot be resolved
+/// - `anyhow::Error` if a mod file exists but cannot be read
+pub fn diagnose(handle: &ConfigHandler) -> Result<Vec<Finding>> {
+    let game_version = &handle.config().game_version;
+    let version_dir = Path::new(&handle.get_absolute_game_root()?)
+        .join("versions")
+        .join(game_version);
+    let (version, version_finding) =
+        check_version(&version_dir.join(format!("{game_version}.json")));
+    let required_java = version
+        .as_ref()
+        .and_then(|version| version.java_version.get("majorVersion"))
+        .and_then(serde_json::Value::as_u64);
+
+    let mut findings = vec![
+        check_java(&handle.config().java_path, required_java),
+        version_finding,
+        check_client(&version_dir.join(format!("{game_version}.jar"))),
+    ];
+    findings.extend(check_mods(handle)?);
+    findings.push(check_account(handle.user_account(), unix_now()));
+    Ok(findings)
+}
+
+/// Checks that the Java binary runs and has the required major version.
+fn check_java(java_path: &str, required: Option<u64>) -> Finding {
+    const CHECK: &str = "java";
+    let fix = |major: Option<u64>| match major {
+        Some(major) => format!("gluon config set java_path <path to Java {major}>"),
+        None => "gluon config set java_path <path to java>".to_owned(),
+    };
+    let output = match Command::new(java_path).arg("-version").output() {
+        Ok(output) if output.status.success() => output,
+        Ok(output) => {
+            let message = format!("'{java_path} -version' failed with {}", output.status);
+            return Finding::problem(CHECK, Severity::Error, message, fix(required));
+        }
+        Err(e) => {
+            let message = format!("cannot run Java '{java_path}': {e}");
+            return Finding::problem(CHECK, Severity::Error, message, fix(required));
+        }
+    };
+    // `java -version` prints to stderr, some builds to stdout.
+    let text = String::from_utf8_lossy(&output.stderr) + String::from_utf8_lossy(&output.stdout);
+    let Some(major) = parse_java_major(&text) else {
+        let message = format!("cannot read the version of Java '{java_path}'");
+        return Finding::problem(CHECK, Severity::Warning, message, fix(required));
+    };
+    let Some(required) = required else {
+        return Finding::ok(CHECK, format!("Java {major} at '{java_path}'"));
+    };
+    match major.cmp(&required) {
+        Ordering::Equal => Finding::ok(CHECK, format!("Java {major} at '{java_path}'")),
+        Ordering::Less => Finding::problem(
+            CHECK,
+            Severity::Error,
+            format!("Java {major} is older than the required Java {required}"),
+            fix(Some(required)),
+        ),
+        Ordering::Greater => Finding::problem(
+            CHECK,
+            Severity::Warning,
+            format!("Java {major} is newer than the expected Java {required}"),
+            fix(Some(required)),
+        ),
+    }
+}
+
+/// Reads the major version from the output of `java -version`.
+///
+/// Handles both the old `1.8.0_402` and the new `21.0.2` version schemes.
+fn parse_java_major(output: &str) -> Option<u64> {
+    let version = output.split('"').nth(1)?;
+    let mut parts = version.split(['.', '_', '-', '+']);
+    match parts.next()? {
+        "1" => parts.next()?.parse().ok(),
+        major => major.parse().ok(),
+    }
+}
+
+/// Checks that the version JSON exists and parses, returning it if it does.
+fn check_version(path: &Path) -> (Option<Version>, Finding) {
+    const CHECK: &str = "version";
+    let data = match fs::read_to_string(path) {
+        Ok(data) => data,
+        Err(e) => {
+            let message = format!("cannot read version JSON '{}': {e}", path.display());
+            return (
+                None,
+                Finding::problem(CHECK, Severity::Error, message, "gluon install"),
+            );
+        }
+    };
+    match serde_json::from_str::<Version>(&data) {
+        Ok(version) => {
+            let message = format!("version JSON of {}", version.id);
+            (Some(version), Finding::ok(CHECK, message))
+        }
+        Err(e) => {
+            let message = format!("invalid version JSON '{}': {e}", path.display());
+            (
+                None,
+                Finding::problem(CHECK, Severity::Error, message, "gluon install"),
+            )
+        }
+    }
+}
+
+/// Checks that the client jar exists.
+fn check_client(path: &Path) -> Finding {
+    if path.is_file() {
+        Finding::ok("client", format!("client jar '{}'", path.display()))
+    } else {
+        Finding::problem(
+            "client",
+            Severity::Error,
+            format!("client jar '{}' is missing", path.display()),
+            "gluon install",
+        )
+    }
+}
+
+/// Checks that every locked mod used by `config.toml` exists with its locked SHA-1.
+fn check_mods(handle: &ConfigHandler) -> Result<Vec<Finding>> {
+    const CHECK: &str = "mods";
+    let (Some(mods), Some(locked)) = (&handle.config().mods, &handle.locked_config().mods) else {
+        return Ok(Vec::new());
+    };
+    let mods_dir = PathBuf::from(handle.get_absolute_game_dir()?).join("mods");
+    let mut findings = Vec::new();
+    let mut checked = 0;
+    for (name, locked) in locked.iter().filter(|(name, _)| mods.contains_key(*name)) {
+        let path = mods_dir.join(&locked.file_name);
+        if !path.is_file() {
+            let message = format!("mod '{name}' is missing '{}'", path.display());
+            findings.push(Finding::problem(
+                CHECK,
+                Severity::Error,
+                message,
+                "gluon mod install",
+            ));
+            continue;
+        }
+        if let Some(sha1) = &locked.sha1 {
+            let data =
+                fs::read(&path).with_context(|| format!("failed to read '{}'", path.display()))?;
+            if data.sha1_cmp(sha1) != Ordering::Equal {
+                let message = format!(
+                    "mod '{name}' does not match its locked SHA-1 '{}'",
+                    path.display()
+                );
+                findings.push(Finding::problem(
+                    CHECK,
+                    Severity::Error,
+                    message,
+                    "gluon mod install",
+                ));
+                continue;
+            }
+        }
+        checked += 1;
+    }
+    if findings.is_empty() {
+        findings.push(Finding::ok(
+            CHECK,
+            format!("{checked} locked mods verified"),
+        ));
+    }
+    Ok(findings)
+}
+
+/// Checks that a Microsoft account has an access token that has not expired at `now`.
+///
+/// Encrypted tokens count as present, their expiry is stored in plain text.
+/// Yggdrasil tokens have no known expiry, only their presence is checked.
+fn check_account(account: &UserAccount, now: u64) -> Finding {
+    const CHECK: &str = "account";
+    if let Some(server) = &account.auth_server {
+        let name = &account.user_name;
+        if account.access_token.is_none() && account.sealed_tokens.is_none() {
+            let message = format!("Yggdrasil account '{name}' has no access token");
+            let fix = format!("gluon account yggdrasil {server} <username>");
+            return Finding::problem(CHECK, Severity::Error, message, fix);
+        }
+        return Finding::ok(CHECK, format!("Yggdrasil account '{name}' on {server}"));
+    }
+    if account.user_type != "msa" {
+        return Finding::ok(CHECK, format!("offline account '{}'", account.user_name));
+    }
+    if account.access_token.is_none() && account.sealed_tokens.is_none() {
+        let message = format!(
+            "Microsoft account '{}' has no access token",
+            account.user_name
+        );
+        return Finding::problem(CHECK, Severity::Error, message, "gluon account microsoft");
+    }
+    match account.token_expires_at {
+        Some(expires_at) if expires_at <= now => Finding::problem(
+            CHECK,
+            Severity::Warning,
+            format!("access token of '{}' has expired", account.user_name),
+            "gluon account refresh",
+        ),
+        Some(expires_at) => Finding::ok(
+            CHECK,
+            format!(
+                "access token of '{}' is valid for {} more minutes",
+                account.user_name,
+                (expires_at - now) / 60
+            ),
+        ),
+        None => Finding::problem(
+            CHECK,
+            Severity::Warning,
+            format!(
+                "access token of '{}' has no known expiry",
+                account.user_name
+            ),
+            "gluon account refresh",
+        ),
+    }
+}
+
+/// Returns the current Unix time in seconds, or 0 if the clock is before the epoch.
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+/// Verifies Java version parsing and the account expiry check.
+#[test]
+fn test_doctor_checks() {
+    let openjdk = "openjdk version \"21.0.2\" 2024-01-16\nOpenJDK Runtime Environment";
+    assert_eq!(parse_java_major(openjdk), Some(21));
+    assert_eq!(parse_java_major("java version \"1.8.0_402\""), Some(8));
+    assert_eq!(parse_java_major("openjdk version \"17-ea\""), Some(17));
+    assert_eq!(parse_java_major("garbage"), None);
+
+    let mut account = UserAccount::new_offline("Steve");
+    assert_eq!(check_account(&account, 100).severity, Severity::Ok);
+    account.user_type = "msa".into();
+    assert_eq!(check_account(&account, 100).severity, Severity::Error);
+    account.access_token = Some("token".into());
+    account.token_expires_at = Some(100);
+    let expired = check_account(&account, 100);
+    assert_eq!(expired.severity, Severity::Warning);
+    assert_eq!(expired.fix.as_deref(), Some("gluon account refresh"));
+    assert_eq!(check_account(&account, 99).severity, Severity::Ok);
+    account.token_expires_at = None;
+    account.auth_server = Some("https://skin.example.com/api/yggdrasil".into());
+    assert_eq!(check_account(&account, 100).severity, Severity::Ok);
+    account.access_token = None;
+    assert_eq!(check_account(&account, 100).severity, Severity::Error);
+
+    let missing = check_java("/nonexistent/java", Some(21));
+    assert_eq!(missing.severity, Severity::Error);
+    assert_eq!(
+        missing.fix.as_deref(),
+        Some("gluon config set java_path <path to Java 21>")
+    );
+}
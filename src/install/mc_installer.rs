@@ -1,17 +1,50 @@
-use crate::config::ConfigHandler;
+use crate::config::{ConfigHandler, LockedFile, RuntimeConfig};
 use anyhow::Result;
+use mc_api::official::Version;
+
+/// A version JSON resolved from the metadata endpoints.
+pub(super) struct ResolvedVersion {
+    /// The version JSON with the loader profile merged in.
+    pub version: Version,
+    /// The official version JSON `version` is based on.
+    pub vanilla_json: LockedFile,
+    /// The loader profile merged into `version`, as JSON.
+    pub loader_profile: Option<String>,
+}
 
 /// Trait for Minecraft installer implementations.
 ///
 /// Provides a uniform interface for installing Minecraft with different mod loaders.
 /// Each loader variant (vanilla, Fabric, `NeoForge`) implements this trait to handle
-/// its specific installation workflow.
+/// its specific installation steps, while `install_game` drives the common workflow
+/// and the lockfile.
 pub(super) trait MCInstaller {
-    /// Installs Minecraft game files and loader dependencies.
+    /// Prepares the installation before the version JSON is resolved or read.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if loader files cannot be downloaded
+    fn prepare(_config: &ConfigHandler) -> Result<()> {
+        Ok(())
+    }
+
+    /// Resolves the version JSON from the metadata endpoints.
     ///
     /// # Errors
     /// - `anyhow::Error` if the version manifest cannot be fetched
-    /// - `anyhow::Error` if the loader version is not found
-    /// - `anyhow::Error` if dependencies cannot be downloaded or installed
-    fn install(config: &ConfigHandler) -> Result<()>;
+    /// - `anyhow::Error` if the game or loader version is not found
+    fn fetch_version(config: &RuntimeConfig) -> Result<ResolvedVersion>;
+
+    /// Merges a loader profile recorded by `fetch_version` into an official version JSON.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the profile is not valid JSON for this loader
+    fn merge_profile(version: &mut Version, profile: &str) -> Result<()>;
+
+    /// Finishes the installation after the game files were downloaded.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if loader specific steps fail
+    fn finish(_config: &ConfigHandler) -> Result<()> {
+        Ok(())
+    }
 }
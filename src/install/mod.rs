@@ -14,25 +14,29 @@
 //! # Installation Workflow
 //!
 //! 1. Determine the loader type from the runtime configuration
-//! 2. Fetch and merge the loader profile with the base Minecraft version JSON
+//! 2. Fetch and merge the loader profile with the base Minecraft version JSON,
+//!    or take both from `config.lock` if it matches the configured game
 //! 3. Download all dependencies (assets, libraries, client JAR, natives)
 //! 4. Execute loader-specific post-install steps (e.g., `NeoForge` processors)
+//! 5. Record the version JSON and every game file checksum in `config.lock`
 //!
 //! # Example
 //! ```no_run
 //! use gluon::install::install_mc;
 //! use gluon::config::ConfigHandler;
 //!
-//! let config = ConfigHandler::default();
-//! install_mc(&config).expect("Installation failed");
+//! let mut config = ConfigHandler::read().expect("Failed to read config");
+//! install_mc(&mut config).expect("Installation failed");
 //! ```
 
-use crate::config::{ConfigHandler, MCLoader};
+use crate::config::{ConfigHandler, LockedFile, LockedGame, MCLoader, MirrorUrls, RuntimeConfig};
+use crate::runtime::{run_hooks, HookEvent};
 use installer::{InstallTask, TaskPool};
 use mc_api::official::{Artifact, Assets, Version};
 use regex::Regex;
+use sha1::{Digest, Sha1};
 use std::{
-    collections::VecDeque,
+    collections::{BTreeMap, VecDeque},
     fs,
     path::{Path, PathBuf},
 };
@@ -47,7 +51,7 @@ mod vanilla;
 use fabric::FabricInstaller;
 use mc_installer::MCInstaller;
 use neoforge::NeoforgeInstaller;
-use vanilla::VanillaInstaller;
+use vanilla::{fetch_locked_vanilla, VanillaInstaller};
 
 /// Operating system identifier set at compile time.
 #[cfg(target_os = "windows")]
@@ -104,17 +108,22 @@ pub enum InstallType {
 ///
 /// Handles the complete installation process including downloading version manifests,
 /// libraries, assets, client JAR, and native libraries. Supports vanilla, Fabric,
-/// and `NeoForge` mod loaders.
+/// and `NeoForge` mod loaders. Runs the `post_install` hooks afterwards.
+///
+/// The resolved game files are recorded in `config.lock`. While the lock matches
+/// the configured game, later installs use it instead of the metadata endpoints,
+/// see `install_locked`.
 ///
 /// # Example
 /// ```no_run
 /// use gluon::install::install_mc;
 /// use gluon::config::ConfigHandler;
 ///
-/// let config = ConfigHandler::default();
-/// if let Err(e) = install_mc(&config) {
+/// let mut config = ConfigHandler::read()?;
+/// if let Err(e) = install_mc(&mut config) {
 ///     eprintln!("Installation failed: {}", e);
 /// }
+/// # Ok::<(), anyhow::Error>(())
 /// ```
 ///
 /// # Errors
@@ -125,20 +134,301 @@ pub enum InstallType {
 /// - `anyhow::Error` if native libraries cannot be extracted
 /// - `anyhow::Error` if file system operations fail
 /// - `anyhow::Error` if network errors occur during download
-pub fn install_mc(config: &ConfigHandler) -> anyhow::Result<()> {
-    match config.config().loader {
-        MCLoader::None => VanillaInstaller::install(config)?,
-        MCLoader::Fabric(_) => FabricInstaller::install(config)?,
-        MCLoader::Neoforge(_) => NeoforgeInstaller::install(config)?,
+/// - `anyhow::Error` if `config.lock` does not match the installed version JSON
+/// - `anyhow::Error` if `config.lock` cannot be written
+/// - `anyhow::Error` if a `post_install` hook fails
+pub fn install_mc(config: &mut ConfigHandler) -> anyhow::Result<()> {
+    install_with(config, false)
+}
+
+/// Installs Minecraft exactly as recorded in `config.lock`.
+///
+/// Unlike `install_mc`, the version manifest and loader metadata are never
+/// consulted: the version JSON is read from disk or rebuilt from the locked
+/// official version JSON and loader profile, and every file is verified
+/// against its locked checksum. The `NeoForge` installer is still downloaded
+/// to run its processors.
+///
+/// # Example
+/// ```no_run
+/// use gluon::install::install_locked;
+/// use gluon::config::ConfigHandler;
+///
+/// let mut config = ConfigHandler::read()?;
+/// install_locked(&mut config)?;
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+///
+/// # Errors
+/// - `anyhow::Error` if `config.lock` has no game files for the configured game
+/// - `anyhow::Error` if a file does not match its locked checksum
+/// - `anyhow::Error` if the game files cannot be downloaded or installed
+/// - `anyhow::Error` if a `post_install` hook fails
+pub fn install_locked(config: &mut ConfigHandler) -> anyhow::Result<()> {
+    install_with(config, true)
+}
+
+/// Installs the game, records its files in `config.lock` and runs the hooks.
+fn install_with(config: &mut ConfigHandler, locked: bool) -> anyhow::Result<()> {
+    let game = match config.config().loader {
+        MCLoader::None => install_game::<VanillaInstaller>(config, locked)?,
+        MCLoader::Fabric(_) => install_game::<FabricInstaller>(config, locked)?,
+        MCLoader::Neoforge(_) => install_game::<NeoforgeInstaller>(config, locked)?,
+    };
+    if config.locked_config().game.as_ref() != Some(&game) {
+        config.locked_config_mut().game = Some(game);
+        config.commit()?;
     }
-    Ok(())
+    run_hooks(config, HookEvent::PostInstall, &[])
+}
+
+/// Installs the game with the given installer and returns its locked files.
+///
+/// Uses the locked game files if they match the configured game, otherwise
+/// resolves the version JSON from the metadata endpoints unless `locked` is set.
+fn install_game<I: MCInstaller>(
+    config: &ConfigHandler,
+    locked: bool,
+) -> anyhow::Result<LockedGame> {
+    I::prepare(config)?;
+    let game_version = &config.config().game_version;
+    let version_json_file_path = Path::new(&config.get_absolute_game_root()?)
+        .join("versions")
+        .join(game_version)
+        .join(format!("{game_version}.json"));
+
+    let lock = config.locked_config().game.as_ref();
+    let (version, game) = match lock.filter(|game| game.matches(config.config())) {
+        Some(game) => {
+            let version = locked_version::<I>(config, game, &version_json_file_path)?;
+            (version, game.clone())
+        }
+        None if locked => {
+            return Err(match lock {
+                Some(game) => anyhow::anyhow!(
+                    "config.lock was written for {}, run 'gluon install' to update it",
+                    game.game_version
+                ),
+                None => anyhow::anyhow!(
+                    "config.lock has no game files, run 'gluon install' to create them"
+                ),
+            });
+        }
+        None => {
+            let resolved = I::fetch_version(config.config())?;
+            let version_sha1 = write_version(&version_json_file_path, &resolved.version)?;
+            let game = locked_game(
+                config.config(),
+                &resolved.version,
+                version_sha1,
+                resolved.vanilla_json,
+                resolved.loader_profile,
+            )?;
+            (resolved.version, game)
+        }
+    };
+
+    let native_dir = Path::new(&config.get_absolute_game_dir()?).join("natives");
+    fs::create_dir_all(native_dir).unwrap_or(());
+    install_dependencies(config, &version)?;
+    I::finish(config)?;
+    Ok(game)
+}
+
+/// Returns the version JSON recorded in `game`.
+///
+/// The installed version JSON is used if its checksum matches, otherwise it is
+/// rebuilt from the locked official version JSON and loader profile.
+///
+/// # Errors
+/// - `anyhow::Error` if the locked official version JSON cannot be fetched
+/// - `anyhow::Error` if the rebuilt version JSON does not match its locked checksum
+/// - `anyhow::Error` if the locked files do not match the version JSON
+fn locked_version<I: MCInstaller>(
+    config: &ConfigHandler,
+    game: &LockedGame,
+    path: &Path,
+) -> anyhow::Result<Version> {
+    let installed = fs::read_to_string(path)
+        .ok()
+        .and_then(|data| serde_json::from_str::<Version>(&data).ok())
+        .filter(|version| {
+            canonical_json(version).is_ok_and(|text| sha1_hex(text) == game.version_sha1)
+        });
+    let version = if let Some(version) = installed {
+        version
+    } else {
+        println!("rebuilding version JSON from config.lock...");
+        let mut version =
+            fetch_locked_vanilla(&config.config().mirror.version_manifest, &game.vanilla_json)?;
+        if let Some(profile) = &game.loader_profile {
+            I::merge_profile(&mut version, profile)?;
+        }
+        if write_version(path, &version)? != game.version_sha1 {
+            return Err(anyhow::anyhow!(
+                "version JSON rebuilt from config.lock does not match its checksum"
+            ));
+        }
+        version
+    };
+
+    let expected = locked_game(
+        config.config(),
+        &version,
+        game.version_sha1.clone(),
+        game.vanilla_json.clone(),
+        game.loader_profile.clone(),
+    )?;
+    if &expected != game {
+        return Err(anyhow::anyhow!(
+            "config.lock does not match the version JSON of {}, remove its [game] table \
+             and run 'gluon install' to resolve the game again",
+            game.game_version
+        ));
+    }
+    Ok(version)
+}
+
+/// Records the files of a version JSON for `config.lock`.
+///
+/// # Errors
+/// - `anyhow::Error` if the client download is missing from the version JSON
+fn locked_game(
+    config: &RuntimeConfig,
+    version: &Version,
+    version_sha1: String,
+    vanilla_json: LockedFile,
+    loader_profile: Option<String>,
+) -> anyhow::Result<LockedGame> {
+    let client = &version.downloads["client"];
+    let client = LockedFile {
+        url: client["url"]
+            .as_str()
+            .ok_or_else(|| anyhow::anyhow!("failed to get client URL"))?
+            .to_owned(),
+        sha1: client["sha1"].as_str().map(ToOwned::to_owned),
+    };
+    let mut libraries = BTreeMap::new();
+    for library in &version.libraries {
+        let downloads = &library.downloads;
+        let classifiers = downloads.classifiers.iter().flat_map(|map| map.values());
+        for artifact in std::iter::once(&downloads.artifact).chain(classifiers) {
+            if !artifact.path.is_empty() {
+                let file = LockedFile {
+                    url: artifact.url.clone(),
+                    sha1: artifact.sha1.clone(),
+                };
+                libraries.insert(artifact.path.clone(), file);
+            }
+        }
+    }
+    Ok(LockedGame {
+        game_version: config.game_version.clone(),
+        vanilla: config.vanilla.clone(),
+        loader: config.loader.clone(),
+        version_sha1,
+        vanilla_json,
+        loader_profile,
+        asset_index: LockedFile {
+            url: version.asset_index.url.clone(),
+            sha1: Some(version.asset_index.sha1.clone()),
+        },
+        client,
+        libraries,
+    })
+}
+
+/// Serializes a version JSON with sorted keys, so equal versions give equal bytes.
+fn canonical_json(version: &Version) -> anyhow::Result<String> {
+    let value = serde_json::to_value(version)?;
+    Ok(serde_json::to_string_pretty(&value)?)
+}
+
+/// Writes a version JSON in its canonical form and returns its SHA-1.
+fn write_version(path: &Path, version: &Version) -> anyhow::Result<String> {
+    let text = canonical_json(version)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, &text)?;
+    Ok(sha1_hex(text))
+}
+
+/// Returns the hex encoded SHA-1 of `data`.
+fn sha1_hex(data: impl AsRef<[u8]>) -> String {
+    hex::encode(Sha1::digest(data))
+}
+
+/// Verifies that all files of a version JSON are locked and that the lock is
+/// independent of the key order of the version JSON.
+#[test]
+fn test_locked_game() {
+    let data = r#"{
+        "arguments": {"game": [], "jvm": []},
+        "assetIndex": {"totalSize": 1, "id": "17", "url": "https://meta/17.json", "sha1": "a1", "size": 1},
+        "assets": "17",
+        "complianceLevel": 1,
+        "downloads": {"client": {"url": "https://meta/client.jar", "sha1": "c1", "size": 1}},
+        "id": "1.21.1",
+        "javaVersion": {"majorVersion": 21, "component": "java-runtime-delta"},
+        "libraries": [{
+            "name": "org.lwjgl:lwjgl:3.3.3",
+            "downloads": {
+                "artifact": {"path": "lwjgl.jar", "sha1": "l1", "size": 1, "url": "https://lib/lwjgl.jar"},
+                "classifiers": {
+                    "natives-linux": {"path": "lwjgl-linux.jar", "sha1": "l2", "size": 1, "url": "https://lib/lwjgl-linux.jar"},
+                    "natives-windows": {"path": "lwjgl-windows.jar", "sha1": "l3", "size": 1, "url": "https://lib/lwjgl-windows.jar"}
+                }
+            }
+        }],
+        "logging": {},
+        "mainClass": "net.minecraft.client.main.Main",
+        "minimumLauncherVersion": 21,
+        "releaseTime": "2024-08-08T12:24:45+00:00",
+        "time": "2024-08-08T12:24:45+00:00",
+        "type": "release"
+    }"#;
+    let version: Version = serde_json::from_str(data).unwrap();
+    let text = canonical_json(&version).unwrap();
+    let reparsed: Version = serde_json::from_str(&text).unwrap();
+    assert_eq!(canonical_json(&reparsed).unwrap(), text);
+
+    let mut config = RuntimeConfig {
+        game_version: "1.21.1".into(),
+        vanilla: "1.21.1".into(),
+        ..RuntimeConfig::default()
+    };
+    let vanilla_json = LockedFile {
+        url: "https://meta/1.21.1.json".into(),
+        sha1: Some("v1".into()),
+    };
+    let game = locked_game(&config, &version, sha1_hex(&text), vanilla_json, None).unwrap();
+    assert!(game.matches(&config));
+    assert_eq!(game.client.sha1.as_deref(), Some("c1"));
+    assert_eq!(game.asset_index.sha1.as_deref(), Some("a1"));
+    assert_eq!(
+        game.libraries.keys().collect::<Vec<_>>(),
+        ["lwjgl-linux.jar", "lwjgl-windows.jar", "lwjgl.jar"]
+    );
+
+    let lock = crate::config::LockedConfig {
+        mods: None,
+        game: Some(game.clone()),
+    };
+    let lock: crate::config::LockedConfig =
+        toml::from_str(&toml::to_string_pretty(&lock).unwrap()).unwrap();
+    assert_eq!(lock.game, Some(game.clone()));
+
+    config.loader = MCLoader::Fabric("0.16.14".into());
+    assert!(!game.matches(&config));
 }
 
 /// Installs all game dependencies for a specific version.
 ///
 /// Orchestrates the installation of all required game files: assets, libraries,
 /// client JAR, and native libraries. Creates download tasks for each file type
-/// and executes them concurrently.
+/// and executes them concurrently. Assets, libraries and the client JAR go to
+/// the shared game root, natives are extracted into the game directory.
 ///
 /// # Errors
 /// - `anyhow::Error` if asset index cannot be fetched
@@ -147,37 +437,39 @@ pub fn install_mc(config: &ConfigHandler) -> anyhow::Result<()> {
 /// - `anyhow::Error` if native library extraction fails
 fn install_dependencies(config: &ConfigHandler, version: &Version) -> anyhow::Result<()> {
     let game_dir = config.get_absolute_game_dir()?;
-    let asset_index_file = Path::new(&game_dir)
+    let game_root = config.get_absolute_game_root()?;
+    let asset_index_file = Path::new(&game_root)
         .join("assets")
         .join("indexes")
         .join(version.asset_index.id.clone() + ".json");
     println!("fetching assets/libraries/natives...");
-    let assets = Assets::fetch(
-        &version.asset_index,
-        &config.config().mirror.version_manifest,
-    )?;
+    let assets = config
+        .config()
+        .mirror
+        .version_manifest
+        .try_each(|mirror| Assets::fetch(&version.asset_index, mirror))?;
     assets.install(&asset_index_file);
-    let mut tasks = assets_installtask(&game_dir, &config.config().mirror.assets, &assets)?;
+    let mut tasks = assets_installtask(&game_root, &config.config().mirror.assets, &assets)?;
     tasks.append(&mut libraries_installtask(
-        &game_dir,
+        &game_root,
         &config.config().mirror.libraries,
         &config.config().mirror.fabric_maven,
         version,
     )?);
     tasks.push_back(client_installtask(
-        &game_dir,
+        &game_root,
         &config.config().game_version,
         &config.config().mirror.client,
         version,
     )?);
     tasks.append(&mut native_installtask(
-        &game_dir,
+        &game_root,
         &config.config().mirror.libraries,
         version,
     )?);
-    TaskPool::from(tasks).install();
+    TaskPool::from(tasks).install()?;
     println!("extracting natives ...");
-    native_extract(&game_dir, version)?;
+    native_extract(&game_root, &game_dir, version)?;
     Ok(())
 }
 
@@ -186,13 +478,14 @@ fn install_dependencies(config: &ConfigHandler, version: &Version) -> anyhow::Re
 /// Filters the version's library list to include only libraries that are
 /// compatible with the current platform and OS, then creates download tasks
 /// for each library. Fabric libraries are downloaded from the Fabric Maven mirror.
+/// Fallback URLs of the mirrors become fallback URLs of the tasks.
 ///
 /// # Errors
 /// - `anyhow::Error` if any library's path cannot be constructed or extracted
 fn libraries_installtask(
-    game_dir: &str,
-    libraries_mirror: &str,
-    fabric_maven_mirror: &str,
+    game_root: &str,
+    libraries_mirror: &MirrorUrls,
+    fabric_maven_mirror: &MirrorUrls,
     version_json: &Version,
 ) -> anyhow::Result<VecDeque<InstallTask>> {
     let libraries = &version_json.libraries;
@@ -204,15 +497,15 @@ fn libraries_installtask(
             let path = &artifact.path;
             let fabric_domain = "https://maven.fabricmc.net/";
             let vanilla_domain = "https://libraries.minecraft.net";
-            let url = if artifact.url.starts_with(vanilla_domain) {
-                libraries_mirror.to_string() + path
+            let (url, fallback_urls) = if artifact.url.starts_with(vanilla_domain) {
+                libraries_mirror.join(path)
             } else if artifact.url.starts_with(fabric_domain) {
-                fabric_maven_mirror.to_string() + path
+                fabric_maven_mirror.join(path)
             } else {
-                artifact.url.clone()
+                (artifact.url.clone(), Vec::new())
             };
 
-            let save_file = Path::new(game_dir).join("libraries").join(path);
+            let save_file = Path::new(game_root).join("libraries").join(path);
             Ok(InstallTask {
                 url,
                 sha1: x.downloads.artifact.sha1.clone(),
@@ -224,6 +517,7 @@ fn libraries_installtask(
                         .display()
                 ),
                 save_file,
+                fallback_urls,
             })
         })
         .collect()
@@ -237,11 +531,11 @@ fn test_libraries_installtask() {
     let manifest_mirror = "https://bmclapi2.bangbang93.com/";
     let manifest = VersionManifest::fetch(manifest_mirror).unwrap();
     let game_dir = "test_dir/";
-    let libraries_mirror = "https://bmclapi2.bangbang93.com/maven/";
-    let fabric_mirror = "https://bmclapi2.bangbang93.com/maven/";
+    let libraries_mirror = MirrorUrls::from("https://bmclapi2.bangbang93.com/maven/");
+    let fabric_mirror = MirrorUrls::from("https://bmclapi2.bangbang93.com/maven/");
     let version_json = Version::fetch(&manifest, "1.16.5", manifest_mirror).unwrap();
     let tasks =
-        libraries_installtask(game_dir, libraries_mirror, fabric_mirror, &version_json).unwrap();
+        libraries_installtask(game_dir, &libraries_mirror, &fabric_mirror, &version_json).unwrap();
     assert!(!tasks.is_empty());
 }
 
@@ -257,8 +551,8 @@ fn test_libraries_installtask() {
 /// - `anyhow::Error` if the current OS is not supported by the library
 /// - `anyhow::Error` if the path cannot be constructed
 fn native_installtask(
-    game_dir: &str,
-    mirror: &str,
+    game_root: &str,
+    mirror: &MirrorUrls,
     version_json: &Version,
 ) -> anyhow::Result<VecDeque<InstallTask>> {
     let libraries = &version_json.libraries;
@@ -280,9 +574,10 @@ fn native_installtask(
                 .get(key)
                 .ok_or_else(|| anyhow::anyhow!("failed to get natives for {key}"))?;
             let path = &artifact.path;
-            let save_file = Path::new(game_dir).join("libraries").join(path);
+            let save_file = Path::new(game_root).join("libraries").join(path);
+            let (url, fallback_urls) = mirror.join(path);
             Ok(InstallTask {
-                url: mirror.to_owned() + path,
+                url,
                 sha1: artifact.sha1.clone(),
                 message: format!(
                     "library {} installed",
@@ -292,6 +587,7 @@ fn native_installtask(
                         .display()
                 ),
                 save_file,
+                fallback_urls,
             })
         })
         .collect()
@@ -305,23 +601,24 @@ fn test_native_installtask() {
     let manifest_mirror = "https://bmclapi2.bangbang93.com/";
     let manifest = VersionManifest::fetch(manifest_mirror).unwrap();
     let game_dir = "test_dir/";
-    let libraries_mirror = "https://bmclapi2.bangbang93.com/maven/";
+    let libraries_mirror = MirrorUrls::from("https://bmclapi2.bangbang93.com/maven/");
     let version_json = Version::fetch(&manifest, "1.16.5", manifest_mirror).unwrap();
-    let tasks = native_installtask(game_dir, libraries_mirror, &version_json).unwrap();
+    let tasks = native_installtask(game_dir, &libraries_mirror, &version_json).unwrap();
     assert!(!tasks.is_empty());
 }
 
 /// Extracts native libraries from downloaded JAR files.
 ///
 /// Extracts platform-specific native libraries (e.g., .so files)
-/// from their JAR containers and places them in the game's natives directory.
+/// from their JAR containers in the game root's libraries directory and
+/// places them in the game's natives directory.
 ///
 /// # Errors
 /// - `anyhow::Error` if no native classifiers are defined for a library
 /// - `anyhow::Error` if the current OS is not supported
 /// - `anyhow::Error` if the JAR file cannot be opened
 /// - `anyhow::Error` if file extraction fails
-fn native_extract(game_dir: &str, version_json: &Version) -> anyhow::Result<()> {
+fn native_extract(game_root: &str, game_dir: &str, version_json: &Version) -> anyhow::Result<()> {
     let libraries = &version_json.libraries;
     libraries
         .iter()
@@ -340,7 +637,7 @@ fn native_extract(game_dir: &str, version_json: &Version) -> anyhow::Result<()>
                 .ok_or_else(|| anyhow::anyhow!("failed to get classifiers"))?
                 .get(key)
                 .ok_or_else(|| anyhow::anyhow!("failed to get natives for {key}"))?;
-            let file_path = Path::new(game_dir).join("libraries").join(&artifact.path);
+            let file_path = Path::new(game_root).join("libraries").join(&artifact.path);
             extract(game_dir, file_path)?;
             Ok(())
         })
@@ -388,36 +685,46 @@ fn extract(game_dir: &str, path: PathBuf) -> anyhow::Result<()> {
 /// Creates a download task for the Minecraft client JAR.
 ///
 /// The client JAR is the main executable file for Minecraft. Constructs the
-/// download task including URL replacement for mirror support. The client JAR is
-/// saved to: `{game_dir}/versions/{game_version}/{game_version}.jar`
+/// download task including URL replacement for mirror support, with one URL
+/// per mirror URL. The client JAR is saved to:
+/// `{game_root}/versions/{game_version}/{game_version}.jar`
 ///
 /// # Errors
 /// - `anyhow::Error` if the client URL cannot be extracted from version metadata
 /// - `anyhow::Error` if domain replacement fails
 /// - `anyhow::Error` if the SHA-1 checksum cannot be extracted
 fn client_installtask(
-    game_dir: &str,
+    game_root: &str,
     game_version: &str,
-    client_mirror: &str,
+    client_mirror: &MirrorUrls,
     version_json: &Version,
 ) -> anyhow::Result<InstallTask> {
     let json_client = &version_json.downloads["client"];
+    let client_url = json_client["url"]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("failed to get client URL"))?
+        .to_string();
+    let mut fallback_urls = client_mirror
+        .urls()
+        .iter()
+        .map(|mirror| client_url.replace_domain(mirror))
+        .collect::<anyhow::Result<VecDeque<_>>>()?;
     Ok(InstallTask {
-        url: json_client["url"]
-            .as_str()
-            .map(|str| str.to_string().replace_domain(client_mirror))
-            .ok_or_else(|| anyhow::anyhow!("failed to get client URL"))??,
+        url: fallback_urls
+            .pop_front()
+            .ok_or_else(|| anyhow::anyhow!("client mirror has no URL"))?,
         sha1: Some(
             json_client["sha1"]
                 .as_str()
                 .ok_or_else(|| anyhow::anyhow!("failed to get client SHA1"))?
                 .to_string(),
         ),
-        save_file: Path::new(game_dir)
+        save_file: Path::new(game_root)
             .join("versions")
             .join(game_version)
             .join(game_version.to_owned() + ".jar"),
         message: "client installed".to_string(),
+        fallback_urls: fallback_urls.into(),
     })
 }
 
@@ -430,10 +737,12 @@ fn test_client_installtask() {
     let manifest = VersionManifest::fetch(manifest_mirror).unwrap();
     let game_dir = "test_dir/";
     let game_version = "1.16.5";
-    let client_mirror = "https://bmclapi2.bangbang93.com/";
+    let mut client_mirror = MirrorUrls::from("https://bmclapi2.bangbang93.com/");
+    client_mirror.push_fallback("https://launcher.mojang.com/");
     let version_json = Version::fetch(&manifest, "1.16.5", manifest_mirror).unwrap();
-    let task = client_installtask(game_dir, game_version, client_mirror, &version_json);
-    assert!(task.is_ok());
+    let task = client_installtask(game_dir, game_version, &client_mirror, &version_json).unwrap();
+    assert!(task.url.starts_with("https://bmclapi2.bangbang93.com/"));
+    assert!(task.fallback_urls[0].starts_with("https://launcher.mojang.com/"));
 }
 
 /// Creates download tasks for all game assets.
@@ -441,13 +750,13 @@ fn test_client_installtask() {
 /// Assets include textures, sounds, models, and other game resources.
 /// Creates a download task for each asset file based on its SHA-1 hash.
 /// Assets are stored in a content-addressable structure based on their SHA-1 hash:
-/// `{game_dir}/assets/objects/{first_two_chars_of_hash}/{full_hash}`.
+/// `{game_root}/assets/objects/{first_two_chars_of_hash}/{full_hash}`.
 ///
 /// # Errors
 /// - `anyhow::Error` if an asset's SHA-1 hash cannot be extracted
 fn assets_installtask(
-    game_dir: &str,
-    assets_mirror: &str,
+    game_root: &str,
+    assets_mirror: &MirrorUrls,
     asset_json: &Assets,
 ) -> anyhow::Result<VecDeque<InstallTask>> {
     asset_json
@@ -456,9 +765,11 @@ fn assets_installtask(
         .into_iter()
         .map(|x| {
             let sha1 = Some(x.1.hash.clone());
+            let (url, fallback_urls) =
+                assets_mirror.join(&format!("{}/{}", &x.1.hash[0..2], x.1.hash));
             Ok(InstallTask {
-                url: assets_mirror.to_owned() + &x.1.hash[0..2] + "/" + &x.1.hash,
-                save_file: Path::new(game_dir)
+                url,
+                save_file: Path::new(game_root)
                     .join("assets")
                     .join("objects")
                     .join(&x.1.hash[0..2])
@@ -469,6 +780,7 @@ fn assets_installtask(
                         .ok_or_else(|| anyhow::anyhow!("failed to get asset SHA1"))?
                 ),
                 sha1,
+                fallback_urls,
             })
         })
         .collect()
@@ -482,9 +794,9 @@ fn test_assets_installtask() {
     let manifest_mirror = "https://bmclapi2.bangbang93.com/";
     let manifest = VersionManifest::fetch(manifest_mirror).unwrap();
     let game_dir = "test_dir/";
-    let assets_mirror = "https://bmclapi2.bangbang93.com/";
+    let assets_mirror = MirrorUrls::from("https://bmclapi2.bangbang93.com/");
     let version_json = Version::fetch(&manifest, "1.16.5", manifest_mirror).unwrap();
-    let assets_json = Assets::fetch(&version_json.asset_index, assets_mirror).unwrap();
-    let task = assets_installtask(game_dir, assets_mirror, &assets_json);
+    let assets_json = Assets::fetch(&version_json.asset_index, assets_mirror.primary()).unwrap();
+    let task = assets_installtask(game_dir, &assets_mirror, &assets_json);
     assert!(!task.unwrap().is_empty());
 }
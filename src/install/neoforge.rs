@@ -1,19 +1,18 @@
-use super::install_dependencies;
 use super::mavencoord::MavenCoord;
-use super::mc_installer::MCInstaller;
+use super::mc_installer::{MCInstaller, ResolvedVersion};
+use super::vanilla::fetch_vanilla;
 use crate::config::ConfigHandler;
 use crate::config::MCLoader;
 use crate::config::RuntimeConfig;
+use crate::config::ENV_PASSPHRASE;
 use anyhow::Result;
 use installer::{InstallTask, TaskPool};
 use mc_api::neoforge;
 use mc_api::neoforge::{InstallerProfile, Profile};
-use mc_api::official::{Version, VersionManifest};
+use mc_api::official::Version;
 use std::collections::{HashMap, VecDeque};
 use std::fs;
-use std::fs::File;
 use std::io;
-use std::io::Read;
 use std::path::Path;
 use std::process::{Command, Stdio};
 use std::thread;
@@ -30,7 +29,7 @@ use std::thread;
 pub(super) struct NeoforgeInstaller;
 
 impl MCInstaller for NeoforgeInstaller {
-    fn install(config: &ConfigHandler) -> Result<()> {
+    fn prepare(config: &ConfigHandler) -> Result<()> {
         let vanilla_version = config.config().vanilla.clone();
         let MCLoader::Neoforge(neoforge_version) = config.config().loader.clone() else {
             return Err(anyhow::anyhow!("loader is not NeoForge"));
@@ -39,10 +38,11 @@ impl MCInstaller for NeoforgeInstaller {
         let tmp_dir =
             std::env::temp_dir().join(format!("{vanilla_version}-neoforge-{neoforge_version}"));
         if !tmp_dir.exists() {
-            let neoforge_jar = neoforge::Installer::fetch(
-                &config.config().mirror.neoforge_neoforge,
-                &neoforge_version,
-            )?;
+            let neoforge_jar = config
+                .config()
+                .mirror
+                .neoforge_neoforge
+                .try_each(|mirror| neoforge::Installer::fetch(mirror, &neoforge_version))?;
 
             println!("extract neoforge installer.jar");
             neoforge_jar.extract(tmp_dir.to_str().unwrap())?;
@@ -51,68 +51,49 @@ impl MCInstaller for NeoforgeInstaller {
                 neoforge_jar.installer.clone(),
             )?;
         }
-        let game_dir = config.get_absolute_game_dir()?;
-
-        let version_json_file_path = Path::new(&game_dir)
-            .join("versions")
-            .join(&config.config().game_version)
-            .join(config.config().game_version.clone() + ".json");
-
-        if !version_json_file_path.exists() {
-            let version = fetch_version(config.config())?;
-            version.install(&version_json_file_path);
-        }
-
-        let native_dir = Path::new(&game_dir).join("natives");
-        fs::create_dir_all(native_dir).unwrap_or(());
-
-        let mut version_json_file = File::open(version_json_file_path)?;
-        let mut content = String::new();
-        version_json_file.read_to_string(&mut content)?;
-
-        let version: Version = serde_json::from_str(&content)?;
-        install_dependencies(config, &version)?;
-        install_installer_dependencies(config)?;
-        process_processors(config)?;
         Ok(())
     }
-}
 
-/// Fetches the merged version JSON for a NeoForge-modded Minecraft version.
-///
-/// Reads the `NeoForge` profile from the extracted installer directory,
-/// fetches the base Minecraft version, and merges them together.
-///
-/// # Errors
-/// - `anyhow::Error` if the `NeoForge` profile JSON cannot be read or parsed
-/// - `anyhow::Error` if the version manifest cannot be fetched
-/// - `anyhow::Error` if the target Minecraft version is not found
-/// - `anyhow::Error` if the base version JSON cannot be fetched
-/// - `anyhow::Error` if the loader is not `MCLoader::Neoforge`
-fn fetch_version(config: &RuntimeConfig) -> Result<Version> {
-    let vanilla_version = config.vanilla.clone();
-    let MCLoader::Neoforge(neoforge_version) = config.loader.clone() else {
-        return Err(anyhow::anyhow!("loader is not NeoForge"));
-    };
-    let tmp_dir =
-        std::env::temp_dir().join(format!("{vanilla_version}-neoforge-{neoforge_version}"));
-    let version_json_file = tmp_dir.join("version.json");
-    let profile = fs::read_to_string(version_json_file)?;
-    let profile: Profile = serde_json::from_str(&profile)?;
+    /// Fetches the merged version JSON for a NeoForge-modded Minecraft version.
+    ///
+    /// Reads the `NeoForge` profile from the extracted installer directory,
+    /// fetches the base Minecraft version, and merges them together.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the `NeoForge` profile JSON cannot be read or parsed
+    /// - `anyhow::Error` if the version manifest cannot be fetched
+    /// - `anyhow::Error` if the target Minecraft version is not found
+    /// - `anyhow::Error` if the base version JSON cannot be fetched
+    /// - `anyhow::Error` if the loader is not `MCLoader::Neoforge`
+    fn fetch_version(config: &RuntimeConfig) -> Result<ResolvedVersion> {
+        let vanilla_version = config.vanilla.clone();
+        let MCLoader::Neoforge(neoforge_version) = config.loader.clone() else {
+            return Err(anyhow::anyhow!("loader is not NeoForge"));
+        };
+        let tmp_dir =
+            std::env::temp_dir().join(format!("{vanilla_version}-neoforge-{neoforge_version}"));
+        let version_json_file = tmp_dir.join("version.json");
+        let profile = fs::read_to_string(version_json_file)?;
+        let profile: Profile = serde_json::from_str(&profile)?;
+
+        let (mut version, vanilla_json) = fetch_vanilla(config)?;
+        version.merge(&profile);
+        Ok(ResolvedVersion {
+            version,
+            vanilla_json,
+            loader_profile: Some(serde_json::to_string(&profile)?),
+        })
+    }
 
-    println!("fetching version manifest...");
-    let manifest = VersionManifest::fetch(&config.mirror.version_manifest)?;
+    fn merge_profile(version: &mut Version, profile: &str) -> Result<()> {
+        version.merge(&serde_json::from_str::<Profile>(profile)?);
+        Ok(())
+    }
 
-    if !manifest.versions.iter().any(|x| x.id == config.vanilla) {
-        return Err(anyhow::anyhow!(
-            "Cannot find the minecraft version {}",
-            config.vanilla
-        ));
+    fn finish(config: &ConfigHandler) -> Result<()> {
+        install_installer_dependencies(config)?;
+        process_processors(config)
     }
-    println!("fetching version...");
-    let mut version = Version::fetch(&manifest, &config.vanilla, &config.mirror.version_manifest)?;
-    version.merge(&profile);
-    Ok(version)
 }
 
 /// Installs `NeoForge` installer-specific library dependencies.
@@ -136,13 +117,12 @@ fn install_installer_dependencies(config: &ConfigHandler) -> Result<()> {
 
     println!("fetching neoforge installer dependencies...");
 
-    let libraries_path = std::path::Path::new(&config.get_absolute_game_dir()?)
+    let libraries_path = std::path::Path::new(&config.get_absolute_game_root()?)
         .join("libraries")
         .display()
         .to_string();
     let tasks: VecDeque<InstallTask> = libraries_installtask(&libraries_path, &installer_profile);
-    TaskPool::from(tasks).install();
-    Ok(())
+    TaskPool::from(tasks).install()
 }
 
 /// Creates download tasks for `NeoForge` installer library dependencies.
@@ -161,6 +141,7 @@ fn libraries_installtask(path: &str, profile: &InstallerProfile) -> VecDeque<Ins
                 sha1: artifact.sha1,
                 save_file: file_path.into(),
                 message: format!("neoforge installer lib {} installed", lib.name.clone()),
+                fallback_urls: Vec::new(),
             }
         })
         .collect()
@@ -202,8 +183,8 @@ fn get_variables(config: &ConfigHandler) -> Result<HashMap<String, String>> {
         config.config().vanilla,
         neoforge_version
     );
-    let game_dir = config.get_absolute_game_dir()?;
-    let path = Path::new(&game_dir)
+    let game_root = config.get_absolute_game_root()?;
+    let path = Path::new(&game_root)
         .join("versions")
         .join(version_dir)
         .join(&filename);
@@ -226,7 +207,7 @@ fn get_variables(config: &ConfigHandler) -> Result<HashMap<String, String>> {
             [b'[', .., b']'] => {
                 let coord = &v.client[1..v.client.len() - 1];
                 let coord_path = MavenCoord::parse(coord).to_path_string();
-                let path = Path::new(&game_dir).join("libraries").join(coord_path);
+                let path = Path::new(&game_root).join("libraries").join(coord_path);
                 let path = std::path::absolute(path)?;
                 path.to_str().unwrap().to_string()
             }
@@ -274,7 +255,7 @@ fn process_processors(config: &ConfigHandler) -> Result<()> {
         serde_json::from_str(&fs::read_to_string(install_profile)?)?;
 
     let variables = get_variables(config)?;
-    let game_dir = config.get_absolute_game_dir()?;
+    let game_root = config.get_absolute_game_root()?;
 
     for process in install_profile.processors {
         if match process.sides {
@@ -302,7 +283,7 @@ fn process_processors(config: &ConfigHandler) -> Result<()> {
                 }
                 if let [b'[', .., b']'] = value.as_bytes() {
                     let path = MavenCoord::parse(&value[1..value.len() - 1]).to_path_string();
-                    let path = Path::new(&game_dir).join("libraries").join(path);
+                    let path = Path::new(&game_root).join("libraries").join(path);
                     value = std::path::absolute(path)?.to_str().unwrap().to_string();
                 }
             }
@@ -313,12 +294,13 @@ fn process_processors(config: &ConfigHandler) -> Result<()> {
         log::debug!("args:{args:#?}");
 
         let classpath = MavenCoord::parse(&process.classpath[0]).to_path_string();
-        let classpath = Path::new(&game_dir).join("libraries").join(classpath);
+        let classpath = Path::new(&game_root).join("libraries").join(classpath);
         log::debug!("program path: {}", classpath.to_str().unwrap());
 
         let mut command = Command::new(&config.config().java_path)
             .args(["-jar", classpath.to_str().unwrap()])
             .args(args)
+            .env_remove(ENV_PASSPHRASE)
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .spawn()?;
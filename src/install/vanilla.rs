@@ -1,68 +1,87 @@
-use super::install_dependencies;
-use super::mc_installer::MCInstaller;
-use crate::config::{ConfigHandler, RuntimeConfig};
+use super::mc_installer::{MCInstaller, ResolvedVersion};
+use super::{sha1_hex, DomainReplacer};
+use crate::config::{LockedFile, MirrorUrls, RuntimeConfig};
 use anyhow::Result;
+use mc_api::fetcher::FetcherBuilder;
 use mc_api::official::{Version, VersionManifest};
-use std::fs;
-use std::fs::File;
-use std::io::Read;
-use std::path::Path;
 
 /// Installer for vanilla (unmodded) Minecraft.
 ///
 /// Downloads and installs the official Minecraft version without any mod loader.
-/// If the version JSON already exists in the game directory, it skips downloading
-/// the manifest and proceeds directly to installing dependencies.
 #[derive(Default)]
 pub(super) struct VanillaInstaller;
 
 impl MCInstaller for VanillaInstaller {
-    fn install(config: &ConfigHandler) -> Result<()> {
-        let game_dir = config.get_absolute_game_dir()?;
-        let version_json_file_path = Path::new(&game_dir)
-            .join("versions")
-            .join(&config.config().game_version)
-            .join(config.config().game_version.clone() + ".json");
-
-        if !version_json_file_path.exists() {
-            let version = fetch_version(config.config())?;
-            version.install(&version_json_file_path);
-        }
-
-        let native_dir = Path::new(&game_dir).join("natives");
-        fs::create_dir_all(native_dir).unwrap_or(());
-
-        let mut version_json_file = File::open(version_json_file_path)?;
-        let mut content = String::new();
-        version_json_file.read_to_string(&mut content)?;
+    fn fetch_version(config: &RuntimeConfig) -> Result<ResolvedVersion> {
+        let (version, vanilla_json) = fetch_vanilla(config)?;
+        Ok(ResolvedVersion {
+            version,
+            vanilla_json,
+            loader_profile: None,
+        })
+    }
 
-        let version: Version = serde_json::from_str(&content)?;
-        install_dependencies(config, &version)?;
+    fn merge_profile(_version: &mut Version, _profile: &str) -> Result<()> {
         Ok(())
     }
 }
 
-/// Fetches the version JSON for a vanilla Minecraft version.
+/// Fetches the official version JSON of the configured vanilla version.
 ///
 /// Downloads the version manifest, validates that the target version exists,
-/// and fetches the corresponding version JSON.
+/// and fetches the corresponding version JSON. Returns the version JSON with
+/// its download URL and checksum.
 ///
 /// # Errors
 /// - `anyhow::Error` if the version manifest cannot be fetched
 /// - `anyhow::Error` if the target Minecraft version is not found
 /// - `anyhow::Error` if the version JSON cannot be fetched
-fn fetch_version(config: &RuntimeConfig) -> Result<Version> {
+pub(super) fn fetch_vanilla(config: &RuntimeConfig) -> Result<(Version, LockedFile)> {
     println!("fetching version manifest...");
-    let manifest = VersionManifest::fetch(&config.mirror.version_manifest)?;
+    let manifest = config
+        .mirror
+        .version_manifest
+        .try_each(VersionManifest::fetch)?;
 
-    if !manifest.versions.iter().any(|x| x.id == config.vanilla) {
+    let Some(entry) = manifest.versions.iter().find(|x| x.id == config.vanilla) else {
         return Err(anyhow::anyhow!(
             "Cannot find the minecraft version {}",
             config.vanilla
         ));
-    }
+    };
 
     println!("fetching version...");
-    let version = Version::fetch(&manifest, &config.vanilla, &config.mirror.version_manifest)?;
-    Ok(version)
+    let file = LockedFile {
+        url: entry.url.clone(),
+        sha1: None,
+    };
+    let data = fetch_json_text(&config.mirror.version_manifest, &file)?;
+    let version = serde_json::from_str(&data)?;
+    Ok((
+        version,
+        LockedFile {
+            sha1: Some(sha1_hex(&data)),
+            ..file
+        },
+    ))
+}
+
+/// Fetches a locked official version JSON, verifying its checksum.
+///
+/// # Errors
+/// - `anyhow::Error` if the version JSON cannot be fetched from any mirror
+/// - `anyhow::Error` if the version JSON is invalid
+pub(super) fn fetch_locked_vanilla(mirror: &MirrorUrls, file: &LockedFile) -> Result<Version> {
+    Ok(serde_json::from_str(&fetch_json_text(mirror, file)?)?)
+}
+
+/// Downloads a JSON file through the mirror, verifying its checksum if known.
+fn fetch_json_text(mirror: &MirrorUrls, file: &LockedFile) -> Result<String> {
+    mirror.try_each(|mirror| {
+        let mut fetcher = FetcherBuilder::fetch(&file.url.replace_domain(mirror)?).text();
+        if let Some(sha1) = &file.sha1 {
+            fetcher = fetcher.sha1(sha1);
+        }
+        fetcher.execute::<String>()?.text()
+    })
 }
@@ -14,14 +14,26 @@
 //!
 //! ## Modules
 //!
+//! - [`archive`]: Instance export and import as portable zip archives
 //! - [`config`]: Configuration handling for game settings, mods, and accounts
+//! - [`diff`]: Comparison of two instances
+//! - [`doctor`]: Instance health check
 //! - [`install`]: Minecraft version and library downloading and installation
 //! - [`mcargument`]: Launch argument generation for JVM and game
+//! - [`mirrorprobe`]: Mirror latency probe and selection
 //! - [`modmanage`]: Mod installation, update, and management
 //! - [`runtime`]: Minecraft game runtime execution
+//! - [`setup`]: Interactive instance setup
+//! - [`yggdrasil`]: Third-party Yggdrasil authentication with authlib-injector
 
+pub mod archive;
 pub mod config;
+pub mod diff;
+pub mod doctor;
 pub mod install;
 pub mod mcargument;
+pub mod mirrorprobe;
 pub mod modmanage;
 pub mod runtime;
+pub mod setup;
+pub mod yggdrasil;
@@ -1,16 +1,31 @@
 use clap::{Parser, Subcommand};
 use clap_cargo::style;
-use gluon::config::{ConfigHandler, MCLoader, MCMirror, VersionType};
-use gluon::install::install_mc;
+use gluon::archive;
+use gluon::config::{
+    read_passphrase, AccountStore, ConfigHandler, ConfigPaths, InstanceRegistry, MCLoader,
+    MCMirror, MirrorDefinition, MirrorUrls, RuntimeConfig, RuntimeOverrides, UserAccount,
+    VersionType, MIRROR_ENDPOINTS,
+};
+use gluon::diff;
+use gluon::doctor::{self, Severity};
+use gluon::install::{install_locked, install_mc};
+use gluon::mirrorprobe;
 use gluon::modmanage;
 use gluon::runtime::gameruntime;
+use gluon::setup::{self, Setup};
+use gluon::yggdrasil::{self, YggdrasilClient};
 use mc_api::{fabric, neoforge, official::VersionManifest};
+use std::path::{Path, PathBuf};
 use tabled::{settings::Style, Table};
 use version_compare::Version;
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None, styles = style::CLAP_STYLING)]
 struct Args {
+    /// Use the named instance instead of the one found from the current directory
+    #[arg(long, global = true)]
+    instance: Option<String>,
+
     #[command(subcommand)]
     command: Command,
 }
@@ -19,13 +34,29 @@ struct Args {
 #[derive(Subcommand, Debug)]
 enum Command {
     /// Init a new instance
-    Init,
+    Init {
+        /// Start from a template name or config.toml path instead of the defaults
+        #[arg(long)]
+        template: Option<String>,
+
+        /// Choose the version, loader, mirror and account step by step
+        #[arg(long)]
+        interactive: bool,
+    },
+
+    /// Manage named instances
+    #[command(subcommand)]
+    Instance(InstanceSub),
+
+    /// Show the configuration
+    #[command(subcommand)]
+    Config(ConfigSub),
 
     /// List Minecraft or Loader versions
     #[command(subcommand)]
     List(ListSub),
 
-    /// Change username
+    /// Manage stored accounts
     #[command(subcommand)]
     Account(Account),
 
@@ -40,18 +71,151 @@ enum Command {
         /// Install neoforge loader
         #[arg(long)]
         neoforge: Option<String>,
+
+        /// Install exactly the game files recorded in config.lock
+        #[arg(long, conflicts_with_all = ["version", "fabric", "neoforge"])]
+        locked: bool,
     },
 
     /// Run the game
-    Run,
+    Run {
+        /// Java executable to use for this run only
+        #[arg(long)]
+        java: Option<String>,
+
+        /// Maximum memory in MB for this run only
+        #[arg(long)]
+        memory: Option<u32>,
+
+        /// Game directory to use for this run only
+        #[arg(long)]
+        game_dir: Option<PathBuf>,
+
+        /// Stored account to use for this run only, by player name or UUID
+        #[arg(long)]
+        account: Option<String>,
+    },
 
-    /// Set Mirror of minecraft api
+    /// Manage download mirrors
     #[command(subcommand)]
     Mirror(Mirrors),
 
     /// Mod Manage
     #[command(subcommand)]
     Mod(ModManage),
+
+    /// Export the instance as a zip archive
+    Export {
+        file: PathBuf,
+
+        /// Extra file or directory of the game directory to export, may be repeated
+        #[arg(long)]
+        include: Vec<PathBuf>,
+    },
+
+    /// Check the instance for problems and suggest fixes
+    Doctor,
+
+    /// Compare the settings and mods of two instances
+    Diff {
+        left: String,
+        right: String,
+
+        /// Also compare the files in the mods directories by SHA-1
+        #[arg(long)]
+        files: bool,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Import an instance from a zip archive and install it
+    Import {
+        file: PathBuf,
+
+        /// Register the imported instance under this name
+        #[arg(long)]
+        name: Option<String>,
+
+        /// Instance directory, defaults to the current directory, or to the
+        /// gluon data directory with --name
+        #[arg(long)]
+        path: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum InstanceSub {
+    /// Create and register a new instance
+    Create {
+        name: String,
+
+        /// Instance directory, defaults to the gluon data directory
+        #[arg(long)]
+        path: Option<PathBuf>,
+    },
+    /// List registered instances
+    List {
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Set the active instance
+    Switch { name: String },
+    /// Unregister an instance
+    Remove {
+        name: String,
+
+        /// Also delete the instance directory
+        #[arg(long)]
+        delete_files: bool,
+    },
+    /// Rename an instance
+    Rename { old: String, new: String },
+    /// Copy an instance with its config, lock and mods
+    Clone {
+        src: String,
+        dst: String,
+
+        /// Instance directory, defaults to the gluon data directory
+        #[arg(long)]
+        path: Option<PathBuf>,
+
+        /// Also copy the saves directory
+        #[arg(long)]
+        with_saves: bool,
+
+        /// Also copy the config directory
+        #[arg(long)]
+        with_config: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ConfigSub {
+    /// Print the instance config.toml
+    Show {
+        /// Print the merged config and the layer each value comes from
+        #[arg(long, conflicts_with = "json")]
+        effective: bool,
+
+        /// Print the merged config as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print the value of a dotted key, e.g. mirror.assets
+    Get {
+        key: String,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Set a dotted key in the instance config.toml
+    Set { key: String, value: String },
+    /// Remove a dotted key from the instance config.toml, falling back to the default
+    Unset { key: String },
 }
 
 #[derive(Subcommand, Debug)]
@@ -82,10 +246,56 @@ enum ListSub {
 
 #[derive(Subcommand, Debug)]
 enum Account {
-    Offline { name: String },
+    /// Add an offline account and make it active
+    Offline {
+        name: String,
+
+        /// UUID to use instead of the one vanilla servers derive from the name
+        #[arg(long)]
+        uuid: Option<uuid::Uuid>,
+    },
+    /// Log in with a Microsoft account and make it active
     Microsoft,
-    /// Refresh the stored Microsoft account tokens
+    /// Log in to a Yggdrasil server used with authlib-injector and make the account active
+    Yggdrasil {
+        /// API root of the server, or its web page if it announces the API root
+        server: String,
+
+        /// Username on the server, usually an email address
+        username: String,
+
+        /// Player name of the profile to use if the user owns several
+        #[arg(long)]
+        profile: Option<String>,
+    },
+    /// Refresh the Microsoft or Yggdrasil account tokens of the current instance
     Refresh,
+    /// List stored accounts
+    List {
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Set the active account, by player name or UUID
+    Use {
+        account: String,
+
+        /// Only use the account for the current instance
+        #[arg(long)]
+        pin: bool,
+    },
+    /// Remove a stored account, by player name or UUID
+    Remove { account: String },
+    /// Encrypt the stored tokens with a passphrase
+    Lock,
+    /// Decrypt the stored tokens and remove the passphrase
+    Unlock,
+    /// Give offline accounts the UUID vanilla servers derive from their name
+    Migrate {
+        /// Accounts to migrate by player name or UUID. If omitted, every offline
+        /// account with a random UUID is migrated, but not ones added with --uuid
+        accounts: Vec<String>,
+    },
 }
 
 #[derive(Subcommand, Debug)]
@@ -96,8 +306,48 @@ enum Loaders {
 
 #[derive(Subcommand, Debug)]
 enum Mirrors {
+    /// Use the official mirror for every endpoint
     Official,
+    /// Use the BMCLAPI mirror for every endpoint
     Bmclapi,
+    /// List named mirrors and the mirror each endpoint uses
+    List,
+    /// Define a named mirror in the instance config.toml
+    Add {
+        name: String,
+
+        /// Endpoint URL, e.g. --endpoint assets=https://mirror.example.com/assets/
+        #[arg(
+            long = "endpoint",
+            value_name = "ENDPOINT=URL",
+            required_unless_present = "from"
+        )]
+        endpoints: Vec<String>,
+
+        /// Start from the endpoints of another mirror
+        #[arg(long)]
+        from: Option<String>,
+    },
+    /// Remove a named mirror from the instance config.toml
+    Remove { name: String },
+    /// Switch endpoints to a named mirror
+    Use {
+        name: String,
+
+        /// Only switch this endpoint, can be repeated
+        #[arg(long = "endpoint", value_name = "ENDPOINT")]
+        endpoints: Vec<String>,
+
+        /// Try the mirror after the current URLs fail instead of replacing them
+        #[arg(long)]
+        fallback: bool,
+    },
+    /// Measure the latency and throughput of every mirror
+    Probe {
+        /// Use the fastest verified mirror of each endpoint, the others as fallbacks
+        #[arg(long)]
+        write: bool,
+    },
 }
 
 #[derive(Subcommand, Debug)]
@@ -185,25 +435,607 @@ fn print_neoforge_table(neoforge_versions: &[String], mc_releases: &[String]) {
     );
 }
 
+fn handle_instance(sub: InstanceSub) -> anyhow::Result<()> {
+    match sub {
+        InstanceSub::Create { name, path } => {
+            let root = ConfigHandler::create_instance(&name, path.as_deref())?;
+            println!("Created instance {name} at {}", root.display());
+        }
+        InstanceSub::List { json } => {
+            let registry = InstanceRegistry::read()?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&registry)?);
+                return Ok(());
+            }
+            if registry.instances.is_empty() {
+                println!("No instances, create one with 'gluon instance create <name>'");
+                return Ok(());
+            }
+            let mut rows = vec![vec![
+                "Active".to_string(),
+                "Name".to_string(),
+                "Path".to_string(),
+            ]];
+            for (name, entry) in &registry.instances {
+                let active = if registry.active.as_ref() == Some(name) {
+                    "*"
+                } else {
+                    ""
+                };
+                rows.push(vec![active.to_string(), name.clone(), entry.path.clone()]);
+            }
+            let mut table: Table = rows.into_iter().collect();
+            println!("{}", table.with(Style::modern()));
+        }
+        InstanceSub::Switch { name } => {
+            let mut registry = InstanceRegistry::read()?;
+            registry.switch(&name)?;
+            registry.write()?;
+            println!("Switched to instance {name}");
+        }
+        InstanceSub::Remove { name, delete_files } => {
+            let mut registry = InstanceRegistry::read()?;
+            let entry = registry.remove(&name)?;
+            registry.write()?;
+            let root = Path::new(&entry.path);
+            if delete_files && root.join("config.toml").exists() {
+                std::fs::remove_dir_all(root)?;
+                println!("Removed instance {name} and deleted {}", entry.path);
+            } else {
+                println!("Removed instance {name}, files kept at {}", entry.path);
+            }
+        }
+        InstanceSub::Rename { old, new } => {
+            let mut registry = InstanceRegistry::read()?;
+            registry.rename(&old, &new)?;
+            registry.write()?;
+            println!("Renamed instance {old} to {new}");
+        }
+        InstanceSub::Clone {
+            src,
+            dst,
+            path,
+            with_saves,
+            with_config,
+        } => {
+            let extra_dirs: Vec<&str> = [(with_saves, "saves"), (with_config, "config")]
+                .into_iter()
+                .filter_map(|(copy, dir)| copy.then_some(dir))
+                .collect();
+            let root = ConfigHandler::clone_instance(&src, &dst, path.as_deref(), &extra_dirs)?;
+            println!("Cloned instance {src} to {dst} at {}", root.display());
+        }
+    }
+    Ok(())
+}
+
+fn handle_account(account: Account) -> anyhow::Result<()> {
+    match account {
+        Account::Offline { name, uuid } => add_offline_account(&name, uuid)?,
+        Account::Microsoft => {
+            let authenticator = current_config()?.microsoft_authenticator()?;
+            let account = UserAccount::new_microsoft_with(&authenticator)?;
+            let name = AccountStore::modify(|store| Ok(store.add(account).user_name.clone()))?;
+            println!("Using Microsoft account {name}");
+        }
+        Account::Yggdrasil {
+            server,
+            username,
+            profile,
+        } => {
+            let password = dialoguer::Password::new()
+                .with_prompt(format!("Password of {username}"))
+                .interact()?;
+            let account =
+                UserAccount::new_yggdrasil(&server, &username, &password, profile.as_deref())?;
+            let name = account.user_name.clone();
+            let server = account.auth_server.clone().unwrap_or_default();
+            AccountStore::modify(|store| {
+                store.add(account);
+                Ok(())
+            })?;
+            println!("Using Yggdrasil account {name} on {server}");
+            if let Err(e) = yggdrasil::ensure_authlib_injector() {
+                eprintln!("Warning: {e:#}, it is downloaded again at launch");
+            }
+        }
+        Account::Refresh => {
+            let mut handle = ConfigHandler::read()?;
+            handle.refresh_account()?;
+            handle.commit()?;
+        }
+        Account::List { json } => list_accounts(json)?,
+        Account::Use { account, pin } => {
+            let store = AccountStore::read()?;
+            let uuid = store.resolve(&account)?;
+            let name = store.accounts[&uuid].user_name.clone();
+            if pin {
+                let mut handle = ConfigHandler::read()?;
+                handle.config_mut().account = Some(uuid);
+                handle.commit()?;
+                println!("Using account {name} for this instance");
+            } else {
+                AccountStore::modify(|store| store.switch(&uuid))?;
+                println!("Using account {name}");
+            }
+        }
+        Account::Remove { account } => {
+            let (store, mut removed) = AccountStore::modify(|store| {
+                let removed = store.remove(&account)?;
+                Ok((store.clone(), removed))
+            })?;
+            println!("Removed account {}", removed.user_name);
+            if removed.auth_server.is_some() {
+                if let Err(e) = invalidate_yggdrasil_tokens(&store, &mut removed) {
+                    eprintln!("Warning: cannot log out of the Yggdrasil server: {e}");
+                }
+            }
+        }
+        Account::Lock => {
+            if AccountStore::read()?.is_locked() {
+                return Err(anyhow::anyhow!("the account store is already locked"));
+            }
+            let passphrase = read_passphrase(true)?;
+            AccountStore::modify(|store| store.lock(&passphrase))?;
+            println!("Locked the account store, tokens are encrypted");
+        }
+        Account::Unlock => {
+            if !AccountStore::read()?.is_locked() {
+                return Err(anyhow::anyhow!("the account store is not locked"));
+            }
+            let passphrase = read_passphrase(false)?;
+            AccountStore::modify(|store| store.unlock(&passphrase))?;
+            println!("Unlocked the account store, tokens are stored in plain text");
+        }
+        Account::Migrate { accounts } => migrate_offline_uuids(accounts)?,
+    }
+    Ok(())
+}
+
+fn current_config() -> anyhow::Result<RuntimeConfig> {
+    if ConfigPaths::resolve().is_ok() {
+        return Ok(ConfigHandler::read()?.config().clone());
+    }
+    RuntimeConfig::read_user()
+}
+
+fn add_offline_account(name: &str, uuid: Option<uuid::Uuid>) -> anyhow::Result<()> {
+    let (uuid, random) = AccountStore::modify(|store| {
+        let mut account = UserAccount::new_offline(name);
+        if let Some(uuid) = uuid {
+            account.user_uuid = uuid.hyphenated().to_string();
+            account.custom_uuid = true;
+        }
+        // Accounts are selected by name, so a name may only be used once.
+        let existing = store
+            .accounts
+            .values()
+            .find(|x| x.user_name.eq_ignore_ascii_case(name) && x.user_uuid != account.user_uuid);
+        match existing {
+            None => Ok((store.add(account).user_uuid.clone(), false)),
+            Some(existing) if uuid.is_none() && existing.user_type == "offline" => {
+                let found = existing.user_uuid.clone();
+                let random = existing.has_random_offline_uuid();
+                store.switch(&found)?;
+                Ok((found, random))
+            }
+            Some(existing) => Err(anyhow::anyhow!(
+                "an account named '{}' already exists with UUID {}, remove it first",
+                existing.user_name,
+                existing.user_uuid
+            )),
+        }
+    })?;
+    println!("Using offline account {name} ({uuid})");
+    if random {
+        println!(
+            "It has a random UUID, run 'gluon account migrate {name}' to use the UUID \
+             offline-mode servers give {name}"
+        );
+    }
+    Ok(())
+}
+
+fn migrate_offline_uuids(accounts: Vec<String>) -> anyhow::Result<()> {
+    let moved = AccountStore::modify(|store| {
+        let accounts = if accounts.is_empty() {
+            store
+                .accounts
+                .values()
+                .filter(|x| x.has_random_offline_uuid())
+                .map(|x| x.user_uuid.clone())
+                .collect()
+        } else {
+            accounts
+        };
+        let mut moved = Vec::new();
+        for account in accounts {
+            moved.extend(store.migrate_offline_uuid(&account)?);
+        }
+        if moved.is_empty() {
+            return Ok(Vec::new());
+        }
+        // Instances pinned to a moved account follow it. The store is written
+        // last, so reading the instances does not warn about their pinned account.
+        let registry = InstanceRegistry::read()?;
+        for name in registry.instances.keys() {
+            let pinned = ConfigHandler::read_instance(name).and_then(|mut handle| {
+                let Some((_, new)) = moved
+                    .iter()
+                    .find(|(old, _)| handle.config().account.as_ref() == Some(old))
+                else {
+                    return Ok(());
+                };
+                handle.config_mut().account = Some(new.clone());
+                handle.commit()
+            });
+            if let Err(e) = pinned {
+                eprintln!("Warning: cannot update the account of instance {name}: {e}");
+            }
+        }
+        Ok(moved
+            .into_iter()
+            .map(|(old, new)| (store.accounts[&new].user_name.clone(), old, new))
+            .collect())
+    })?;
+    if moved.is_empty() {
+        println!("All offline accounts already use the UUID of their name");
+        return Ok(());
+    }
+    for (name, old, new) in &moved {
+        println!("Moved {name} from {old} to {new}");
+    }
+    Ok(())
+}
+
+fn invalidate_yggdrasil_tokens(
+    store: &AccountStore,
+    account: &mut UserAccount,
+) -> anyhow::Result<()> {
+    store.open_account(account)?;
+    if let (Some(server), Some(access_token), Some(client_token)) = (
+        &account.auth_server,
+        &account.access_token,
+        &account.client_token,
+    ) {
+        YggdrasilClient::new(server).invalidate(access_token, client_token)?;
+    }
+    Ok(())
+}
+
+fn list_accounts(json: bool) -> anyhow::Result<()> {
+    let store = AccountStore::read()?;
+    let is_active = |uuid: &String| store.active.as_ref() == Some(uuid);
+    if json {
+        // Tokens are left out, the output is meant to be shared and scripted.
+        let accounts: Vec<_> = store
+            .accounts
+            .iter()
+            .map(|(uuid, account)| {
+                serde_json::json!({
+                    "active": is_active(uuid),
+                    "name": account.user_name,
+                    "type": account.user_type,
+                    "uuid": uuid,
+                })
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&accounts)?);
+        return Ok(());
+    }
+    if store.accounts.is_empty() {
+        println!(
+            "No accounts, add one with 'gluon account offline <name>' or 'gluon account microsoft'"
+        );
+        return Ok(());
+    }
+    let mut rows = vec![vec![
+        "Active".to_string(),
+        "Name".to_string(),
+        "Type".to_string(),
+        "UUID".to_string(),
+    ]];
+    for (uuid, account) in &store.accounts {
+        let active = if is_active(uuid) { "*" } else { "" };
+        rows.push(vec![
+            active.to_string(),
+            account.user_name.clone(),
+            account.user_type.clone(),
+            uuid.clone(),
+        ]);
+    }
+    let mut table: Table = rows.into_iter().collect();
+    println!("{}", table.with(Style::modern()));
+    if store.is_locked() {
+        println!("Tokens are encrypted, run 'gluon account unlock' to decrypt them");
+    }
+    Ok(())
+}
+
+fn init_interactive(mut handle: ConfigHandler) -> anyhow::Result<()> {
+    Setup::prompt(handle.config())?.apply(&mut handle)?;
+    println!("Initialized {}", handle.config().game_version);
+    let install = dialoguer::Confirm::new()
+        .with_prompt("Install now?")
+        .default(true)
+        .interact()?;
+    if install {
+        install_mc(&mut handle)?;
+    } else {
+        println!("Run 'gluon install' to install the game later");
+    }
+    Ok(())
+}
+
+fn handle_config(sub: &ConfigSub) -> anyhow::Result<()> {
+    match sub {
+        ConfigSub::Show { effective, json } => {
+            let handle = ConfigHandler::read()?;
+            if *json {
+                println!("{}", serde_json::to_string_pretty(handle.config())?);
+                return Ok(());
+            }
+            if !*effective {
+                print!("{}", std::fs::read_to_string(handle.config_path())?);
+                return Ok(());
+            }
+            let mut rows = vec![vec![
+                "Key".to_string(),
+                "Value".to_string(),
+                "Source".to_string(),
+            ]];
+            for entry in handle.effective_entries()? {
+                rows.push(vec![
+                    entry.key,
+                    entry.value.to_string(),
+                    entry.layer.to_string(),
+                ]);
+            }
+            let mut table: Table = rows.into_iter().collect();
+            println!("{}", table.with(Style::modern()));
+            match handle.user_config_path() {
+                Some(path) => println!("user: {}", path.display()),
+                None => println!("user: none"),
+            }
+            println!("instance: {}", handle.config_path().display());
+        }
+        ConfigSub::Get { key, json } => {
+            let value = ConfigHandler::read()?.get_value(key)?;
+            if *json {
+                println!("{}", serde_json::to_string_pretty(&value)?);
+            } else if let toml::Value::String(value) = value {
+                // Unquoted, so scripts can use the output directly.
+                println!("{value}");
+            } else {
+                println!("{value}");
+            }
+        }
+        ConfigSub::Set { key, value } => {
+            let mut handle = ConfigHandler::read()?;
+            handle.set_value(key, value)?;
+            handle.commit()?;
+            println!("Set {key} to {}", handle.get_value(key)?);
+        }
+        ConfigSub::Unset { key } => {
+            let mut handle = ConfigHandler::read()?;
+            handle.unset_value(key)?;
+            handle.commit()?;
+            println!("Unset {key}");
+        }
+    }
+    Ok(())
+}
+
+fn handle_mirror(mirror: Mirrors) -> anyhow::Result<()> {
+    let mut handle = ConfigHandler::read()?;
+    match mirror {
+        Mirrors::Official => {
+            handle.config_mut().mirror = MCMirror::official_mirror();
+            println!("Set official mirror");
+        }
+        Mirrors::Bmclapi => {
+            handle.config_mut().mirror = MCMirror::bmcl_mirror();
+            println!("Set BMCLAPI mirror");
+        }
+        Mirrors::List => {
+            print_mirrors(handle.config())?;
+            return Ok(());
+        }
+        Mirrors::Add {
+            name,
+            endpoints,
+            from,
+        } => {
+            let mut definition = match from {
+                Some(from) => handle.config().named_mirror(&from)?,
+                None => MirrorDefinition::new(),
+            };
+            for endpoint in endpoints {
+                let (endpoint, url) = endpoint.split_once('=').ok_or_else(|| {
+                    anyhow::anyhow!("invalid endpoint '{endpoint}', expected ENDPOINT=URL")
+                })?;
+                definition.insert(endpoint.to_owned(), url.to_owned());
+            }
+            handle.config_mut().add_mirror(&name, definition)?;
+            println!("Added mirror {name}");
+        }
+        Mirrors::Probe { write } => {
+            probe_mirrors(&mut handle, write)?;
+            if !write {
+                return Ok(());
+            }
+        }
+        Mirrors::Remove { name } => {
+            handle.remove_mirror(&name)?;
+            println!("Removed mirror {name}");
+        }
+        Mirrors::Use {
+            name,
+            endpoints,
+            fallback: false,
+        } => {
+            handle.config_mut().use_mirror(&name, &endpoints)?;
+            if endpoints.is_empty() {
+                println!("Set mirror {name}");
+            } else {
+                println!("Set mirror {name} for {}", endpoints.join(", "));
+            }
+        }
+        Mirrors::Use {
+            name,
+            endpoints,
+            fallback: true,
+        } => {
+            handle.config_mut().add_fallback_mirror(&name, &endpoints)?;
+            if endpoints.is_empty() {
+                println!("Added mirror {name} as fallback");
+            } else {
+                println!(
+                    "Added mirror {name} as fallback for {}",
+                    endpoints.join(", ")
+                );
+            }
+        }
+    }
+    handle.commit()
+}
+
+/// Prints the named mirrors and the URLs of each endpoint.
+fn print_mirrors(config: &RuntimeConfig) -> anyhow::Result<()> {
+    let mut rows = vec![vec!["Name".to_string(), "Endpoints".to_string()]];
+    for name in config.mirror_names() {
+        let definition = config.named_mirror(&name)?;
+        let endpoints = MIRROR_ENDPOINTS
+            .iter()
+            .filter(|endpoint| definition.contains_key(**endpoint))
+            .copied()
+            .collect::<Vec<_>>();
+        rows.push(vec![name, endpoints.join(", ")]);
+    }
+    let mut table: Table = rows.into_iter().collect();
+    println!("{}", table.with(Style::modern()));
+
+    let mut rows = vec![vec![
+        "Endpoint".to_string(),
+        "URL".to_string(),
+        "Mirror".to_string(),
+    ]];
+    for endpoint in MIRROR_ENDPOINTS {
+        let urls = config
+            .mirror
+            .endpoint(endpoint)
+            .map(MirrorUrls::urls)
+            .unwrap_or_default();
+        rows.push(vec![
+            (*endpoint).to_string(),
+            urls.join("\n"),
+            urls.iter()
+                .map(|url| config.mirrors_of_url(endpoint, url).join(", "))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        ]);
+    }
+    let mut table: Table = rows.into_iter().collect();
+    println!("{}", table.with(Style::modern()));
+    Ok(())
+}
+
+/// Probes every mirror, prints the measurements and, if `write` is set, switches
+/// each endpoint to its fastest verified mirror.
+fn probe_mirrors(handle: &mut ConfigHandler, write: bool) -> anyhow::Result<()> {
+    let results = mirrorprobe::probe(handle.config());
+    let mut rows = vec![vec![
+        "Endpoint".to_string(),
+        "Mirror".to_string(),
+        "Latency".to_string(),
+        "Throughput".to_string(),
+        "Result".to_string(),
+    ]];
+    for result in &results {
+        let row = match &result.outcome {
+            Ok(measurement) => [
+                format!("{} ms", measurement.latency.as_millis()),
+                format!("{} KB/s", measurement.throughput() / 1024),
+                "ok".to_string(),
+            ],
+            Err(e) => [String::new(), String::new(), e.to_string()],
+        };
+        rows.push(
+            [result.endpoint.to_string(), result.mirror.clone()]
+                .into_iter()
+                .chain(row)
+                .collect(),
+        );
+    }
+    let mut table: Table = rows.into_iter().collect();
+    println!("{}", table.with(Style::modern()));
+
+    let fastest = mirrorprobe::fastest_urls(&results);
+    if fastest.is_empty() {
+        println!("no mirror passed the probe");
+    }
+    for (endpoint, urls) in fastest {
+        if write {
+            println!("Set {endpoint} to {urls}");
+            handle
+                .config_mut()
+                .mirror
+                .set_endpoint_urls(endpoint, urls)?;
+        } else {
+            println!("fastest {endpoint}: {}", urls.primary());
+        }
+    }
+    Ok(())
+}
+
 #[allow(
     clippy::too_many_lines,
     reason = "Current implementation is easy to edit, need too many lines"
 )]
 fn handle_args() -> anyhow::Result<()> {
     let args = Args::parse();
+    if let Some(name) = args.instance.as_deref() {
+        ConfigHandler::select_instance(Some(name));
+    }
     match args.command {
-        Command::Init => {
-            if ConfigHandler::init()? {
+        Command::Init {
+            template,
+            interactive,
+        } => {
+            if interactive {
+                setup::ensure_terminal()?;
+            }
+            let initialized = match template {
+                Some(template) => {
+                    ConfigHandler::init_from_template(ConfigPaths::default(), &template)?
+                }
+                None => ConfigHandler::init()?,
+            };
+            if interactive {
+                let handle = if initialized {
+                    ConfigHandler::read_from_paths(ConfigPaths::default())?
+                } else {
+                    println!("config.toml already exists, updating it");
+                    ConfigHandler::read()?
+                };
+                init_interactive(handle)?;
+            } else if initialized {
                 println!("Initialized empty game directory");
             } else {
                 println!("config.toml already exists, skip init");
             }
         }
+        Command::Instance(sub) => handle_instance(sub)?,
+        Command::Config(sub) => handle_config(&sub)?,
         Command::List(sub) => {
             let handle = ConfigHandler::read()?;
             match sub {
                 ListSub::MC { mc, limit, json } => {
-                    let list = VersionManifest::fetch(&handle.config().mirror.version_manifest)?
+                    let list = handle
+                        .config()
+                        .mirror
+                        .version_manifest
+                        .try_each(VersionManifest::fetch)?
                         .list(&mc.into());
                     if json {
                         let json = serde_json::json!({
@@ -219,7 +1051,11 @@ fn handle_args() -> anyhow::Result<()> {
                 }
                 ListSub::Loader { loader, limit, json } => match loader {
                     Loaders::Fabric => {
-                        let l = fabric::Loader::fetch(&handle.config().mirror.fabric_meta)?;
+                        let l = handle
+                            .config()
+                            .mirror
+                            .fabric_meta
+                            .try_each(fabric::Loader::fetch)?;
                         let list: Vec<String> = l.iter().map(|x| x.version.clone()).collect();
                         if json {
                             let json = serde_json::json!({
@@ -234,23 +1070,18 @@ fn handle_args() -> anyhow::Result<()> {
                         }
                     }
                     Loaders::Neoforge => {
-                        let l = neoforge::Loader::fetch(&handle.config().mirror.neoforge_neoforge)?;
+                        let l = handle
+                            .config()
+                            .mirror
+                            .neoforge_neoforge
+                            .try_each(neoforge::Loader::fetch)?;
                         let neoforge_versions = l.versioning.versions.version;
 
                         // Check if game_version is set and valid
-                        if let Some(mc_version) = Version::from(&handle.config().game_version) {
+                        let game_version = &handle.config().game_version;
+                        if Version::from(game_version).is_some() {
                             // Filter by MC version
-                            let list: Vec<String> = neoforge_versions
-                                .into_iter()
-                                .filter(|x| {
-                                    if let Some(neoforge_version) = Version::from(x) {
-                                        mc_version.part(1) == neoforge_version.part(0)
-                                            && mc_version.part(2) == neoforge_version.part(1)
-                                    } else {
-                                        false
-                                    }
-                                })
-                                .collect();
+                            let list = setup::neoforge_versions(game_version, neoforge_versions);
                             if json {
                                 let json = serde_json::json!({
                                     "name": "neoforge loader",
@@ -264,8 +1095,11 @@ fn handle_args() -> anyhow::Result<()> {
                             }
                         } else {
                             // No MC version set, show table grouped by MC version
-                            let manifest =
-                                VersionManifest::fetch(&handle.config().mirror.version_manifest)?;
+                            let manifest = handle
+                                .config()
+                                .mirror
+                                .version_manifest
+                                .try_each(VersionManifest::fetch)?;
                             let mc_releases =
                                 manifest.list(&mc_api::official::VersionType::Release);
                             if json {
@@ -291,22 +1125,18 @@ fn handle_args() -> anyhow::Result<()> {
                 },
             }
         }
-        Command::Account(account_type) => {
-            let mut handle = ConfigHandler::read()?;
-            match account_type {
-                Account::Offline { name } => {
-                    handle.add_offline_account(&name);
-                }
-                Account::Microsoft => handle.add_microsoft_account()?,
-                Account::Refresh => handle.refresh_account()?,
-            }
-        }
+        Command::Account(account) => handle_account(account)?,
         Command::Install {
             version,
             fabric,
             neoforge,
+            locked,
         } => {
             let mut handle = ConfigHandler::read()?;
+            if locked {
+                install_locked(&mut handle)?;
+                return Ok(());
+            }
             // Loader options must be given together with a version argument;
             // installing a loader without a version is undefined behavior
             // (the game_version suffix would be stacked on re-install).
@@ -317,7 +1147,7 @@ fn handle_args() -> anyhow::Result<()> {
                 ));
             }
             if version.is_none() && fabric.is_none() && neoforge.is_none() {
-                install_mc(&handle)?;
+                install_mc(&mut handle)?;
                 return Ok(());
             }
 
@@ -338,27 +1168,34 @@ fn handle_args() -> anyhow::Result<()> {
             } else {
                 handle.config_mut().loader = MCLoader::None;
             }
-            drop(handle);
-            install_mc(&ConfigHandler::read()?)?;
+            handle.commit()?;
+            install_mc(&mut handle)?;
         }
-        Command::Run => {
+        Command::Run {
+            java,
+            memory,
+            game_dir,
+            account,
+        } => {
             let mut config = ConfigHandler::read()?;
+            if let Some(account) = account {
+                config.use_account(&account)?;
+            }
             config.ensure_valid_token()?;
-            gameruntime(&config)?;
-        }
-        Command::Mirror(mirror) => {
-            let mut handle = ConfigHandler::read()?;
-            match mirror {
-                Mirrors::Official => {
-                    handle.config_mut().mirror = MCMirror::official_mirror();
-                    println!("Set official mirror");
-                }
-                Mirrors::Bmclapi => {
-                    handle.config_mut().mirror = MCMirror::bmcl_mirror();
-                    println!("Set BMCLAPI mirror");
-                }
+            config.commit()?;
+            if config.user_account().auth_server.is_some() {
+                yggdrasil::ensure_authlib_injector()?;
             }
+            let overrides = RuntimeOverrides {
+                java_path: java,
+                max_memory_size: memory,
+                game_dir,
+            };
+            let mut launch = config.clone();
+            launch.apply_overrides(&overrides.or(RuntimeOverrides::from_env()?))?;
+            gameruntime(&launch)?;
         }
+        Command::Mirror(mirror) => handle_mirror(mirror)?,
         Command::Mod(option) => match option {
             ModManage::Add {
                 name,
@@ -373,7 +1210,123 @@ fn handle_args() -> anyhow::Result<()> {
             ModManage::Search { name, limit, json } => modmanage::search(&name, limit, json)?,
             ModManage::Clean => modmanage::clean()?,
         },
+        Command::Export { file, include } => {
+            let handle = ConfigHandler::read()?;
+            let count = archive::export(&handle, &file, &include)?;
+            println!("Exported instance with {count} files to {}", file.display());
+        }
+        Command::Import { file, name, path } => import_instance(&file, name.as_deref(), path)?,
+        Command::Doctor => run_doctor()?,
+        Command::Diff {
+            left,
+            right,
+            files,
+            json,
+        } => diff_instances(&left, &right, files, json)?,
+    }
+    Ok(())
+}
+
+/// Prints the findings of `doctor::diagnose` and fails if any check found an error.
+fn run_doctor() -> anyhow::Result<()> {
+    let handle = ConfigHandler::read()?;
+    let findings = doctor::diagnose(&handle)?;
+    let mut rows = vec![vec![
+        "Check".to_string(),
+        "Status".to_string(),
+        "Finding".to_string(),
+        "Fix".to_string(),
+    ]];
+    for finding in &findings {
+        rows.push(vec![
+            finding.check.to_string(),
+            finding.severity.to_string(),
+            finding.message.clone(),
+            finding.fix.clone().unwrap_or_default(),
+        ]);
+    }
+    let mut table: Table = rows.into_iter().collect();
+    println!("{}", table.with(Style::modern()));
+
+    let errors = findings
+        .iter()
+        .filter(|finding| finding.severity == Severity::Error)
+        .count();
+    if errors > 0 {
+        return Err(anyhow::anyhow!("doctor found {errors} problems"));
+    }
+    Ok(())
+}
+
+fn diff_instances(left: &str, right: &str, files: bool, json: bool) -> anyhow::Result<()> {
+    if left == right {
+        return Err(anyhow::anyhow!(
+            "cannot compare instance '{left}' with itself"
+        ));
+    }
+    let diff = diff::diff(
+        &ConfigHandler::read_instance(left)?,
+        &ConfigHandler::read_instance(right)?,
+        files,
+    )?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&diff)?);
+        return Ok(());
+    }
+    if diff.is_empty() {
+        println!("No differences between {left} and {right}");
+        return Ok(());
+    }
+    let no_files = Vec::new();
+    let sections = [
+        ("Setting", &diff.settings),
+        ("Mod", &diff.mods),
+        ("File", diff.files.as_ref().unwrap_or(&no_files)),
+    ];
+    for (title, differences) in sections {
+        if differences.is_empty() {
+            continue;
+        }
+        let mut rows = vec![vec![title.to_string(), left.to_string(), right.to_string()]];
+        for difference in differences {
+            rows.push(vec![
+                difference.key.clone(),
+                difference.left.clone().unwrap_or_else(|| "-".to_string()),
+                difference.right.clone().unwrap_or_else(|| "-".to_string()),
+            ]);
+        }
+        let mut table: Table = rows.into_iter().collect();
+        println!("{}", table.with(Style::modern()));
+    }
+    Ok(())
+}
+
+fn import_instance(file: &Path, name: Option<&str>, path: Option<PathBuf>) -> anyhow::Result<()> {
+    let root = match (path, name) {
+        (Some(path), _) => std::path::absolute(path)?,
+        (None, Some(name)) => InstanceRegistry::default_instance_dir(name)?,
+        (None, None) => std::env::current_dir()?,
+    };
+    // Registering before unpacking rejects invalid or taken names early,
+    // the registry is only written once the import succeeded.
+    let mut registry = InstanceRegistry::read()?;
+    if let Some(name) = name {
+        let root_str = root
+            .to_str()
+            .ok_or_else(|| anyhow::anyhow!("invalid instance path '{}'", root.display()))?;
+        registry.add(name, root_str)?;
+    }
+    let mut handle = archive::import(file, &root)?;
+    if name.is_some() {
+        registry.write()?;
+    }
+    println!("Imported instance to {}", root.display());
+    if handle.locked_config().game.is_some() {
+        install_locked(&mut handle)?;
+    } else {
+        install_mc(&mut handle)?;
     }
+    modmanage::install_for(&mut handle)?;
     Ok(())
 }
 
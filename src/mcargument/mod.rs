@@ -5,6 +5,7 @@
 //! paths, user authentication, and game configuration.
 
 use crate::config::ConfigHandler;
+use crate::yggdrasil::authlib_injector_path;
 use anyhow::{Context, Result};
 use mc_api::official::Version;
 use regex::Regex;
@@ -61,7 +62,7 @@ fn replace_arguments_from_jvm(
         .join("natives")
         .to_string_lossy()
         .into();
-    let library_dir: String = Path::new(&game_dir)
+    let library_dir: String = Path::new(&handle.get_absolute_game_root()?)
         .join("libraries")
         .to_str()
         .unwrap()
@@ -92,7 +93,10 @@ fn replace_arguments_from_game(
 ) -> anyhow::Result<Vec<String>> {
     let js = handle.version_api()?;
     let game_dir = handle.get_absolute_game_dir()?;
-    let assets_root: String = Path::new(&game_dir).join("assets").to_string_lossy().into();
+    let assets_root: String = Path::new(&handle.get_absolute_game_root()?)
+        .join("assets")
+        .to_string_lossy()
+        .into();
     let assets_index_name = js.assets;
     let mut valuemap = HashMap::from([
         (
@@ -104,7 +108,10 @@ fn replace_arguments_from_game(
         ("${assets_root}", assets_root),
         ("${assets_index_name}", assets_index_name),
         ("${auth_uuid}", handle.user_account().user_uuid.clone()),
-        ("${user_type}", handle.user_account().user_type.clone()),
+        (
+            "${user_type}",
+            game_user_type(&handle.user_account().user_type),
+        ),
         ("${version_type}", "release".into()),
     ]);
 
@@ -115,15 +122,43 @@ fn replace_arguments_from_game(
     Ok(replace_arguments(args, &valuemap))
 }
 
+/// Returns the user type passed to the game for an account type.
+///
+/// The game only knows Microsoft and Mojang accounts, Yggdrasil accounts are
+/// passed as the latter like other launchers do.
+fn game_user_type(account_type: &str) -> String {
+    match account_type {
+        "yggdrasil" => "mojang".into(),
+        other => other.into(),
+    }
+}
+
+/// Returns the authlib-injector `-javaagent` argument for a Yggdrasil API root.
+///
+/// # Errors
+/// - `anyhow::Error` if authlib-injector has not been downloaded
+fn authlib_injector_arg(api_root: &str) -> Result<String> {
+    let jar = authlib_injector_path()?;
+    if !fs::exists(&jar)? {
+        return Err(anyhow::anyhow!(
+            "authlib-injector is missing at '{}', run 'gluon run' to download it",
+            jar.display()
+        ));
+    }
+    Ok(format!("-javaagent:{}={api_root}", jar.display()))
+}
+
 impl ConfigHandler {
     /// Generates the complete launch arguments for Minecraft.
     ///
-    /// Combines base JVM settings (memory settings, GC, security flags), version-specific JVM
-    /// arguments from manifest, the main class specification, and version-specific game arguments
-    /// with authentication data. Base JVM arguments include -Xmx{`max_memory_size`}m for maximum
-    /// heap, -Xmn256m for young generation size, -XX:+UseG1GC for G1 garbage collector, and several
-    /// compatibility flags for Forge and Log4j security. Returns a vector of strings representing
-    /// the complete command line for launching Minecraft.
+    /// Combines base JVM settings (memory settings and the JVM preset, see
+    /// `RuntimeConfig::base_jvm_args`), version-specific JVM arguments from manifest, the
+    /// user `jvm_args`, the main class specification, version-specific game arguments with
+    /// authentication data and the user `game_args`. User arguments support the same
+    /// `${variable}` placeholders as the manifest arguments. Yggdrasil accounts
+    /// add the authlib-injector agent pointing at their server before the other JVM
+    /// arguments. Returns a vector of strings representing the complete command line
+    /// for launching Minecraft.
     ///
     /// # Example
     /// ```no_run
@@ -138,29 +173,28 @@ impl ConfigHandler {
     /// - `anyhow::Error` if version API JSON cannot be read
     /// - `anyhow::Error` if classpath cannot be generated
     /// - `anyhow::Error` if game directory or version files cannot be accessed
+    /// - `anyhow::Error` if the memory settings or the JVM preset are invalid
+    /// - `anyhow::Error` if authlib-injector is needed but has not been downloaded,
+    ///   see `gluon::yggdrasil::ensure_authlib_injector`
     pub fn args_provider(&self) -> anyhow::Result<Vec<String>> {
-        let mut args = vec![
-            format!("-Xmx{}m", self.config().max_memory_size),
-            format!("-Xmn256m"),
-            format!("-XX:+UseG1GC"),
-            format!("-XX:-UseAdaptiveSizePolicy"),
-            format!("-XX:-OmitStackTraceInFastThrow"),
-            format!("-Dfml.ignoreInvalidMinecraftCertificates=True"),
-            format!("-Dfml.ignorePatchDiscrepancies=True"),
-            format!("-Dlog4j2.formatMsgNoLookups=true"),
-            format!("-XX:HeapDumpPath=MojangTricksIntelDriversForPerformance_javaw.exe_minecraft.exe.heapdump"),
-        ];
+        let mut args = Vec::new();
+        if let Some(api_root) = &self.user_account().auth_server {
+            args.push(authlib_injector_arg(api_root)?);
+        }
+        args.extend(self.config().base_jvm_args()?);
 
         let js = self.version_api()?;
         let jvm = &mut js.arguments.jvm.clone();
 
-        let jvm_args = Self::get_normal_args_from(jvm);
+        let mut jvm_args = Self::get_normal_args_from(jvm);
+        jvm_args.extend(self.config().jvm_args.iter().cloned());
         let mut jvm_args = replace_arguments_from_jvm(&jvm_args, self, &js)?;
         args.append(&mut jvm_args);
         args.push(js.main_class.as_str().into());
 
         let game = &mut js.arguments.game.clone();
-        let game_args = Self::get_normal_args_from(game);
+        let mut game_args = Self::get_normal_args_from(game);
+        game_args.extend(self.config().game_args.iter().cloned());
         let mut game_args = replace_arguments_from_game(&game_args, self)?;
         args.append(&mut game_args);
 
@@ -181,8 +215,9 @@ impl ConfigHandler {
 
     /// Generates the complete Java classpath for the game.
     ///
-    /// Includes all required libraries and the client JAR file, handling library version
-    /// resolution to ensure that only the latest version of each library is included.
+    /// Includes all required libraries and the client JAR file from the shared game root,
+    /// handling library version resolution to ensure that only the latest version of each
+    /// library is included.
     /// Filters libraries for the current platform and excludes older versions when a newer
     /// version of the same library exists. Returns a platform-specific classpath string
     /// with entries separated by `:` on Unix-like systems or `;` on Windows.
@@ -198,7 +233,7 @@ impl ConfigHandler {
     /// - `anyhow::Error` if a library path cannot be constructed
     /// - `anyhow::Error` if version comparison fails
     fn get_classpaths(&self, version_api: &Version) -> anyhow::Result<String> {
-        let game_dir = self.get_absolute_game_dir()?;
+        let game_root = self.get_absolute_game_root()?;
         let mut paths: Vec<String> = version_api
             .libraries
             .iter()
@@ -236,7 +271,7 @@ impl ConfigHandler {
                 match has_greater_version {
                     Ok(has_greater_version) => {
                         if lib.is_target_lib() && !has_greater_version {
-                            let path = Path::new(&game_dir)
+                            let path = Path::new(&game_root)
                                 .join("libraries")
                                 .join(&lib.downloads.artifact.path)
                                 .to_string_lossy()
@@ -252,7 +287,7 @@ impl ConfigHandler {
             })
             .collect::<Result<_>>()?;
 
-        let client_path = Path::new(&game_dir)
+        let client_path = Path::new(&game_root)
             .join("versions")
             .join(&self.config().game_version)
             .join(self.config().game_version.clone() + ".jar")
@@ -268,14 +303,14 @@ impl ConfigHandler {
     /// and parses it into a structured Version object. Returns a Version object containing
     /// parsed version metadata including main class name, JVM arguments, game arguments,
     /// library dependencies, and asset index information. The version JSON is located at
-    /// `{game_dir}/versions/{game_version}/{game_version}.json`.
+    /// `{game_root}/versions/{game_version}/{game_version}.json`.
     ///
     /// # Errors
     /// - `anyhow::Error` if the version JSON file cannot be read
     /// - `anyhow::Error` if the JSON cannot be parsed
     /// - `anyhow::Error` if the file is missing or inaccessible
     fn version_api(&self) -> anyhow::Result<Version> {
-        let jsfile_path = Path::new(&self.get_absolute_game_dir()?)
+        let jsfile_path = Path::new(&self.get_absolute_game_root()?)
             .join("versions")
             .join(&self.config().game_version)
             .join(self.config().game_version.clone() + ".json");
@@ -0,0 +1,374 @@
+//! Mirror latency probe and selection.
+//!
+//! Downloads a small known object from every mirror defining an endpoint,
+//! measures the latency and throughput of each download and verifies the
+//! content. The fastest verified mirror of each endpoint can then be written to
+//! `RuntimeConfig.mirror`, followed by the other verified mirrors as fallbacks.
+//!
+//! Metadata endpoints are probed with fixed objects whose content is checked by
+//! its format. Asset, library and client objects are taken from the latest
+//! release, so their content can be checked against the SHA-1 published in
+//! the version metadata.
+
+use crate::config::{MirrorUrls, RuntimeConfig, MIRROR_ENDPOINTS};
+use anyhow::{Context, Result};
+use installer::ShaCompare;
+use mc_api::official::{Assets, Version, VersionManifest};
+use reqwest::blocking::Client;
+use reqwest::header;
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::time::{Duration, Instant};
+
+/// Timeout of a single probe download.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Number of bytes read from objects too large to download completely.
+const PARTIAL_PROBE_SIZE: u64 = 256 * 1024;
+
+/// Name used for configured URLs that no named mirror defines.
+pub const CURRENT_MIRROR: &str = "(current)";
+
+/// How the content of a probed object is verified.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verify {
+    /// The SHA-1 hash of the content must match.
+    Sha1(String),
+    /// The content must be valid JSON.
+    Json,
+    /// The content must start with these bytes.
+    Prefix(&'static [u8]),
+}
+
+impl Verify {
+    /// Checks downloaded content.
+    ///
+    /// # Example
+    /// ```
+    /// use gluon::mirrorprobe::Verify;
+    /// assert!(Verify::Json.check(br#"{"latest": {}}"#).is_ok());
+    /// assert!(Verify::Prefix(b"<?xml").check(b"<html>").is_err());
+    /// ```
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the content does not pass the verification
+    pub fn check(&self, data: &[u8]) -> Result<()> {
+        match self {
+            Verify::Sha1(sha1) if !data.sha1_cmp(sha1).is_eq() => {
+                Err(anyhow::anyhow!("content does not match SHA-1 {sha1}"))
+            }
+            Verify::Json => serde_json::from_slice::<serde_json::Value>(data)
+                .map(|_| ())
+                .context("content is not valid JSON"),
+            Verify::Prefix(prefix) if !data.starts_with(prefix) => {
+                Err(anyhow::anyhow!("content has an unexpected format"))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A known object probed on every mirror of an endpoint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProbeTarget {
+    /// Name of the endpoint, one of `MIRROR_ENDPOINTS`.
+    pub endpoint: &'static str,
+    /// Path of the object, appended to the endpoint URL.
+    pub path: String,
+    /// Verification of the downloaded content.
+    pub verify: Verify,
+    /// Whether only the beginning of the object is downloaded.
+    pub partial: bool,
+}
+
+impl ProbeTarget {
+    /// Creates a target downloaded completely.
+    fn new(endpoint: &'static str, path: &str, verify: Verify) -> Self {
+        Self {
+            endpoint,
+            path: path.to_owned(),
+            verify,
+            partial: false,
+        }
+    }
+}
+
+/// Timing of a successful probe download.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Measurement {
+    /// Time until the response headers arrived.
+    pub latency: Duration,
+    /// Time until the downloaded content was complete.
+    pub total: Duration,
+    /// Number of bytes downloaded.
+    pub bytes: usize,
+}
+
+impl Measurement {
+    /// Returns the download speed in bytes per second.
+    ///
+    /// # Example
+    /// ```
+    /// use gluon::mirrorprobe::Measurement;
+    /// use std::time::Duration;
+    /// let measurement = Measurement {
+    ///     latency: Duration::from_millis(100),
+    ///     total: Duration::from_millis(500),
+    ///     bytes: 1000,
+    /// };
+    /// assert_eq!(measurement.throughput(), 2000);
+    /// ```
+    #[must_use]
+    pub fn throughput(&self) -> u128 {
+        self.bytes as u128 * 1_000_000 / self.total.as_micros().max(1)
+    }
+}
+
+/// Result of probing one mirror of an endpoint.
+#[derive(Debug)]
+pub struct ProbeResult {
+    /// Name of the endpoint.
+    pub endpoint: &'static str,
+    /// Name of the mirror, or [`CURRENT_MIRROR`].
+    pub mirror: String,
+    /// URL of the endpoint on this mirror.
+    pub url: String,
+    /// Timing of the download, or why it failed or was rejected.
+    pub outcome: Result<Measurement>,
+}
+
+/// Returns the mirrors of an endpoint as `(name, url)` pairs.
+///
+/// Named mirrors come first, followed by configured URLs that no named mirror
+/// defines. Every URL is listed once.
+#[must_use]
+pub fn candidates(config: &RuntimeConfig, endpoint: &str) -> Vec<(String, String)> {
+    let mut candidates: Vec<(String, String)> = Vec::new();
+    let named = config.mirror_names().into_iter().filter_map(|name| {
+        let url = config.named_mirror(&name).ok()?.remove(endpoint)?;
+        Some((name, url))
+    });
+    let current = config
+        .mirror
+        .endpoint(endpoint)
+        .map(MirrorUrls::urls)
+        .unwrap_or_default()
+        .iter()
+        .map(|url| (CURRENT_MIRROR.to_owned(), url.clone()));
+    for (name, url) in named.chain(current) {
+        if !candidates.iter().any(|(_, known)| *known == url) {
+            candidates.push((name, url));
+        }
+    }
+    candidates
+}
+
+/// Returns the objects probed for every endpoint.
+///
+/// Asset, library and client objects are resolved from the latest release
+/// using the first mirror serving its metadata. They are left out, with a
+/// message, if no mirror does.
+#[must_use]
+pub fn probe_targets(config: &RuntimeConfig) -> Vec<ProbeTarget> {
+    let mut targets = vec![
+        ProbeTarget::new(
+            "version_manifest",
+            "mc/game/version_manifest.json",
+            Verify::Json,
+        ),
+        ProbeTarget::new("fabric_meta", "v2/versions/loader", Verify::Json),
+        ProbeTarget::new(
+            "fabric_maven",
+            "net/fabricmc/fabric-loader/maven-metadata.xml",
+            Verify::Prefix(b"<?xml"),
+        ),
+        ProbeTarget::new(
+            "neoforge_forge",
+            "/maven-metadata.xml",
+            Verify::Prefix(b"<?xml"),
+        ),
+        ProbeTarget::new(
+            "neoforge_neoforge",
+            "/maven-metadata.xml",
+            Verify::Prefix(b"<?xml"),
+        ),
+    ];
+    let release = candidates(config, "version_manifest")
+        .into_iter()
+        .find_map(|(_, mirror)| release_targets(&mirror).ok());
+    match release {
+        Some(release) => targets.extend(release),
+        None => println!("cannot fetch the latest release, skipping assets, libraries and client"),
+    }
+    targets.sort_by_key(|target| {
+        MIRROR_ENDPOINTS
+            .iter()
+            .position(|endpoint| *endpoint == target.endpoint)
+    });
+    targets
+}
+
+/// Resolves asset, library and client objects of the latest release.
+///
+/// The smallest asset and library are used to keep the probe fast.
+///
+/// # Errors
+/// - `anyhow::Error` if the release metadata cannot be fetched
+/// - `anyhow::Error` if the release has no asset, library or client
+fn release_targets(mirror: &str) -> Result<Vec<ProbeTarget>> {
+    let manifest = VersionManifest::fetch(mirror)?;
+    let version = Version::fetch(&manifest, &manifest.latest.release, mirror)?;
+    let assets = Assets::fetch(&version.asset_index, mirror)?;
+
+    let asset = assets
+        .objects
+        .values()
+        .min_by_key(|asset| asset.size)
+        .context("release has no assets")?;
+    let library = version
+        .libraries
+        .iter()
+        .map(|library| &library.downloads.artifact)
+        .filter(|artifact| artifact.url.starts_with("https://libraries.minecraft.net/"))
+        .filter(|artifact| artifact.sha1.is_some())
+        .min_by_key(|artifact| artifact.size.unwrap_or(i32::MAX))
+        .context("release has no libraries")?;
+    let client = version.downloads["client"]["url"]
+        .as_str()
+        .context("failed to get client URL")?;
+    let client = reqwest::Url::parse(client)?;
+
+    Ok(vec![
+        ProbeTarget::new(
+            "assets",
+            &format!("{}/{}", &asset.hash[0..2], asset.hash),
+            Verify::Sha1(asset.hash.clone()),
+        ),
+        ProbeTarget::new(
+            "libraries",
+            &library.path,
+            Verify::Sha1(library.sha1.clone().unwrap_or_default()),
+        ),
+        ProbeTarget {
+            endpoint: "client",
+            path: client.path().trim_start_matches('/').to_owned(),
+            verify: Verify::Prefix(b"PK\x03\x04"),
+            partial: true,
+        },
+    ])
+}
+
+/// Downloads a target from one mirror URL and verifies it.
+///
+/// # Errors
+/// - `anyhow::Error` if the download fails or times out
+/// - `anyhow::Error` if the content does not pass the verification
+fn probe_url(client: &Client, target: &ProbeTarget, mirror: &str) -> Result<Measurement> {
+    let url = mirror.to_owned() + &target.path;
+    let start = Instant::now();
+    let response = client
+        .get(&url)
+        .header(header::USER_AGENT, "github.com/funny233-github/MCLauncher")
+        .timeout(PROBE_TIMEOUT)
+        .send()?
+        .error_for_status()?;
+    let latency = start.elapsed();
+    let mut data = Vec::new();
+    if target.partial {
+        response.take(PARTIAL_PROBE_SIZE).read_to_end(&mut data)?;
+    } else {
+        let mut response = response;
+        response.read_to_end(&mut data)?;
+    }
+    let total = start.elapsed();
+    target.verify.check(&data)?;
+    Ok(Measurement {
+        latency,
+        total,
+        bytes: data.len(),
+    })
+}
+
+/// Probes every mirror of every endpoint.
+///
+/// Mirrors are probed one after another so they do not share bandwidth.
+///
+/// # Example
+/// ```no_run
+/// use gluon::config::ConfigHandler;
+/// use gluon::mirrorprobe::probe;
+///
+/// let config = ConfigHandler::read()?;
+/// for result in probe(config.config()) {
+///     println!("{} {}: {:?}", result.endpoint, result.mirror, result.outcome);
+/// }
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+#[must_use]
+pub fn probe(config: &RuntimeConfig) -> Vec<ProbeResult> {
+    let client = Client::new();
+    let mut results = Vec::new();
+    for target in probe_targets(config) {
+        println!("probing {}...", target.endpoint);
+        for (mirror, url) in candidates(config, target.endpoint) {
+            let outcome = probe_url(&client, &target, &url);
+            results.push(ProbeResult {
+                endpoint: target.endpoint,
+                mirror,
+                url,
+                outcome,
+            });
+        }
+    }
+    results
+}
+
+/// Returns the verified URLs of each probed endpoint, fastest first.
+///
+/// Endpoints without a verified mirror are left out.
+#[must_use]
+pub fn fastest_urls(results: &[ProbeResult]) -> BTreeMap<&'static str, MirrorUrls> {
+    let mut verified: Vec<(&ProbeResult, Duration)> = results
+        .iter()
+        .filter_map(|result| Some((result, result.outcome.as_ref().ok()?.total)))
+        .collect();
+    verified.sort_by_key(|(_, total)| *total);
+    let mut fastest: BTreeMap<&'static str, MirrorUrls> = BTreeMap::new();
+    for (result, _) in verified {
+        fastest
+            .entry(result.endpoint)
+            .and_modify(|urls| urls.push_fallback(&result.url))
+            .or_insert_with(|| result.url.as_str().into());
+    }
+    fastest
+}
+
+/// Verifies that failed mirrors are dropped and the others ordered by speed.
+#[test]
+fn test_fastest_urls() {
+    let result = |mirror: &str, millis: Option<u64>| ProbeResult {
+        endpoint: "assets",
+        mirror: mirror.to_owned(),
+        url: format!("https://{mirror}/"),
+        outcome: millis
+            .map(|millis| Measurement {
+                latency: Duration::from_millis(millis / 2),
+                total: Duration::from_millis(millis),
+                bytes: 1024,
+            })
+            .context("down"),
+    };
+    let results = [
+        result("slow", Some(900)),
+        result("down", None),
+        result("fast", Some(120)),
+    ];
+    let fastest = fastest_urls(&results);
+    assert_eq!(fastest["assets"].urls(), ["https://fast/", "https://slow/"]);
+    assert!(!fastest.contains_key("client"));
+
+    let sha1 = Verify::Sha1("0a0a9f2a6772942557ab5355d76af442f8f65e01".into());
+    assert!(sha1.check(b"Hello, World!").is_ok());
+    assert!(sha1.check(b"Hello, Mirror!").is_err());
+    assert!(Verify::Json.check(b"<html>").is_err());
+}
@@ -156,6 +156,7 @@ pub fn add(name: &str, version: Option<&String>, local: bool, config_only: bool)
         config_handler.add_mod_unlocal_blocking(name, version)?;
         format!("Add mod {name} successful")
     };
+    config_handler.commit()?;
     drop(config_handler);
 
     if !config_only {
@@ -188,6 +189,7 @@ pub fn add(name: &str, version: Option<&String>, local: bool, config_only: bool)
 pub fn remove(name: &str) -> Result<()> {
     let mut config_handler = ConfigHandler::read()?;
     config_handler.remove_mod(name)?;
+    config_handler.commit()?;
 
     println!("mod {name} removed");
     Ok(())
@@ -259,6 +261,7 @@ fn mod_installtasks(handle: &ConfigHandler) -> Result<VecDeque<InstallTask>> {
                 ),
                 message: format!("Mod {} installed", save_file.display()),
                 save_file,
+                fallback_urls: Vec::new(),
             })
         })
         .collect()
@@ -291,7 +294,31 @@ fn mod_installtasks(handle: &ConfigHandler) -> Result<VecDeque<InstallTask>> {
 /// - `anyhow::Error` if file system operations fail
 /// - `anyhow::Error` if SHA-1 verification fails
 pub fn install() -> Result<()> {
-    let mut config_handler = ConfigHandler::read()?;
+    install_for(&mut ConfigHandler::read()?)
+}
+
+/// Installs all configured mods of the given instance.
+///
+/// Behaves like `install`, but works on an already opened configuration
+/// instead of reading the current instance.
+///
+/// # Example
+/// ```no_run
+/// use gluon::config::{ConfigHandler, ConfigPaths};
+/// use gluon::modmanage::install_for;
+/// use std::path::Path;
+///
+/// let mut config = ConfigHandler::read_from_paths(ConfigPaths::from_root(Path::new("survival")))?;
+/// install_for(&mut config)?;
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+///
+/// # Errors
+/// - `anyhow::Error` if mod download fails
+/// - `anyhow::Error` if file system operations fail
+/// - `anyhow::Error` if SHA-1 verification fails
+/// - `anyhow::Error` if the updated configuration cannot be written
+pub fn install_for(config_handler: &mut ConfigHandler) -> Result<()> {
     if config_handler.locked_config().mods.is_none() || config_handler.config().mods.is_none() {
         return Ok(());
     }
@@ -311,9 +338,9 @@ pub fn install() -> Result<()> {
             })
             .collect(),
     );
-    let tasks = mod_installtasks(&config_handler)?;
-    TaskPool::from(tasks).install();
-    Ok(())
+    let tasks = mod_installtasks(config_handler)?;
+    TaskPool::from(tasks).install()?;
+    config_handler.commit()
 }
 
 /// Syncs all mods to their configured versions.
@@ -546,6 +573,10 @@ async fn sync_or_update(sync: bool) -> Result<()> {
             .buffer_unordered(10)
             .try_collect::<Vec<_>>()
             .await?;
+        config_handler
+            .write()
+            .map_err(|_| anyhow::anyhow!("config handler lock is poisoned"))?
+            .commit()?;
     }
 
     if sync {
@@ -589,7 +620,7 @@ fn clean_locked_config_mods() -> Result<()> {
         x.clone()
             .for_each(|(name, _)| handle.locked_config_mut().remove_mod(name));
     }
-    Ok(())
+    handle.commit()
 }
 
 /// Removes unused mod files from the mods directory.
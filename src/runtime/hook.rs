@@ -0,0 +1,187 @@
+//! Hook commands run around the game.
+//!
+//! Hooks are configured under `[hooks]` in `config.toml` and receive
+//! information about the instance through `GLUON_*` environment variables.
+
+use crate::config::{
+    ConfigHandler, MCLoader, ENV_GAME_DIR, ENV_JAVA_PATH, ENV_MAX_MEMORY_SIZE, ENV_PASSPHRASE,
+};
+use anyhow::{Context, Result};
+use std::process::Command;
+
+/// Environment variables of gluon that hooks do not inherit.
+const REMOVED_ENV: &[&str] = &[
+    ENV_PASSPHRASE,
+    ENV_JAVA_PATH,
+    ENV_MAX_MEMORY_SIZE,
+    ENV_GAME_DIR,
+];
+
+/// The moment a hook runs at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookEvent {
+    /// Before the game is launched, see `Hooks::pre_launch`.
+    PreLaunch,
+    /// After the game exited, see `Hooks::post_exit`.
+    PostExit,
+    /// After the game was installed, see `Hooks::post_install`.
+    PostInstall,
+}
+
+impl HookEvent {
+    /// Returns the name of the event, as used in `config.toml` and `GLUON_HOOK`.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            HookEvent::PreLaunch => "pre_launch",
+            HookEvent::PostExit => "post_exit",
+            HookEvent::PostInstall => "post_install",
+        }
+    }
+}
+
+/// Runs the hook commands configured for an event.
+///
+/// Commands run one after another through the system shell, in the game
+/// directory and with the output of gluon. Besides `extra_env`, they get these
+/// environment variables:
+///
+/// - `GLUON_HOOK`: the event name, e.g. `pre_launch`
+/// - `GLUON_INSTANCE_DIR`: absolute path of the instance, the directory
+///   containing `config.toml`
+/// - `GLUON_HOOK_GAME_DIR`: absolute path of the game directory
+/// - `GLUON_GAME_ROOT`: absolute path of the shared game root
+/// - `GLUON_GAME_VERSION` and `GLUON_VANILLA_VERSION`
+/// - `GLUON_LOADER` (`none`, `fabric` or `neoforge`) and `GLUON_LOADER_VERSION`
+///
+/// The instance stays locked while its hooks run, so a hook running `gluon`
+/// on the same instance fails with an "instance is busy" error.
+///
+/// # Example
+/// ```no_run
+/// use gluon::config::ConfigHandler;
+/// use gluon::runtime::{run_hooks, HookEvent};
+///
+/// let config = ConfigHandler::read()?;
+/// run_hooks(&config, HookEvent::PostExit, &[("GLUON_EXIT_CODE", "0".into())])?;
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+///
+/// # Errors
+/// - `anyhow::Error` if the instance, game directory or game root cannot be resolved
+/// - `anyhow::Error` if a command cannot be started or exits unsuccessfully;
+///   the remaining commands are not run
+pub fn run_hooks(
+    handle: &ConfigHandler,
+    event: HookEvent,
+    extra_env: &[(&str, String)],
+) -> Result<()> {
+    let hooks = &handle.config().hooks;
+    let commands = match event {
+        HookEvent::PreLaunch => &hooks.pre_launch,
+        HookEvent::PostExit => &hooks.post_exit,
+        HookEvent::PostInstall => &hooks.post_install,
+    };
+    if commands.is_empty() {
+        return Ok(());
+    }
+
+    let game_dir = handle.get_absolute_game_dir()?;
+    let (loader, loader_version) = match &handle.config().loader {
+        MCLoader::None => ("none", ""),
+        MCLoader::Fabric(version) => ("fabric", version.as_str()),
+        MCLoader::Neoforge(version) => ("neoforge", version.as_str()),
+    };
+    let env = [
+        ("GLUON_HOOK", event.name().to_owned()),
+        ("GLUON_INSTANCE_DIR", handle.get_absolute_instance_dir()?),
+        ("GLUON_HOOK_GAME_DIR", game_dir.clone()),
+        ("GLUON_GAME_ROOT", handle.get_absolute_game_root()?),
+        ("GLUON_GAME_VERSION", handle.config().game_version.clone()),
+        ("GLUON_VANILLA_VERSION", handle.config().vanilla.clone()),
+        ("GLUON_LOADER", loader.to_owned()),
+        ("GLUON_LOADER_VERSION", loader_version.to_owned()),
+    ];
+
+    for command in commands {
+        println!("running {} hook: {command}", event.name());
+        let status = shell(command)
+            .envs(
+                env.iter()
+                    .chain(extra_env)
+                    .map(|(key, value)| (*key, value)),
+            )
+            .current_dir(&game_dir)
+            .status()
+            .with_context(|| format!("failed to run {} hook '{command}'", event.name()))?;
+        if !status.success() {
+            return Err(anyhow::anyhow!(
+                "{} hook '{command}' failed with {status}",
+                event.name()
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Creates a command running `command` through the system shell.
+///
+/// The account store passphrase and the `gluon run` overrides are removed
+/// from its environment, so a hook running `gluon` on another instance does
+/// not inherit them.
+fn shell(command: &str) -> Command {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.args(["/C", command]);
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.args(["-c", command]);
+        shell
+    };
+    for key in REMOVED_ENV {
+        shell.env_remove(key);
+    }
+    shell
+}
+
+/// Verifies that hooks get the instance environment and stop on failure.
+#[cfg(unix)]
+#[test]
+fn test_run_hooks() {
+    let dir = std::env::temp_dir().join(format!("gluon-test-hooks-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let out = dir.join("hook.txt");
+    let mut handle = ConfigHandler::default();
+    handle.config_mut().loader = MCLoader::Fabric("0.16.14".into());
+    handle.config_mut().hooks.post_exit = vec![
+        format!(
+            "echo $GLUON_HOOK $GLUON_LOADER_VERSION $GLUON_EXIT_CODE > '{}'",
+            out.display()
+        ),
+        "exit 3".into(),
+        format!("echo unreachable > '{}'", out.display()),
+    ];
+
+    let result = run_hooks(
+        &handle,
+        HookEvent::PostExit,
+        &[("GLUON_EXIT_CODE", "0".into())],
+    );
+    assert!(result.is_err());
+    assert_eq!(
+        std::fs::read_to_string(&out).unwrap(),
+        "post_exit 0.16.14 0\n"
+    );
+    assert!(run_hooks(&handle, HookEvent::PreLaunch, &[]).is_ok());
+
+    let shell = shell("true");
+    let removed: Vec<_> = shell
+        .get_envs()
+        .filter(|(_, value)| value.is_none())
+        .map(|(key, _)| key.to_str().unwrap())
+        .collect();
+    assert_eq!(removed.len(), REMOVED_ENV.len());
+    assert!(REMOVED_ENV.iter().all(|key| removed.contains(key)));
+    let _ = std::fs::remove_dir_all(&dir);
+}
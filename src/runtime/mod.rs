@@ -7,6 +7,7 @@
 //! - **Process Spawning**: Creates a new Java process to run Minecraft
 //! - **Argument Generation**: Generates JVM and game launch arguments from configuration
 //! - **Output Streaming**: Captures and forwards game output to the console in real-time
+//! - **Hooks**: Runs the `pre_launch` and `post_exit` commands around the game
 //!
 //! ## Example
 //!
@@ -18,7 +19,11 @@
 //! gameruntime(&config).expect("Failed to launch Minecraft");
 //! ```
 
-use crate::config::ConfigHandler;
+mod hook;
+
+pub use hook::{run_hooks, HookEvent};
+
+use crate::config::{ConfigHandler, ENV_PASSPHRASE};
 use std::io;
 use std::process::{Command, Stdio};
 use std::thread;
@@ -27,21 +32,28 @@ use std::thread;
 ///
 /// Generates the appropriate launch arguments and spawns a new process
 /// to run Minecraft. Captures and forwards the game's stdout to the console.
+/// The `pre_launch` hooks run before the game starts and the `post_exit` hooks
+/// after it exited, with its exit code in `GLUON_EXIT_CODE`.
 ///
 /// # Errors
 /// Returns an error if:
+/// - A `pre_launch` hook fails, in which case the game is not launched
 /// - Launch arguments cannot be generated
 /// - Java process cannot be spawned
-/// - Output cannot be captured
+/// - Output cannot be captured or forwarded, after the `post_exit` hooks ran
+/// - A `post_exit` hook fails
 /// # Panics
 /// Panics if the stderr forwarding thread panics, or if joining the thread fails
 /// (e.g., due to a panic in the stderr copy loop). This is unlikely under normal operation
 /// but can occur if the system is under extreme memory pressure.
 pub fn gameruntime(handle: &ConfigHandler) -> anyhow::Result<()> {
+    run_hooks(handle, HookEvent::PreLaunch, &[])?;
     let args = handle.args_provider()?;
     let path = &handle.config().java_path;
+    // The game and its mods must not see the account store passphrase.
     let mut child = Command::new(path)
         .args(args)
+        .env_remove(ENV_PASSPHRASE)
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
         .current_dir(&handle.get_absolute_game_dir()?)
@@ -57,8 +69,21 @@ pub fn gameruntime(handle: &ConfigHandler) -> anyhow::Result<()> {
         .ok_or_else(|| anyhow::anyhow!("Failed to capture stdout"))?;
 
     let stderr_handle = thread::spawn(move || io::copy(&mut stderr, &mut io::stderr()));
-    io::copy(&mut stdout, &mut io::stdout())?;
-    stderr_handle.join().unwrap()?;
-    child.wait()?;
+    let stdout_copied = io::copy(&mut stdout, &mut io::stdout());
+    let stderr_copied = stderr_handle.join().unwrap();
+    let status = child.wait()?;
+    let exit_code = status
+        .code()
+        .map(|code| code.to_string())
+        .unwrap_or_default();
+    // The game has exited either way, so the hooks run even if forwarding its
+    // output failed.
+    run_hooks(
+        handle,
+        HookEvent::PostExit,
+        &[("GLUON_EXIT_CODE", exit_code)],
+    )?;
+    stdout_copied?;
+    stderr_copied?;
     Ok(())
 }
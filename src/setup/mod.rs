@@ -0,0 +1,315 @@
+//! Interactive instance setup.
+//!
+//! Walks the user through the choices a new instance needs before it can be
+//! installed and launched: a download mirror, a Minecraft version, a mod
+//! loader with its version and an account. The versions offered are fetched
+//! through the chosen mirror.
+
+use crate::config::{ConfigHandler, MCLoader, RuntimeConfig};
+use anyhow::{Context, Result};
+use dialoguer::theme::ColorfulTheme;
+use dialoguer::{Input, Select};
+use mc_api::{fabric, neoforge, official::VersionManifest, official::VersionType};
+use std::io::IsTerminal;
+use version_compare::Version;
+
+/// Number of entries shown at once in long version lists.
+const PAGE_SIZE: usize = 15;
+
+/// The account chosen during setup.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccountChoice {
+    /// Keep the account of the instance.
+    Keep,
+    /// Use an offline account with this name.
+    Offline(String),
+    /// Log in with a Microsoft account through the device code flow.
+    Microsoft,
+}
+
+/// The choices made during setup.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Setup {
+    /// Named mirror used for every endpoint, see `RuntimeConfig::mirror_names`.
+    pub mirror: String,
+    /// Minecraft version, e.g. `1.21.1`.
+    pub vanilla: String,
+    /// Mod loader and its version.
+    pub loader: MCLoader,
+    /// Account used to launch the game.
+    pub account: AccountChoice,
+}
+
+impl Setup {
+    /// Asks for every choice on the terminal.
+    ///
+    /// `config` provides the named mirrors to choose from. Minecraft and
+    /// loader versions are fetched through the chosen mirror.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use gluon::config::ConfigHandler;
+    /// use gluon::setup::Setup;
+    ///
+    /// let mut config = ConfigHandler::read()?;
+    /// let setup = Setup::prompt(config.config())?;
+    /// setup.apply(&mut config)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if stdin or stdout is not a terminal
+    /// - `anyhow::Error` if a prompt fails or is cancelled
+    /// - `anyhow::Error` if the version lists cannot be fetched from any mirror
+    /// - `anyhow::Error` if no loader version exists for the chosen Minecraft version
+    pub fn prompt(config: &RuntimeConfig) -> Result<Self> {
+        ensure_terminal()?;
+        let theme = ColorfulTheme::default();
+
+        let mirrors = config.mirror_names();
+        let mirror = mirrors[Select::with_theme(&theme)
+            .with_prompt("Download mirror")
+            .items(&mirrors)
+            .default(0)
+            .interact()?]
+        .clone();
+        let mut config = config.clone();
+        config.use_mirror(&mirror, &[])?;
+
+        let vanilla = prompt_vanilla(&theme, &config)?;
+        let loader = prompt_loader(&theme, &config, &vanilla)?;
+        let account = prompt_account(&theme)?;
+        Ok(Self {
+            mirror,
+            vanilla,
+            loader,
+            account,
+        })
+    }
+
+    /// Applies the choices to an instance and commits them.
+    ///
+    /// The game version is named after the Minecraft and loader versions, the
+    /// same way `gluon install <version> --fabric <loader>` names it. Choosing
+    /// a Microsoft account starts the device code login.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the mirror is not defined
+    /// - `anyhow::Error` if the Microsoft login fails
+    /// - `anyhow::Error` if the configuration cannot be written
+    pub fn apply(&self, handle: &mut ConfigHandler) -> Result<()> {
+        let config = handle.config_mut();
+        config.use_mirror(&self.mirror, &[])?;
+        config.vanilla.clone_from(&self.vanilla);
+        config.game_version = game_version_name(&self.vanilla, &self.loader);
+        config.loader = self.loader.clone();
+        match &self.account {
+            AccountChoice::Keep => {}
+            AccountChoice::Offline(name) => handle.add_offline_account(name),
+            AccountChoice::Microsoft => handle.add_microsoft_account()?,
+        }
+        handle.commit()
+    }
+}
+
+/// Checks that setup can prompt the user, i.e. stdin and stdout are a terminal.
+///
+/// # Errors
+/// - `anyhow::Error` if stdin or stdout is not a terminal
+pub fn ensure_terminal() -> Result<()> {
+    if std::io::stdin().is_terminal() && std::io::stdout().is_terminal() {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!(
+            "interactive setup needs a terminal, use 'gluon init' without --interactive"
+        ))
+    }
+}
+
+/// Returns the name of the version directory for a Minecraft version and loader.
+///
+/// # Example
+/// ```
+/// use gluon::config::MCLoader;
+/// use gluon::setup::game_version_name;
+///
+/// let loader = MCLoader::Fabric("0.16.5".into());
+/// assert_eq!(game_version_name("1.21.1", &loader), "1.21.1-fabric-0.16.5");
+/// assert_eq!(game_version_name("1.21.1", &MCLoader::None), "1.21.1");
+/// ```
+#[must_use]
+pub fn game_version_name(vanilla: &str, loader: &MCLoader) -> String {
+    match loader {
+        MCLoader::None => vanilla.to_owned(),
+        MCLoader::Fabric(v) => format!("{vanilla}-fabric-{v}"),
+        MCLoader::Neoforge(v) => format!("{vanilla}-neoforge-{v}"),
+    }
+}
+
+/// Returns the `NeoForge` versions built for a Minecraft version.
+///
+/// `NeoForge` versions start with the minor and patch version of Minecraft,
+/// e.g. `21.1.77` is built for `1.21.1`. Returns nothing if `vanilla` is not
+/// a version number.
+///
+/// # Example
+/// ```
+/// use gluon::setup::neoforge_versions;
+///
+/// let versions = vec!["20.4.237".to_owned(), "21.1.77".to_owned()];
+/// assert_eq!(neoforge_versions("1.21.1", versions), ["21.1.77"]);
+/// ```
+#[must_use]
+pub fn neoforge_versions(vanilla: &str, versions: Vec<String>) -> Vec<String> {
+    let Some(mc_version) = Version::from(vanilla) else {
+        return Vec::new();
+    };
+    versions
+        .into_iter()
+        .filter(|x| {
+            Version::from(x).is_some_and(|neoforge_version| {
+                mc_version.part(1) == neoforge_version.part(0)
+                    && mc_version.part(2) == neoforge_version.part(1)
+            })
+        })
+        .collect()
+}
+
+/// Asks for a Minecraft release, or a snapshot if the user wants one.
+fn prompt_vanilla(theme: &ColorfulTheme, config: &RuntimeConfig) -> Result<String> {
+    println!("fetching version manifest...");
+    let manifest = config
+        .mirror
+        .version_manifest
+        .try_each(VersionManifest::fetch)?;
+    let kind = Select::with_theme(theme)
+        .with_prompt("Version type")
+        .items(["release", "snapshot"])
+        .default(0)
+        .interact()?;
+    let kind = if kind == 0 {
+        VersionType::Release
+    } else {
+        VersionType::Snapshot
+    };
+    let versions = manifest.list(&kind);
+    let index = Select::with_theme(theme)
+        .with_prompt("Minecraft version")
+        .items(&versions)
+        .default(0)
+        .max_length(PAGE_SIZE)
+        .interact()?;
+    Ok(versions[index].clone())
+}
+
+/// Asks for a mod loader and one of its versions for the Minecraft version.
+fn prompt_loader(theme: &ColorfulTheme, config: &RuntimeConfig, vanilla: &str) -> Result<MCLoader> {
+    let loader = Select::with_theme(theme)
+        .with_prompt("Mod loader")
+        .items(["none", "fabric", "neoforge"])
+        .default(0)
+        .interact()?;
+    match loader {
+        1 => {
+            println!("fetching fabric loaders version...");
+            let loaders = config.mirror.fabric_meta.try_each(fabric::Loader::fetch)?;
+            let versions: Vec<&str> = loaders.iter().map(|x| x.version.as_str()).collect();
+            let index = Select::with_theme(theme)
+                .with_prompt("Fabric loader version")
+                .items(&versions)
+                .default(loaders.iter().position(|x| x.stable).unwrap_or(0))
+                .max_length(PAGE_SIZE)
+                .interact()?;
+            Ok(MCLoader::Fabric(versions[index].to_owned()))
+        }
+        2 => {
+            println!("fetching neoforge loaders version...");
+            let metadata = config
+                .mirror
+                .neoforge_neoforge
+                .try_each(neoforge::Loader::fetch)?;
+            let mut versions = neoforge_versions(vanilla, metadata.versioning.versions.version);
+            versions.reverse();
+            if versions.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "Cannot find a neoforge version for minecraft {vanilla}"
+                ));
+            }
+            let index = Select::with_theme(theme)
+                .with_prompt("NeoForge version")
+                .items(&versions)
+                .default(0)
+                .max_length(PAGE_SIZE)
+                .interact()?;
+            Ok(MCLoader::Neoforge(versions[index].clone()))
+        }
+        _ => Ok(MCLoader::None),
+    }
+}
+
+/// Asks for the account to launch the game with.
+fn prompt_account(theme: &ColorfulTheme) -> Result<AccountChoice> {
+    let account = Select::with_theme(theme)
+        .with_prompt("Account")
+        .items(["offline", "microsoft", "keep the current account"])
+        .default(0)
+        .interact()?;
+    match account {
+        0 => {
+            let name: String = Input::with_theme(theme)
+                .with_prompt("Player name")
+                .validate_with(|name: &String| check_player_name(name))
+                .interact_text()
+                .context("failed to read the player name")?;
+            Ok(AccountChoice::Offline(name))
+        }
+        1 => Ok(AccountChoice::Microsoft),
+        _ => Ok(AccountChoice::Keep),
+    }
+}
+
+/// Checks that a player name is accepted by vanilla servers.
+fn check_player_name(name: &str) -> Result<(), String> {
+    if (3..=16).contains(&name.len()) && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        Ok(())
+    } else {
+        Err("player names have 3 to 16 letters, digits or '_'".to_owned())
+    }
+}
+
+/// Verifies that the setup choices are written to a new instance.
+#[test]
+fn test_setup_apply() {
+    use crate::config::ConfigPaths;
+
+    assert!(check_player_name("Steve_01").is_ok());
+    assert!(check_player_name("St").is_err());
+    assert!(check_player_name("Steve!").is_err());
+
+    let root = std::env::temp_dir().join("gluon-test-setup");
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(&root).unwrap();
+    let paths = ConfigPaths::from_root(&root).with_account_store(&root.join("accounts.toml"));
+    ConfigHandler::init_for_paths(paths.clone()).unwrap();
+    let mut handle = ConfigHandler::read_from_paths(paths.clone()).unwrap();
+    let setup = Setup {
+        mirror: "bmclapi".into(),
+        vanilla: "1.21.1".into(),
+        loader: MCLoader::Neoforge("21.1.77".into()),
+        account: AccountChoice::Offline("Steve".into()),
+    };
+    setup.apply(&mut handle).unwrap();
+    drop(handle);
+
+    let handle = ConfigHandler::read_from_paths(paths).unwrap();
+    assert_eq!(handle.config().game_version, "1.21.1-neoforge-21.1.77");
+    assert_eq!(handle.config().loader, setup.loader);
+    assert_eq!(handle.user_account().user_name, "Steve");
+    assert!(handle
+        .config()
+        .mirror
+        .version_manifest
+        .primary()
+        .contains("bmclapi"));
+}
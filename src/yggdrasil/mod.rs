@@ -0,0 +1,343 @@
+//! Third-party Yggdrasil authentication with authlib-injector.
+//!
+//! Yggdrasil is the legacy Mojang authentication API. Self-hosted skin servers
+//! implement it under their own API root, and the game is pointed at them by
+//! the authlib-injector Java agent:
+//!
+//! ```text
+//! java -javaagent:authlib-injector.jar=https://skin.example.com/api/yggdrasil ...
+//! ```
+//!
+//! The API root may be given directly or as any URL answering with the
+//! `X-Authlib-Injector-API-Location` header, as described by the
+//! authlib-injector specification. The agent is downloaded once into the user
+//! data directory, see `ensure_authlib_injector`.
+
+use crate::config::user_data_dir;
+use anyhow::{Context, Result};
+use reqwest::blocking::{Client, Response};
+use reqwest::{StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Timeout of a single request to the authentication server.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Header pointing from a server's web page to its Yggdrasil API root.
+const API_LOCATION_HEADER: &str = "X-Authlib-Injector-API-Location";
+
+/// Metadata of the latest authlib-injector build.
+const AUTHLIB_INJECTOR_LATEST: &str = "https://authlib-injector.yushi.moe/artifact/latest.json";
+
+/// Game profile owned by a Yggdrasil user.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Profile {
+    /// Profile UUID without hyphens.
+    pub id: String,
+    /// Player name.
+    pub name: String,
+}
+
+/// Tokens and profiles returned by `authenticate` and `refresh`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Session {
+    /// Access token passed to the game.
+    pub access_token: String,
+    /// Client token the access token is bound to.
+    pub client_token: String,
+    /// Profiles of the user, only returned by `authenticate`.
+    #[serde(default)]
+    pub available_profiles: Vec<Profile>,
+    /// Profile bound to the access token, if any.
+    pub selected_profile: Option<Profile>,
+}
+
+/// Error body of a failed Yggdrasil request.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ErrorResponse {
+    error: String,
+    error_message: Option<String>,
+}
+
+/// Published authlib-injector build.
+#[derive(Deserialize)]
+struct Artifact {
+    version: String,
+    download_url: String,
+    checksums: Checksums,
+}
+
+/// Checksums of an authlib-injector build.
+#[derive(Deserialize)]
+struct Checksums {
+    sha256: String,
+}
+
+/// Client of a Yggdrasil authentication server.
+pub struct YggdrasilClient {
+    /// API root without a trailing slash.
+    api_root: String,
+    /// HTTP client.
+    client: Client,
+}
+
+impl YggdrasilClient {
+    /// Creates a client for the given API root.
+    ///
+    /// # Example
+    /// ```
+    /// use gluon::yggdrasil::YggdrasilClient;
+    /// let client = YggdrasilClient::new("https://skin.example.com/api/yggdrasil/");
+    /// assert_eq!(client.api_root(), "https://skin.example.com/api/yggdrasil");
+    /// ```
+    #[must_use]
+    pub fn new(api_root: &str) -> Self {
+        Self {
+            api_root: api_root.trim_end_matches('/').to_owned(),
+            client: Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Creates a client for a server given by its API root or web page.
+    ///
+    /// `https://` is assumed when `server` has no scheme. If the server
+    /// answers with the `X-Authlib-Injector-API-Location` header, the API
+    /// root it names is used, otherwise `server` itself.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use gluon::yggdrasil::YggdrasilClient;
+    /// let client = YggdrasilClient::discover("littleskin.cn")?;
+    /// println!("API root: {}", client.api_root());
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if `server` is not a valid URL
+    /// - `anyhow::Error` if the server cannot be reached
+    pub fn discover(server: &str) -> Result<Self> {
+        let url = server_url(server)?;
+        let probe = Self::new(url.as_str());
+        let response = probe
+            .client
+            .get(url.clone())
+            .send()
+            .with_context(|| format!("cannot reach the authentication server {url}"))?;
+        match response.headers().get(API_LOCATION_HEADER) {
+            Some(location) => {
+                let location = location
+                    .to_str()
+                    .context("invalid API location header of the authentication server")?;
+                Ok(Self::new(url.join(location)?.as_str()))
+            }
+            None => Ok(probe),
+        }
+    }
+
+    /// Returns the API root, which is also passed to authlib-injector.
+    #[must_use]
+    pub fn api_root(&self) -> &str {
+        &self.api_root
+    }
+
+    /// Logs in with a username, usually an email address, and a password.
+    ///
+    /// The returned access token is bound to `client_token`. Servers select a
+    /// profile right away only if the user owns a single one, otherwise bind
+    /// one of `Session::available_profiles` with `refresh`.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the server cannot be reached
+    /// - `anyhow::Error` if the credentials are rejected
+    pub fn authenticate(
+        &self,
+        username: &str,
+        password: &str,
+        client_token: &str,
+    ) -> Result<Session> {
+        let body = json!({
+            "agent": { "name": "Minecraft", "version": 1 },
+            "username": username,
+            "password": password,
+            "clientToken": client_token,
+            "requestUser": false,
+        });
+        Ok(self.post("authenticate", &body)?.json()?)
+    }
+
+    /// Exchanges an access token for a new one, invalidating the old token.
+    ///
+    /// With `profile`, the new token is bound to that profile, which is only
+    /// possible if the old token has none.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the server cannot be reached
+    /// - `anyhow::Error` if the tokens are rejected, e.g. after a password change
+    pub fn refresh(
+        &self,
+        access_token: &str,
+        client_token: &str,
+        profile: Option<&Profile>,
+    ) -> Result<Session> {
+        let mut body = json!({
+            "accessToken": access_token,
+            "clientToken": client_token,
+            "requestUser": false,
+        });
+        if let Some(profile) = profile {
+            body["selectedProfile"] = json!(profile);
+        }
+        Ok(self.post("refresh", &body)?.json()?)
+    }
+
+    /// Tells whether an access token can still be used to join servers.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the server cannot be reached or answers unexpectedly
+    pub fn validate(&self, access_token: &str, client_token: &str) -> Result<bool> {
+        let body = json!({ "accessToken": access_token, "clientToken": client_token });
+        let response = self.send("validate", &body)?;
+        if response.status() == StatusCode::FORBIDDEN {
+            return Ok(false);
+        }
+        check_response("validate", response)?;
+        Ok(true)
+    }
+
+    /// Invalidates an access token, logging the session out.
+    ///
+    /// # Errors
+    /// - `anyhow::Error` if the server cannot be reached or rejects the request
+    pub fn invalidate(&self, access_token: &str, client_token: &str) -> Result<()> {
+        let body = json!({ "accessToken": access_token, "clientToken": client_token });
+        self.post("invalidate", &body)?;
+        Ok(())
+    }
+
+    /// Posts to an `authserver` endpoint and checks the response status.
+    fn post(&self, endpoint: &str, body: &serde_json::Value) -> Result<Response> {
+        check_response(endpoint, self.send(endpoint, body)?)
+    }
+
+    /// Posts to an `authserver` endpoint.
+    fn send(&self, endpoint: &str, body: &serde_json::Value) -> Result<Response> {
+        self.client
+            .post(format!("{}/authserver/{endpoint}", self.api_root))
+            .json(body)
+            .send()
+            .with_context(|| format!("cannot reach the authentication server {}", self.api_root))
+    }
+}
+
+/// Fails with the server's error message if a request was rejected.
+fn check_response(endpoint: &str, response: Response) -> Result<Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let message = match response.json::<ErrorResponse>() {
+        Ok(ErrorResponse {
+            error_message: Some(message),
+            ..
+        }) => message,
+        Ok(ErrorResponse { error, .. }) => error,
+        Err(_) => status.to_string(),
+    };
+    Err(anyhow::anyhow!("Yggdrasil {endpoint} failed: {message}"))
+}
+
+/// Returns the URL of a server, assuming `https://` when it has no scheme.
+///
+/// # Errors
+/// - `anyhow::Error` if `server` is not a valid URL
+fn server_url(server: &str) -> Result<Url> {
+    let server = if server.contains("://") {
+        server.to_owned()
+    } else {
+        format!("https://{server}")
+    };
+    Url::parse(&server).with_context(|| format!("invalid authentication server URL '{server}'"))
+}
+
+/// Returns the path authlib-injector is downloaded to, in the user data directory.
+///
+/// # Errors
+/// - `anyhow::Error` if the user data directory cannot be determined
+pub fn authlib_injector_path() -> Result<PathBuf> {
+    Ok(user_data_dir()?.join("authlib-injector.jar"))
+}
+
+/// Downloads the latest authlib-injector unless it was downloaded before.
+///
+/// The download is checked against the SHA-256 checksum published with the
+/// build. Returns the path of the jar.
+///
+/// # Example
+/// ```no_run
+/// use gluon::yggdrasil::ensure_authlib_injector;
+/// let jar = ensure_authlib_injector()?;
+/// println!("-javaagent:{}=https://skin.example.com/api/yggdrasil", jar.display());
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+///
+/// # Errors
+/// - `anyhow::Error` if the user data directory cannot be determined or written
+/// - `anyhow::Error` if the build metadata or the jar cannot be downloaded
+/// - `anyhow::Error` if the jar does not match its checksum
+pub fn ensure_authlib_injector() -> Result<PathBuf> {
+    let path = authlib_injector_path()?;
+    if fs::exists(&path)? {
+        return Ok(path);
+    }
+    let client = Client::builder().timeout(REQUEST_TIMEOUT).build()?;
+    let artifact: Artifact = client
+        .get(AUTHLIB_INJECTOR_LATEST)
+        .send()
+        .and_then(Response::error_for_status)
+        .and_then(Response::json)
+        .context("failed to fetch the latest authlib-injector build")?;
+    let jar = client
+        .get(&artifact.download_url)
+        .send()
+        .and_then(Response::error_for_status)
+        .and_then(Response::bytes)
+        .context("failed to download authlib-injector")?;
+    if sha256::digest(jar.as_ref()) != artifact.checksums.sha256.to_lowercase() {
+        return Err(anyhow::anyhow!(
+            "authlib-injector {} does not match its checksum",
+            artifact.version
+        ));
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, jar)?;
+    println!("Downloaded authlib-injector {}", artifact.version);
+    Ok(path)
+}
+
+/// Verifies that server addresses without a scheme default to HTTPS and that
+/// relative API locations resolve against the server.
+#[test]
+fn test_server_url() {
+    let url = server_url("skin.example.com").unwrap();
+    assert_eq!(url.as_str(), "https://skin.example.com/");
+    assert_eq!(
+        url.join("/api/yggdrasil").unwrap().as_str(),
+        "https://skin.example.com/api/yggdrasil"
+    );
+    let url = server_url("http://localhost:8080/web/").unwrap();
+    assert_eq!(
+        url.join("api/yggdrasil/").unwrap().as_str(),
+        "http://localhost:8080/web/api/yggdrasil/"
+    );
+    assert!(server_url("https://").is_err());
+}